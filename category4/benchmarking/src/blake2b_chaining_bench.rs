@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use ark_std::{end_timer, start_timer};
+    use halo2_exp::{blake2b_initial_h, layout, Blake2fChip, Blake2fConfig, Blake2fInstructions, MAX_ROUND};
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error};
+    use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+    use halo2_proofs::poly::kzg::multiopen::ProverSHPLONK;
+    use halo2_proofs::{
+        halo2curves::bn256::{Bn256, Fr, G1Affine},
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    };
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use std::env::var;
+
+    use crate::constants::{PROOFGEN_PREFIX, SETUP_PREFIX};
+
+    const BLOCKS: usize = 8;
+    const MESSAGE_BYTES: usize = BLOCKS * 128;
+
+    // left for blinding factors at the end of the columns
+    const RESERVED_ROWS: usize = 16;
+
+    // BLAKE2b-512 of a message of constant words, every block after the first chained to the
+    // output of the one before or, without chaining, its state assigned and decomposed again
+    #[derive(Default)]
+    struct Blake2bCircuit<const CHAINED: bool>;
+
+    impl<const CHAINED: bool> Circuit<Fr> for Blake2bCircuit<CHAINED> {
+        type Config = Blake2fConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Blake2fChip::configure(meta)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            let chip = Blake2fChip::construct(config);
+            let iv = chip.initialize(&mut layouter.namespace(|| "load spread table"))?;
+
+            let message = (0..MESSAGE_BYTES / 8)
+                .map(|i| chip.assign_constant(&mut layouter, i as u64))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut state = chip.initial_state(&mut layouter, blake2b_initial_h(64))?;
+            for (i, m) in message.chunks(16).enumerate() {
+                let m = m.to_vec().try_into().unwrap();
+                let t = [((i + 1) * 128) as u64, 0];
+                let last = i == BLOCKS - 1;
+
+                state = if CHAINED {
+                    chip.compress_chained(&mut layouter, &iv, &state, m, t, last, false, MAX_ROUND as u64)?
+                } else {
+                    chip.compress_node(&mut layouter, &iv, &state, m, t, last, false, MAX_ROUND as u64)?
+                };
+            }
+
+            Ok(())
+        }
+    }
+
+    // the rows of the compression regions and of the whole circuit, as laid out
+    fn measure<const CHAINED: bool>() -> (usize, usize) {
+        let layout = layout::measure(&Blake2bCircuit::<CHAINED>).expect("synthesis should not fail");
+
+        let compressions = layout
+            .regions("compress")
+            .chain(layout.regions("compress chained"))
+            .map(|region| region.rows)
+            .sum();
+
+        (compressions, layout.rows)
+    }
+
+    fn prove<const CHAINED: bool>(benchmark_id: &str, degree: u32) {
+        let circuit = Blake2bCircuit::<CHAINED>;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let setup_message = format!("{} {} with degree = {}", benchmark_id, SETUP_PREFIX, degree);
+        let start1 = start_timer!(|| setup_message);
+        let general_params = ParamsKZG::<Bn256>::setup(degree, &mut rng);
+        let vk = keygen_vk(&general_params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&general_params, vk, &circuit).expect("keygen_pk should not fail");
+        end_timer!(start1);
+
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+
+        let proof_message = format!("{} {} with degree = {}", benchmark_id, PROOFGEN_PREFIX, degree);
+        let start2 = start_timer!(|| proof_message);
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            XorShiftRng,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            Blake2bCircuit<CHAINED>,
+        >(&general_params, &pk, &[circuit], &[&[]], rng, &mut transcript)
+        .expect("proof generation should not fail");
+        end_timer!(start2);
+    }
+
+    #[test]
+    fn bench_blake2b_chaining_rows() {
+        const BENCHMARK_ID: &str = "Chained BLAKE2b Circuit";
+
+        let (chained, chained_rows) = measure::<true>();
+        let (unchained, unchained_rows) = measure::<false>();
+        let min_degree = (unchained_rows.max(chained_rows) + RESERVED_ROWS).next_power_of_two().trailing_zeros();
+        println!(
+            "{} of {} blocks: compression regions of {} rows, {} without chaining ({} saved), {} rows in total, {} without chaining, degree >= {}",
+            BENCHMARK_ID,
+            BLOCKS,
+            chained,
+            unchained,
+            unchained - chained,
+            chained_rows,
+            unchained_rows,
+            min_degree
+        );
+
+        let degree: u32 = var("DEGREE")
+            .expect("No DEGREE env var was provided")
+            .parse()
+            .expect("Cannot parse DEGREE env var as u32");
+        assert!(degree >= min_degree);
+
+        prove::<true>(BENCHMARK_ID, degree);
+        prove::<false>("Unchained BLAKE2b Circuit", degree);
+    }
+}
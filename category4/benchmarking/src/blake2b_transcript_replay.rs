@@ -0,0 +1,211 @@
+#[cfg(test)]
+mod tests {
+    use blake2f_circuit::dev::{Blake2fTestCircuit, INPUTS_OUTPUTS};
+    use halo2_exp::{Blake2bTranscript, TranscriptChip, TranscriptConfig, TranscriptInstructions, REPR_BYTES};
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem, Error,
+        Instance,
+    };
+    use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG, ParamsVerifierKZG};
+    use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+    use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+    use halo2_proofs::{
+        arithmetic::CurveAffine,
+        halo2curves::{
+            bn256::{Bn256, Fr, G1Affine},
+            group::ff::PrimeField,
+        },
+        poly::commitment::ParamsProver,
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, Transcript, TranscriptRead,
+            TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use std::io;
+
+    // degree of the benchmark proof
+    const PROOF_DEGREE: u32 = 17;
+    // one BLAKE2f compression per transcript block and per challenge
+    const REPLAY_DEGREE: u32 = 18;
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Point(G1Affine),
+        Scalar(Fr),
+        Challenge(Fr),
+    }
+
+    // Blake2bRead logging everything absorbed and squeezed by the verifier
+    struct RecordingTranscript<T> {
+        inner: T,
+        ops: Vec<Op>,
+    }
+
+    impl<T: TranscriptRead<G1Affine, Challenge255<G1Affine>>> Transcript<G1Affine, Challenge255<G1Affine>>
+        for RecordingTranscript<T>
+    {
+        fn squeeze_challenge(&mut self) -> Challenge255<G1Affine> {
+            let challenge = self.inner.squeeze_challenge();
+            self.ops.push(Op::Challenge(challenge.get_scalar()));
+            challenge
+        }
+
+        fn common_point(&mut self, point: G1Affine) -> io::Result<()> {
+            self.ops.push(Op::Point(point));
+            self.inner.common_point(point)
+        }
+
+        fn common_scalar(&mut self, scalar: Fr) -> io::Result<()> {
+            self.ops.push(Op::Scalar(scalar));
+            self.inner.common_scalar(scalar)
+        }
+    }
+
+    impl<T: TranscriptRead<G1Affine, Challenge255<G1Affine>>> TranscriptRead<G1Affine, Challenge255<G1Affine>>
+        for RecordingTranscript<T>
+    {
+        fn read_point(&mut self) -> io::Result<G1Affine> {
+            let point = self.inner.read_point()?;
+            self.ops.push(Op::Point(point));
+            Ok(point)
+        }
+
+        fn read_scalar(&mut self) -> io::Result<Fr> {
+            let scalar = self.inner.read_scalar()?;
+            self.ops.push(Op::Scalar(scalar));
+            Ok(scalar)
+        }
+    }
+
+    fn record_proof_transcript() -> Vec<Op> {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        let circuit: Blake2fTestCircuit<Fr> = Blake2fTestCircuit::new(inputs, outputs);
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let general_params = ParamsKZG::<Bn256>::setup(PROOF_DEGREE, &mut rng);
+        let verifier_params: ParamsVerifierKZG<Bn256> = general_params.verifier_params().clone();
+
+        let vk = keygen_vk(&general_params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&general_params, vk, &circuit).expect("keygen_pk should not fail");
+
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            XorShiftRng,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            Blake2fTestCircuit<Fr>,
+        >(&general_params, &pk, &[circuit], &[&[]], rng, &mut transcript)
+        .expect("proof generation should not fail");
+        let proof = transcript.finalize();
+
+        let mut transcript = RecordingTranscript {
+            inner: Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]),
+            ops: vec![],
+        };
+        let strategy = SingleStrategy::new(&general_params);
+        verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            RecordingTranscript<Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>>,
+            SingleStrategy<'_, Bn256>,
+        >(&verifier_params, pk.get_vk(), strategy, &[&[]], &mut transcript)
+        .expect("failed to verify bench circuit");
+
+        transcript.ops
+    }
+
+    #[derive(Default)]
+    struct ReplayCircuit {
+        ops: Vec<Op>,
+    }
+
+    impl Circuit<Fr> for ReplayCircuit {
+        type Config = (TranscriptConfig, Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { ops: self.ops.clone() }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let scalars = meta.advice_column();
+            let instance = meta.instance_column();
+
+            meta.enable_equality(scalars);
+            meta.enable_equality(instance);
+
+            (TranscriptChip::configure(meta), scalars, instance)
+        }
+
+        fn synthesize(
+            &self,
+            (config, scalars, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = TranscriptChip::construct(config);
+            let mut transcript = Blake2bTranscript::new(chip.clone(), layouter.namespace(|| "load tables"))?;
+
+            let mut challenges = 0;
+            for op in self.ops.iter() {
+                match op {
+                    Op::Point(point) => {
+                        // like `Blake2bWrite::common_point`, the identity has no encoding
+                        let coordinates: Option<_> = point.coordinates().into();
+                        let coordinates = coordinates.ok_or(Error::Synthesis)?;
+                        let x = coordinates.x().to_repr().map(Value::known);
+                        let y = coordinates.y().to_repr().map(Value::known);
+
+                        let x: [_; REPR_BYTES] = chip.assign_bytes(&mut layouter, &x)?.try_into().unwrap();
+                        let y: [_; REPR_BYTES] = chip.assign_bytes(&mut layouter, &y)?.try_into().unwrap();
+
+                        transcript.common_point(&mut layouter, &x, &y)?;
+                    }
+                    Op::Scalar(scalar) => {
+                        let scalar = layouter.assign_region(
+                            || "scalar",
+                            |mut region| region.assign_advice(|| "scalar", scalars, 0, || Value::known(*scalar)),
+                        )?;
+
+                        transcript.common_scalar(&mut layouter, &scalar)?;
+                    }
+                    Op::Challenge(_) => {
+                        let challenge = transcript.squeeze_challenge(&mut layouter)?;
+                        layouter.constrain_instance(challenge.cell(), instance, challenges)?;
+                        challenges += 1;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn replay_blake2f_proof_transcript() {
+        let ops = record_proof_transcript();
+
+        let challenges: Vec<Fr> = ops
+            .iter()
+            .filter_map(|op| match op {
+                Op::Challenge(challenge) => Some(*challenge),
+                _ => None,
+            })
+            .collect();
+        assert!(!challenges.is_empty());
+
+        let circuit = ReplayCircuit { ops };
+        let prover = MockProver::run(REPLAY_DEGREE, &circuit, vec![challenges]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
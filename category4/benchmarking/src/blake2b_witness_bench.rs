@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use ark_std::{end_timer, start_timer};
+    use halo2_exp::{Blake2b, Blake2fChip, Blake2fConfig, Blake2fInstructions};
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use std::env::var;
+
+    use crate::constants::WITNESS_PREFIX;
+
+    // left for blinding factors at the end of the columns
+    const RESERVED_ROWS: usize = 16;
+
+    // BLAKE2b-512 of a message of `blocks` blocks of constant words
+    struct Blake2bCircuit {
+        blocks: usize,
+    }
+
+    impl Circuit<Fr> for Blake2bCircuit {
+        type Config = Blake2fConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { blocks: self.blocks }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Blake2fChip::configure(meta)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            let chip = Blake2fChip::construct(config);
+            let blake2b = Blake2b::new(chip.clone(), layouter.namespace(|| "load spread table"))?;
+
+            let message_bytes = self.blocks * 128;
+            let message = (0..message_bytes / 8)
+                .map(|i| chip.assign_constant(&mut layouter, i as u64))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            blake2b.hash(&mut layouter, &message, message_bytes, 64)?;
+
+            Ok(())
+        }
+    }
+
+    // Witness generation of as many chained compressions as fit in 2^DEGREE rows. Every xor
+    // of every round goes through the spread helpers, so this is where they show.
+    #[test]
+    fn bench_blake2b_witness() {
+        const BENCHMARK_ID: &str = "BLAKE2b Circuit";
+
+        let degree: u32 = var("DEGREE")
+            .expect("No DEGREE env var was provided")
+            .parse()
+            .expect("Cannot parse DEGREE env var as u32");
+
+        // the message words, the zero word and the initial state take a row each
+        let rows = |blocks: usize| {
+            Blake2fChip::ROWS_PER_COMPRESSION
+                + (blocks - 1) * Blake2fChip::ROWS_PER_CHAINED_COMPRESSION
+                + blocks * 16
+                + 1
+                + 8
+        };
+        let blocks = (1..).take_while(|blocks| rows(*blocks) + RESERVED_ROWS <= 1 << degree).last().expect("degree too small for a block");
+
+        let witness_message = format!("{} {} of {} blocks with degree = {}", BENCHMARK_ID, WITNESS_PREFIX, blocks, degree);
+        let start = start_timer!(|| witness_message);
+        let prover = MockProver::<Fr>::run(degree, &Blake2bCircuit { blocks }, vec![]).expect("synthesis should not fail");
+        end_timer!(start);
+
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -15,7 +15,7 @@ mod tests {
     };
     use rand::SeedableRng;
     use rand_xorshift::XorShiftRng;
-    use std::{env::var, marker::PhantomData};
+    use std::env::var;
 
     use crate::constants::{PROOFGEN_PREFIX, PROOFVER_PREFIX, SETUP_PREFIX};
 
@@ -31,11 +31,7 @@ mod tests {
 
         // Create BLAKE2F circuit with some test vectors.
         let (inputs, outputs) = INPUTS_OUTPUTS.clone();
-        let circuit: Blake2fTestCircuit<Fr> = Blake2fTestCircuit {
-            inputs,
-            outputs,
-            _marker: PhantomData,
-        };
+        let circuit: Blake2fTestCircuit<Fr> = Blake2fTestCircuit::new(inputs, outputs);
 
         // Initialize the polynomial commitment parameters.
         let mut rng = XorShiftRng::from_seed([
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use ark_std::{end_timer, start_timer};
+    use halo2_exp::{Blake2b, Blake2fChip, Blake2fConfig, Blake2fInstructions};
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error};
+    use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+    use halo2_proofs::poly::kzg::multiopen::ProverSHPLONK;
+    use halo2_proofs::{
+        halo2curves::bn256::{Bn256, Fr, G1Affine},
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    };
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use std::env::var;
+
+    use crate::constants::{PROOFGEN_PREFIX, SETUP_PREFIX};
+
+    const MESSAGE_BYTES: usize = 4 * 128;
+
+    // BLAKE2b-512 of a 4-block message, with `LANES` lookup pairs side by side
+    #[derive(Default)]
+    struct Blake2bCircuit<const LANES: usize>;
+
+    impl<const LANES: usize> Circuit<Fr> for Blake2bCircuit<LANES> {
+        type Config = Blake2fConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Blake2fChip::configure_with_lanes(meta, LANES)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            let chip = Blake2fChip::construct(config);
+            let blake2b = Blake2b::new(chip.clone(), layouter.namespace(|| "load spread table"))?;
+
+            let message = (0..MESSAGE_BYTES / 8)
+                .map(|i| chip.assign_constant(&mut layouter, i as u64))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            blake2b.hash(&mut layouter, &message, MESSAGE_BYTES, 64)?;
+
+            Ok(())
+        }
+    }
+
+    fn bench<const LANES: usize>(benchmark_id: &str, degree: u32) {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let chip = Blake2fChip::construct(Blake2fChip::configure_with_lanes(&mut meta, LANES));
+        println!(
+            "{} takes {} rows per compression, {} chained, over {} advice columns",
+            benchmark_id,
+            chip.rows_per_compression(),
+            chip.rows_per_chained_compression(),
+            meta.num_advice_columns()
+        );
+
+        let circuit = Blake2bCircuit::<LANES>;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let setup_message = format!("{} {} with degree = {}", benchmark_id, SETUP_PREFIX, degree);
+        let start1 = start_timer!(|| setup_message);
+        let general_params = ParamsKZG::<Bn256>::setup(degree, &mut rng);
+        let vk = keygen_vk(&general_params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&general_params, vk, &circuit).expect("keygen_pk should not fail");
+        end_timer!(start1);
+
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+
+        let proof_message = format!("{} {} with degree = {}", benchmark_id, PROOFGEN_PREFIX, degree);
+        let start2 = start_timer!(|| proof_message);
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            XorShiftRng,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            Blake2bCircuit<LANES>,
+        >(&general_params, &pk, &[circuit], &[&[]], rng, &mut transcript)
+        .expect("proof generation should not fail");
+        end_timer!(start2);
+    }
+
+    #[test]
+    fn bench_blake2f_lanes() {
+        let degree: u32 = var("DEGREE")
+            .expect("No DEGREE env var was provided")
+            .parse()
+            .expect("Cannot parse DEGREE env var as u32");
+
+        bench::<1>("BLAKE2b Circuit (1 lookup pair)", degree);
+        bench::<2>("BLAKE2b Circuit (2 lookup pairs)", degree);
+        bench::<4>("BLAKE2b Circuit (4 lookup pairs)", degree);
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod tests {
+    use ark_std::{end_timer, start_timer};
+    use halo2_exp::{blake2b_initial_h, native, Blake2fChip, Blake2fConfig, Blake2fInstructions, Blake2fTrace, MAX_ROUND};
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use rayon::prelude::*;
+    use std::env::var;
+
+    use crate::constants::WITNESS_PREFIX;
+
+    // left for blinding factors at the end of the columns
+    const RESERVED_ROWS: usize = 16;
+
+    // the words of block `block` of the message
+    fn block(block: usize) -> [u64; 16] {
+        std::array::from_fn(|i| (block * 16 + i) as u64)
+    }
+
+    // h, m, the counter and the final flag of every compression of a BLAKE2b-512 hash of `blocks`
+    // blocks, chaining h natively
+    fn inputs(blocks: usize) -> Vec<([u64; 8], [u64; 16], u128, bool)> {
+        let mut h = blake2b_initial_h(64);
+        (0..blocks)
+            .map(|i| {
+                let (m, t, f) = (block(i), ((i + 1) * 128) as u128, i + 1 == blocks);
+                let input = (h, m, t, f);
+                native::compress(&mut h, &m, t, f, false);
+                input
+            })
+            .collect()
+    }
+
+    fn trace(&(h, m, t, f): &([u64; 8], [u64; 16], u128, bool)) -> Blake2fTrace {
+        Blake2fTrace::new(h, m, [t as u64, (t >> 64) as u64], if f { u64::MAX } else { 0 }, MAX_ROUND)
+    }
+
+    // the compressions of the hash, each copying its trace into the region
+    struct TraceCircuit {
+        blocks: usize,
+        traces: Vec<Blake2fTrace>,
+    }
+
+    impl Circuit<Fr> for TraceCircuit {
+        type Config = Blake2fConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { blocks: self.blocks, traces: vec![] }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Blake2fChip::configure(meta)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            let chip = Blake2fChip::construct(config);
+            let iv = chip.initialize(&mut layouter)?;
+
+            let mut h = chip.initial_state(&mut layouter, blake2b_initial_h(64))?;
+            for i in 0..self.blocks {
+                let m = block(i)
+                    .iter()
+                    .map(|word| chip.assign_constant(&mut layouter, *word))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let trace = self.traces.get(i).cloned().map_or(Value::unknown(), Value::known);
+
+                let t = [((i + 1) * 128) as u64, 0];
+                h = chip.compress_with_trace(&mut layouter, &iv, &h, m.try_into().unwrap(), t, i + 1 == self.blocks, false, MAX_ROUND as u64, trace)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    // The traces of as many chained compressions as fit in 2^DEGREE rows, computed one after the
+    // other and in parallel with rayon, then copied into the regions.
+    #[test]
+    fn bench_blake2f_trace() {
+        const BENCHMARK_ID: &str = "BLAKE2f traces";
+
+        let degree: u32 = var("DEGREE")
+            .expect("No DEGREE env var was provided")
+            .parse()
+            .expect("Cannot parse DEGREE env var as u32");
+
+        // the message words and the initial state take a row each
+        let rows = |blocks: usize| {
+            Blake2fChip::ROWS_PER_COMPRESSION + (blocks - 1) * Blake2fChip::ROWS_PER_CHAINED_COMPRESSION + blocks * 16 + 8
+        };
+        let blocks = (1..).take_while(|blocks| rows(*blocks) + RESERVED_ROWS <= 1 << degree).last().expect("degree too small for a block");
+        let inputs = inputs(blocks);
+
+        let start = start_timer!(|| format!("{} {} sequential of {} blocks", BENCHMARK_ID, WITNESS_PREFIX, blocks));
+        let sequential = inputs.iter().map(trace).collect::<Vec<_>>();
+        end_timer!(start);
+
+        let start = start_timer!(|| format!("{} {} rayon of {} blocks", BENCHMARK_ID, WITNESS_PREFIX, blocks));
+        let traces = inputs.par_iter().map(trace).collect::<Vec<_>>();
+        end_timer!(start);
+
+        assert_eq!(traces, sequential);
+
+        let start = start_timer!(|| format!("{} {} assignment with degree = {}", BENCHMARK_ID, WITNESS_PREFIX, degree));
+        let prover = MockProver::<Fr>::run(degree, &TraceCircuit { blocks, traces }, vec![]).expect("synthesis should not fail");
+        end_timer!(start);
+
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
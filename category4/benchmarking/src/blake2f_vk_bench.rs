@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use ark_std::{end_timer, start_timer};
+    use halo2_exp::{Blake2b, Blake2fChip, Blake2fConfig, Blake2fInstructions};
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error};
+    use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+    use halo2_proofs::poly::kzg::multiopen::ProverSHPLONK;
+    use halo2_proofs::{
+        halo2curves::bn256::{Bn256, Fr, G1Affine},
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+        SerdeFormat,
+    };
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use std::env::var;
+
+    use crate::constants::{PROOFGEN_PREFIX, SETUP_PREFIX};
+
+    const MESSAGE_BYTES: usize = 4 * 128;
+
+    // BLAKE2b-512 of a 4-block message, in the layout with or without the `round` columns
+    #[derive(Default)]
+    struct Blake2bCircuit<const FIXED_ROUNDS: bool>;
+
+    impl<const FIXED_ROUNDS: bool> Circuit<Fr> for Blake2bCircuit<FIXED_ROUNDS> {
+        type Config = Blake2fConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            configure(meta, FIXED_ROUNDS)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            let chip = Blake2fChip::construct(config);
+            let blake2b = Blake2b::new(chip.clone(), layouter.namespace(|| "load spread table"))?;
+
+            let message = (0..MESSAGE_BYTES / 8)
+                .map(|i| chip.assign_constant(&mut layouter, i as u64))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            blake2b.hash(&mut layouter, &message, MESSAGE_BYTES, 64)?;
+
+            Ok(())
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>, fixed_rounds: bool) -> Blake2fConfig {
+        if fixed_rounds {
+            Blake2fChip::configure_fixed_rounds(meta)
+        } else {
+            Blake2fChip::configure(meta)
+        }
+    }
+
+    fn bench<const FIXED_ROUNDS: bool>(benchmark_id: &str, degree: u32) {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        configure(&mut meta, FIXED_ROUNDS);
+        println!(
+            "{} has {} selectors and {} fixed columns, max gate degree {}",
+            benchmark_id,
+            meta.num_selectors(),
+            meta.num_fixed_columns(),
+            meta.degree()
+        );
+
+        let circuit = Blake2bCircuit::<FIXED_ROUNDS>;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let setup_message = format!("{} {} with degree = {}", benchmark_id, SETUP_PREFIX, degree);
+        let start1 = start_timer!(|| setup_message);
+        let general_params = ParamsKZG::<Bn256>::setup(degree, &mut rng);
+        let vk = keygen_vk(&general_params, &circuit).expect("keygen_vk should not fail");
+        println!(
+            "{} verifying key: {} fixed commitments, {} bytes",
+            benchmark_id,
+            vk.fixed_commitments().len(),
+            vk.to_bytes(SerdeFormat::RawBytes).len()
+        );
+        let pk = keygen_pk(&general_params, vk, &circuit).expect("keygen_pk should not fail");
+        end_timer!(start1);
+
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+
+        let proof_message = format!("{} {} with degree = {}", benchmark_id, PROOFGEN_PREFIX, degree);
+        let start2 = start_timer!(|| proof_message);
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            XorShiftRng,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            Blake2bCircuit<FIXED_ROUNDS>,
+        >(&general_params, &pk, &[circuit], &[&[]], rng, &mut transcript)
+        .expect("proof generation should not fail");
+        end_timer!(start2);
+    }
+
+    #[test]
+    fn bench_blake2f_vk() {
+        let degree: u32 = var("DEGREE")
+            .expect("No DEGREE env var was provided")
+            .parse()
+            .expect("Cannot parse DEGREE env var as u32");
+
+        bench::<false>("BLAKE2b Circuit", degree);
+        bench::<true>("BLAKE2b Circuit (fixed rounds)", degree);
+    }
+}
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use ark_std::{end_timer, start_timer};
+    use halo2_exp::{layout, Equihash, EquihashChip, EquihashConfig};
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_proofs::plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error};
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use std::env::var;
+
+    use crate::constants::SETUP_PREFIX;
+
+    // Zcash mainnet parameters
+    const N: usize = 200;
+    const K: usize = 9;
+
+    type Chip = EquihashChip<N, K>;
+
+    // left for blinding factors at the end of the columns
+    const RESERVED_ROWS: usize = 16;
+
+    // The layout does not depend on the witness, so it is measured without one.
+    #[derive(Default)]
+    struct EquihashCircuit;
+
+    impl Circuit<Fr> for EquihashCircuit {
+        type Config = EquihashConfig<N, K>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Chip::configure(meta)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            let equihash = Equihash::new(Chip::construct(config), layouter.namespace(|| "load spread table"))?;
+            equihash.verify(&mut layouter, &[Value::unknown(); 1 << K])
+        }
+    }
+
+    #[test]
+    fn bench_equihash_rows() {
+        const BENCHMARK_ID: &str = "Equihash (200, 9) Solution Circuit";
+
+        let layout = layout::measure(&EquihashCircuit).expect("synthesis should not fail");
+        let min_degree = layout.min_k(RESERVED_ROWS);
+        println!(
+            "{} uses {} rows in {} regions ({} BLAKE2f compressions), degree >= {}",
+            BENCHMARK_ID,
+            layout.rows,
+            layout.regions.len(),
+            Chip::INDICES + 1,
+            min_degree
+        );
+
+        let degree: u32 = var("DEGREE")
+            .expect("No DEGREE env var was provided")
+            .parse()
+            .expect("Cannot parse DEGREE env var as u32");
+        assert!(degree >= min_degree);
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let setup_message = format!("{} {} with degree = {}", BENCHMARK_ID, SETUP_PREFIX, degree);
+        let start = start_timer!(|| setup_message);
+        let general_params = ParamsKZG::<Bn256>::setup(degree, &mut rng);
+        let vk = keygen_vk(&general_params, &EquihashCircuit).expect("keygen_vk should not fail");
+        let _pk = keygen_pk(&general_params, vk, &EquihashCircuit).expect("keygen_pk should not fail");
+        end_timer!(start);
+    }
+}
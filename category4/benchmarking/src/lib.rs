@@ -1,5 +1,29 @@
 #[cfg(test)]
 pub mod blake2f_circuit_bench;
 
+#[cfg(test)]
+pub mod blake2b_transcript_replay;
+
+#[cfg(test)]
+pub mod equihash_bench;
+
+#[cfg(test)]
+pub mod blake2b_chaining_bench;
+
+#[cfg(test)]
+pub mod blake2f_fixed_rounds_bench;
+
+#[cfg(test)]
+pub mod blake2f_lanes_bench;
+
+#[cfg(test)]
+pub mod blake2f_vk_bench;
+
+#[cfg(test)]
+pub mod blake2b_witness_bench;
+
+#[cfg(test)]
+pub mod blake2f_trace_bench;
+
 #[cfg(test)]
 mod constants;
@@ -0,0 +1,311 @@
+// A soundness harness: the circuit is laid out once into `layout::Recorder` to list its advice
+// cells and gates, then proved once per mutation, with one cell plus one or with a pair of cells
+// of a gate moved together. `MockProver::verify` must reject every mutation, a mutation it
+// accepts is a cell the constraints do not pin.
+//
+// The mutation happens below the chips, in an `Assignment` wrapping the one of the prover,
+// so every advice cell can be tampered with: values, carries, limbs, xor slots,
+// round and s_round cells and copies of m alike. A hook on `CompressionConfig` or
+// `SchedulerConfig` would only reach the cells those configs assign, not the IV, the copies out
+// of the state or the cells of the caller, and would put a test branch in every assignment of
+// the gadget. `FloorPlanner::synthesize` has no `self` and `Circuit::FloorPlanner` is a type,
+// so the mutation reaches the floor planner through `TAMPERING`, thread local so tests running
+// in parallel do not see each other's.
+
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, marker::PhantomData};
+
+use halo2_exp::layout;
+use halo2_proofs::{
+    circuit::{FloorPlanner, Layouter, Value},
+    dev::MockProver,
+    halo2curves::{bn256::Fr, group::ff::Field},
+    plonk::{
+        Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error, Fixed, Instance,
+        Selector,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdviceCell {
+    pub region: String,
+    pub annotation: String,
+    pub column: usize,
+    pub row: usize,
+}
+
+impl AdviceCell {
+    fn key(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+}
+
+impl std::fmt::Display for AdviceCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} (advice {}, row {})", self.region, self.annotation, self.column, self.row)
+    }
+}
+
+// Two cells of a gate polynomial: `cells[0]` plus one and `cells[1]` plus `delta`, which keeps
+// the polynomial at its value. Another constraint has to reject it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PairedMutation {
+    pub gate: String,
+    pub cells: [AdviceCell; 2],
+    pub delta: Fr,
+}
+
+impl std::fmt::Display for PairedMutation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} plus one, {} plus {:?}", self.gate, self.cells[0], self.cells[1], self.delta)
+    }
+}
+
+thread_local! {
+    // the value added to each mutated advice cell, by (column, row)
+    static TAMPERING: RefCell<HashMap<(usize, usize), Fr>> = RefCell::new(HashMap::new());
+}
+
+struct Tamper<'a, F, CS> {
+    cs: &'a mut CS,
+    targets: HashMap<(usize, usize), F>,
+}
+
+impl<'a, F: Field, CS: Assignment<F>> Assignment<F> for Tamper<'a, F, CS> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.enter_region(name_fn)
+    }
+
+    fn annotate_column<A, AR>(&mut self, annotation: A, column: Column<Any>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.annotate_column(annotation, column)
+    }
+
+    fn exit_region(&mut self) {
+        self.cs.exit_region()
+    }
+
+    fn enable_selector<A, AR>(&mut self, annotation: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.enable_selector(annotation, selector, row)
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
+        self.cs.query_instance(column, row)
+    }
+
+    fn assign_advice<V, VR, A, AR>(&mut self, annotation: A, column: Column<Advice>, row: usize, to: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        match self.targets.get(&(column.index(), row)) {
+            Some(delta) => self.cs.assign_advice(annotation, column, row, || {
+                to().map(|v| Into::<Assigned<F>>::into(v) + Assigned::from(*delta))
+            }),
+            None => self.cs.assign_advice(annotation, column, row, to),
+        }
+    }
+
+    fn assign_fixed<V, VR, A, AR>(&mut self, annotation: A, column: Column<Fixed>, row: usize, to: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.assign_fixed(annotation, column, row, to)
+    }
+
+    fn copy(&mut self, left_column: Column<Any>, left_row: usize, right_column: Column<Any>, right_row: usize) -> Result<(), Error> {
+        self.cs.copy(left_column, left_row, right_column, right_row)
+    }
+
+    fn fill_from_row(&mut self, column: Column<Fixed>, row: usize, to: Value<Assigned<F>>) -> Result<(), Error> {
+        self.cs.fill_from_row(column, row, to)
+    }
+
+    fn get_challenge(&self, challenge: Challenge) -> Value<F> {
+        self.cs.get_challenge(challenge)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.cs.pop_namespace(gadget_name)
+    }
+}
+
+// `P` with the assignment wrapped in a `Tamper`
+pub struct TamperFloorPlanner<P>(PhantomData<P>);
+
+impl<P: FloorPlanner> FloorPlanner for TamperFloorPlanner<P> {
+    fn synthesize<F: Field, CS: Assignment<F>, C: Circuit<F>>(
+        cs: &mut CS,
+        circuit: &C,
+        config: C::Config,
+        constants: Vec<Column<Fixed>>,
+    ) -> Result<(), Error> {
+        // `Mutated` is only a circuit over Fr, so F is Fr
+        let targets = TAMPERING.with(|tampering| {
+            tampering
+                .borrow()
+                .iter()
+                .map(|(cell, delta)| (*cell, *(delta as &dyn std::any::Any).downcast_ref::<F>().expect("a circuit over Fr")))
+                .collect()
+        });
+
+        P::synthesize(&mut Tamper { cs, targets }, circuit, config, constants)
+    }
+}
+
+// `C` laid out by `TamperFloorPlanner`
+pub struct Mutated<C>(pub C);
+
+impl<C: Circuit<Fr>> Circuit<Fr> for Mutated<C> {
+    type Config = C::Config;
+    type FloorPlanner = TamperFloorPlanner<C::FloorPlanner>;
+
+    fn without_witnesses(&self) -> Self {
+        Self(self.0.without_witnesses())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        self.0.synthesize(config, layouter)
+    }
+}
+
+// `per_group` indices of a group of `len`, the first, the last and evenly spaced between them
+fn samples(len: usize, per_group: usize) -> impl Iterator<Item = usize> {
+    let samples = per_group.clamp(1, len);
+    (0..samples).map(move |i| if samples == 1 { 0 } else { i * (len - 1) / (samples - 1) })
+}
+
+impl<C: Circuit<Fr>> Mutated<C> {
+    // the circuit laid out for 2^k rows
+    fn record(&self, k: u32) -> (ConstraintSystem<Fr>, layout::Recorder) {
+        layout::record(&self.0, k).expect("synthesis should not fail")
+    }
+
+    fn cell(recorder: &layout::Recorder, (column, row): (usize, usize)) -> AdviceCell {
+        let advice = &recorder.advice[&(column, row)];
+        let region = advice.region.map_or(String::new(), |region| recorder.regions[region].0.clone());
+
+        AdviceCell { region, annotation: advice.annotation.clone(), column, row }
+    }
+
+    // the advice cells, in row order
+    pub fn cells(&self, k: u32) -> Vec<AdviceCell> {
+        let (_, recorder) = self.record(k);
+
+        let mut cells = recorder.advice.keys().map(|cell| Self::cell(&recorder, *cell)).collect::<Vec<_>>();
+        cells.sort_by_key(|cell| (cell.row, cell.column));
+        cells
+    }
+
+    // whether the prover accepts the circuit with each cell of `mutation` moved by its delta
+    pub fn accepts(&self, k: u32, mutation: &[(&AdviceCell, Fr)]) -> bool {
+        let prover = self.with_mutation(mutation, |circuit| MockProver::run(k, circuit, vec![]).expect("synthesis should not fail"));
+        prover.verify().is_ok()
+    }
+
+    // `f` with `mutation` applied, e.g. a real prover
+    pub fn with_mutation<R>(&self, mutation: &[(&AdviceCell, Fr)], f: impl FnOnce(&Self) -> R) -> R {
+        let targets = mutation.iter().map(|(cell, delta)| (cell.key(), *delta)).collect();
+        TAMPERING.with(|tampering| *tampering.borrow_mut() = targets);
+        let result = f(self);
+        TAMPERING.with(|tampering| tampering.borrow_mut().clear());
+
+        result
+    }
+
+    // The cells whose mutation is accepted, among every advice cell: one prover run per cell.
+    pub fn accepted_mutations(&self, k: u32) -> Vec<AdviceCell> {
+        self.accepted_mutations_sampled(k, usize::MAX)
+    }
+
+    // `accepted_mutations` among `per_group` cells of every annotation of every advice column
+    // in a region, the first, the last and evenly spaced between them.
+    pub fn accepted_mutations_sampled(&self, k: u32, per_group: usize) -> Vec<AdviceCell> {
+        assert!(self.accepts(k, &[]), "the circuit must be satisfied before any mutation");
+
+        let mut groups = BTreeMap::<_, Vec<AdviceCell>>::new();
+        for cell in self.cells(k) {
+            groups.entry((cell.region.clone(), cell.annotation.clone(), cell.column)).or_default().push(cell);
+        }
+
+        groups
+            .values()
+            .flat_map(|group| samples(group.len(), per_group).map(|i| group[i].clone()))
+            .filter(|cell| self.accepts(k, &[(cell, Fr::one())]))
+            .collect()
+    }
+
+    // Every pair of cells a gate polynomial changes with, the second moved so the polynomial
+    // keeps its value, by gate instance. A pair whose second cell the polynomial does not read
+    // linearly has no such move and is left out.
+    fn paired_mutations(&self, k: u32) -> BTreeMap<(String, String, usize), Vec<PairedMutation>> {
+        let (cs, recorder) = self.record(k);
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+
+        for gate in cs.gates() {
+            for row in recorder.gate_rows(gate) {
+                for (i, polynomial) in gate.polynomials().iter().enumerate() {
+                    let cells = recorder.sensitive(std::slice::from_ref(polynomial), row);
+                    let value = recorder.evaluate(polynomial, row, &[]);
+                    let slope = |cell| recorder.evaluate(polynomial, row, &[(cell, Fr::one())]) - value;
+
+                    for (j, &a) in cells.iter().enumerate() {
+                        for &b in &cells[j + 1..] {
+                            let delta = -slope(a) * slope(b).invert().unwrap();
+                            if recorder.evaluate(polynomial, row, &[(a, Fr::one()), (b, delta)]) != value {
+                                continue;
+                            }
+
+                            let cells = [Self::cell(&recorder, a), Self::cell(&recorder, b)];
+                            groups
+                                .entry((cells[0].region.clone(), gate.name().to_string(), i))
+                                .or_default()
+                                .push(PairedMutation { gate: gate.name().to_string(), cells, delta });
+                        }
+                    }
+                }
+            }
+        }
+
+        groups
+    }
+
+    // The paired mutations that are accepted, among `per_group` pairs of every polynomial of
+    // every gate in a region, sampled as `accepted_mutations_sampled` does.
+    pub fn accepted_paired_mutations_sampled(&self, k: u32, per_group: usize) -> Vec<PairedMutation> {
+        assert!(self.accepts(k, &[]), "the circuit must be satisfied before any mutation");
+
+        self.paired_mutations(k)
+            .into_values()
+            .flat_map(|group| samples(group.len(), per_group).map(move |i| group[i].clone()).collect::<Vec<_>>())
+            .filter(|pair| self.accepts(k, &[(&pair.cells[0], Fr::one()), (&pair.cells[1], pair.delta)]))
+            .collect()
+    }
+}
@@ -0,0 +1,388 @@
+// A static counterpart of `mutation`: the circuit is laid out once into `layout::Recorder` and
+// its `ConstraintSystem` is walked over the recorded rows, without running a prover.
+//
+// A cell takes part in a constraint at a row if the constraint there changes when the cell does,
+// at the assigned values. So the operation of a padding round, multiplied by s_round = 0, reads
+// none of its cells.
+//
+// A cell is determined if it is an input of the circuit or copied from a determined cell, a
+// fixed or an instance cell. A looked up cell is only bounded: it is in the table, below the
+// table column or a shift of it. Three rules then determine cells until none changes:
+// 1. the only undetermined cell of a gate constraint it takes part in is determined;
+// 2. at a lookup row, when the input of a table column no two table rows share is determined, so
+//    is the row of the table: the only undetermined cell of each other input is determined;
+// 3. the undetermined cells of a gate constraint are determined if they are the digits of a
+//    number: the constraint is linear in them, their slopes are one slope times powers of two,
+//    and their bounds shifted by those powers are disjoint below 2^253, so the sum is an integer
+//    below the modulus with a single decomposition. The xor of spreads is such a sum.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use halo2_exp::layout::{self, Recorder, DELTA};
+use halo2_proofs::{
+    halo2curves::{
+        bn256::Fr,
+        group::ff::{Field, PrimeField},
+    },
+    plonk::{Any, Circuit, Column, Expression},
+};
+
+use super::mutation::AdviceCell;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    // in no enabled gate, lookup or copy constraint
+    Unconstrained,
+    // constrained, but never pinned by its constraints
+    Underdetermined,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub cell: AdviceCell,
+    // the row in the region
+    pub offset: usize,
+    pub kind: Kind,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}+{}: {} (advice {}, row {}) is {:?}",
+            self.cell.region, self.offset, self.cell.annotation, self.cell.column, self.cell.row, self.kind
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Cell {
+    Advice(usize, usize),
+    Fixed(usize, usize),
+    Instance(usize, usize),
+}
+
+impl Cell {
+    fn new(column: Column<Any>, row: usize) -> Self {
+        match column.column_type() {
+            Any::Advice(_) => Cell::Advice(column.index(), row),
+            Any::Fixed => Cell::Fixed(column.index(), row),
+            Any::Instance => Cell::Instance(column.index(), row),
+        }
+    }
+}
+
+// copy classes as a union-find over the cells
+#[derive(Default)]
+struct Classes(HashMap<Cell, Cell>);
+
+impl Classes {
+    fn find(&mut self, cell: Cell) -> Cell {
+        let parent = *self.0.entry(cell).or_insert(cell);
+        if parent == cell {
+            return cell;
+        }
+        let root = self.find(parent);
+        self.0.insert(cell, root);
+        root
+    }
+
+    fn union(&mut self, a: Cell, b: Cell) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0.insert(a, b);
+    }
+
+    fn advice(&mut self, (column, row): (usize, usize)) -> Cell {
+        self.find(Cell::Advice(column, row))
+    }
+}
+
+// a gate polynomial at a row: the advice cells it queries with their class, and the classes
+// it changes with
+struct Constraint<'a> {
+    polynomial: &'a Expression<Fr>,
+    row: usize,
+    queried: Vec<((usize, usize), Cell)>,
+    sensitive: Vec<Cell>,
+}
+
+// The classes of the sensitive cells of each input of a lookup at a row, with whether no two
+// rows of the table share the value of its column.
+struct LookupRow<'a> {
+    inputs: Vec<Vec<Cell>>,
+    injective: &'a [bool],
+}
+
+// A lookup input that is `2^shift * cell` once the `others` cells of the input are determined:
+// the cell is below the table column shifted right. Only a bound with no shift puts the cell
+// itself in the table, the others then narrow it.
+struct Bound {
+    class: Cell,
+    shift: u32,
+    column: u128,
+    others: Vec<Cell>,
+}
+
+// the value, if it is below 2^128
+fn to_u128(value: Fr) -> Option<u128> {
+    let repr = value.to_repr();
+    let (low, high) = repr.as_ref().split_at(16);
+    high.iter().all(|byte| *byte == 0).then(|| u128::from_le_bytes(low.try_into().expect("16 bytes")))
+}
+
+// k with `value` = 2^k, k negative for the inverse of a power of two
+fn log2(value: Fr) -> Option<i64> {
+    let bit = |value: Fr| {
+        let repr = value.to_repr();
+        let bytes = repr.as_ref();
+        (bytes.iter().map(|byte| byte.count_ones()).sum::<u32>() == 1)
+            .then(|| bytes.iter().position(|byte| *byte != 0).map(|i| 8 * i as i64 + bytes[i].trailing_zeros() as i64))
+            .flatten()
+    };
+    bit(value).or_else(|| bit(Option::from(value.invert())?).map(|k| -k))
+}
+
+// the slope of `expression` at `row` in the cells of `moved`, if it is linear in them
+fn slope(recorder: &Recorder, expression: &Expression<Fr>, row: usize, moved: &[(usize, usize)]) -> Option<Fr> {
+    let value = recorder.evaluate(expression, row, &[]);
+    let shift = |by: Fr| {
+        let moved = moved.iter().map(|cell| (*cell, by)).collect::<Vec<_>>();
+        recorder.evaluate(expression, row, &moved) - value
+    };
+
+    let delta = Fr::from(DELTA);
+    let slope = shift(delta) * Option::<Fr>::from(delta.invert())?;
+    (shift(delta.double()) == slope * delta.double()).then_some(slope)
+}
+
+// The bounds of the classes with a bound of no shift, intersected.
+fn bounded(bounds: &[Bound], determined: &HashSet<Cell>) -> HashMap<Cell, u128> {
+    let mut intersected = HashMap::<Cell, (bool, u128)>::new();
+    for bound in bounds.iter().filter(|bound| bound.others.iter().all(|other| determined.contains(other))) {
+        let (in_table, mask) = intersected.entry(bound.class).or_insert((false, u128::MAX));
+        *in_table |= bound.shift == 0;
+        *mask &= bound.column >> bound.shift;
+    }
+
+    intersected.into_iter().filter(|(_, (in_table, _))| *in_table).map(|(class, (_, mask))| (class, mask)).collect()
+}
+
+// Rule 3: Some if the `undetermined` classes are the digits of `constraint`.
+fn digits(recorder: &Recorder, constraint: &Constraint, undetermined: &[Cell], bounds: &HashMap<Cell, u128>) -> Option<()> {
+    let moved = |classes: &[Cell]| {
+        constraint.queried.iter().filter(|(_, class)| classes.contains(class)).map(|(cell, _)| *cell).collect::<Vec<_>>()
+    };
+    let slopes = undetermined
+        .iter()
+        .map(|class| slope(recorder, constraint.polynomial, constraint.row, &moved(std::slice::from_ref(class))))
+        .collect::<Option<Vec<_>>>()?;
+
+    // linear in all of them at once, no product of two
+    let sum = slopes.iter().fold(Fr::zero(), |sum, slope| sum + slope);
+    (slope(recorder, constraint.polynomial, constraint.row, &moved(undetermined))? == sum).then_some(())?;
+
+    let first = Option::<Fr>::from(slopes[0].invert())?;
+    let powers = slopes.iter().map(|slope| log2(*slope * first)).collect::<Option<Vec<_>>>()?;
+    let lowest = *powers.iter().min()?;
+
+    let mut bits = HashSet::new();
+    for (class, power) in undetermined.iter().zip(&powers) {
+        let bound = bounds.get(class)?;
+        for bit in (0..128).filter(|bit| (bound >> bit) & 1 == 1) {
+            let bit = bit + power - lowest;
+            if bit >= 253 || !bits.insert(bit) {
+                return None;
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// The advice cells of `circuit` that are unconstrained or underdetermined, in row order, for a
+/// circuit of 2^`k` rows. The cells whose annotation is an `input` are the statement the circuit
+/// is about and are determined.
+pub fn analyze<C: Circuit<Fr>>(circuit: &C, k: u32, input: impl Fn(&str) -> bool) -> Vec<Finding> {
+    let (cs, recorder) = layout::record(circuit, k).expect("synthesis should not fail");
+
+    let mut classes = Classes::default();
+    let mut copied = HashSet::new();
+    for ((a, a_row), (b, b_row)) in &recorder.copies {
+        let (a, b) = (Cell::new(*a, *a_row), Cell::new(*b, *b_row));
+        classes.union(a, b);
+        copied.extend([a, b]);
+    }
+
+    // the sensitive cells of every gate constraint at every row a selector of the gate is on
+    let mut constraints = Vec::new();
+    for gate in cs.gates() {
+        for row in recorder.gate_rows(gate) {
+            for polynomial in gate.polynomials() {
+                let sensitive = recorder.sensitive(std::slice::from_ref(polynomial), row);
+                if sensitive.is_empty() {
+                    continue;
+                }
+
+                let mut queried = recorder.queried(polynomial, row);
+                queried.sort();
+                queried.dedup();
+                let queried = queried.into_iter().map(|cell| (cell, classes.advice(cell))).collect();
+
+                let mut sensitive = sensitive.into_iter().map(|cell| classes.advice(cell)).collect::<Vec<_>>();
+                sensitive.sort();
+                sensitive.dedup();
+
+                constraints.push(Constraint { polynomial, row, queried, sensitive });
+            }
+        }
+    }
+
+    // the table of each lookup, the bounds of its columns and whether each is injective
+    let tables = cs
+        .lookups()
+        .iter()
+        .map(|lookup| {
+            lookup
+                .table_expressions()
+                .iter()
+                .map(|expression| {
+                    let mut rows = BTreeMap::<Vec<u8>, HashSet<Vec<Vec<u8>>>>::new();
+                    let mut column = Some(0u128);
+                    for row in 0..recorder.rows {
+                        let value = recorder.evaluate(expression, row, &[]);
+                        column = column.zip(to_u128(value)).map(|(column, value)| column | value);
+
+                        let table_row = lookup
+                            .table_expressions()
+                            .iter()
+                            .map(|expression| recorder.evaluate(expression, row, &[]).to_repr().as_ref().to_vec());
+                        rows.entry(value.to_repr().as_ref().to_vec()).or_default().insert(table_row.collect());
+                    }
+                    (column, rows.values().all(|rows| rows.len() == 1))
+                })
+                .unzip::<_, _, Vec<_>, Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut looked_up = HashSet::new();
+    let mut lookup_rows = Vec::new();
+    let mut bounds = Vec::new();
+    for (lookup, (columns, injective)) in cs.lookups().iter().zip(&tables) {
+        for row in 0..recorder.rows {
+            let inputs = lookup
+                .input_expressions()
+                .iter()
+                .map(|expression| recorder.sensitive(std::slice::from_ref(expression), row))
+                .collect::<Vec<_>>();
+            if inputs.iter().all(Vec::is_empty) {
+                continue;
+            }
+
+            for ((expression, cells), column) in lookup.input_expressions().iter().zip(&inputs).zip(columns) {
+                looked_up.extend(cells.iter().copied());
+
+                let Some(column) = column else { continue };
+                for cell in cells {
+                    let value = recorder.evaluate(expression, row, &[]);
+                    let Some(slope) = slope(&recorder, expression, row, &[*cell]) else { continue };
+                    // no intercept, the input is the cell times its slope
+                    if value != slope * recorder.value(*cell) {
+                        continue;
+                    }
+                    let Some(shift) = log2(slope).filter(|shift| (0..128).contains(shift)) else { continue };
+
+                    let others = cells.iter().filter(|other| *other != cell).map(|other| classes.advice(*other)).collect();
+                    bounds.push(Bound { class: classes.advice(*cell), shift: shift as u32, column: *column, others });
+                }
+            }
+
+            let inputs = inputs
+                .into_iter()
+                .map(|cells| cells.into_iter().map(|cell| classes.advice(cell)).collect())
+                .collect();
+            lookup_rows.push(LookupRow { inputs, injective });
+        }
+    }
+
+    let inputs = recorder
+        .advice
+        .iter()
+        .filter(|(_, advice)| input(&advice.annotation))
+        .map(|(&(column, row), _)| Cell::Advice(column, row))
+        .collect::<Vec<_>>();
+
+    let mut determined = HashSet::new();
+    for cell in copied.iter().filter(|cell| !matches!(cell, Cell::Advice(..))).chain(&inputs) {
+        determined.insert(classes.find(*cell));
+    }
+
+    loop {
+        let mut changed = false;
+
+        let bounded = bounded(&bounds, &determined);
+        for constraint in &constraints {
+            let undetermined =
+                constraint.sensitive.iter().filter(|class| !determined.contains(*class)).copied().collect::<Vec<_>>();
+            let pinned = match undetermined.len() {
+                0 => false,
+                1 => true,
+                _ => digits(&recorder, constraint, &undetermined, &bounded).is_some(),
+            };
+            if pinned {
+                determined.extend(undetermined);
+                changed = true;
+            }
+        }
+
+        for lookup_row in &lookup_rows {
+            let table_row = lookup_row
+                .inputs
+                .iter()
+                .zip(lookup_row.injective)
+                .any(|(cells, injective)| *injective && cells.iter().all(|class| determined.contains(class)));
+            if !table_row {
+                continue;
+            }
+            for cells in &lookup_row.inputs {
+                let mut undetermined = cells.iter().filter(|class| !determined.contains(*class));
+                if let (Some(class), None) = (undetermined.next(), undetermined.next()) {
+                    determined.insert(*class);
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let constrained = constraints.iter().flat_map(|constraint| &constraint.sensitive).copied().collect::<HashSet<_>>();
+
+    let mut findings = recorder
+        .advice
+        .iter()
+        .filter_map(|(&(column, row), advice)| {
+            let cell = Cell::Advice(column, row);
+            let class = classes.find(cell);
+
+            let kind = if !constrained.contains(&class) && !looked_up.contains(&(column, row)) && !copied.contains(&cell) {
+                Kind::Unconstrained
+            } else if !determined.contains(&class) {
+                Kind::Underdetermined
+            } else {
+                return None;
+            };
+
+            let (name, start, _) = advice.region.map_or((String::new(), row, row), |region| recorder.regions[region].clone());
+            Some(Finding {
+                cell: AdviceCell { region: name, annotation: advice.annotation.clone(), column, row },
+                offset: row - start,
+                kind,
+            })
+        })
+        .collect::<Vec<_>>();
+    findings.sort_by_key(|finding| (finding.cell.row, finding.cell.column));
+
+    findings
+}
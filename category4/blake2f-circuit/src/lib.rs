@@ -8,6 +8,10 @@ use halo2_proofs::{
     circuit::Layouter,
     plonk::Error,
 };
+use std::fmt;
+
+// rounds || h || m || t || f, the input of the precompile
+pub const INPUT_BYTES: usize = 213;
 
 #[derive(Clone, Debug, Default)]
 pub struct Blake2fWitness {
@@ -18,85 +22,184 @@ pub struct Blake2fWitness {
     pub f: bool,
 }
 
+// the precompile rejects an input that is not INPUT_BYTES long or whose f is not 0 or 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blake2fInputError {
+    InvalidLength(usize),
+    InvalidFinalFlag(u8),
+}
+
+impl fmt::Display for Blake2fInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(len) => write!(f, "input is {len} bytes instead of {INPUT_BYTES}"),
+            Self::InvalidFinalFlag(flag) => write!(f, "final block indicator is {flag} instead of 0 or 1"),
+        }
+    }
+}
+
+impl std::error::Error for Blake2fInputError {}
+
+impl Blake2fWitness {
+    // rounds is big endian, h, m and t are little endian words
+    pub fn from_bytes(input: &[u8]) -> Result<Self, Blake2fInputError> {
+        if input.len() != INPUT_BYTES {
+            return Err(Blake2fInputError::InvalidLength(input.len()));
+        }
+
+        let f = match input[INPUT_BYTES - 1] {
+            0 => false,
+            1 => true,
+            flag => return Err(Blake2fInputError::InvalidFinalFlag(flag)),
+        };
+        let word = |i: usize| u64::from_le_bytes(input[4 + 8 * i..12 + 8 * i].try_into().unwrap());
+
+        Ok(Self {
+            rounds: u32::from_be_bytes(input[..4].try_into().unwrap()),
+            h: std::array::from_fn(word),
+            m: std::array::from_fn(|i| word(8 + i)),
+            t: [word(24), word(25)],
+            f,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.rounds.to_be_bytes().to_vec();
+        for word in self.h.iter().chain(self.m.iter()).chain(self.t.iter()) {
+            bytes.extend(word.to_le_bytes());
+        }
+        bytes.push(self.f as u8);
+        bytes
+    }
+}
+
 #[cfg(any(feature = "test", test))]
 pub mod dev {
     use super::*;
 
     use ethers_core::{types::H512, utils::hex::FromHex};
-    use halo2_exp::Blake2fInstructions;
+    use halo2_exp::{Blake2fInstructions, MAX_ROUND};
     use halo2_proofs::{circuit::{SimpleFloorPlanner, Value}, plonk::Circuit, halo2curves::bn256};
     use std::{marker::PhantomData, str::FromStr};
 
+    pub mod mutation;
+    pub mod underconstrained;
+
+    // h, m and t shared by vectors 1 to 8 of https://eips.ethereum.org/EIPS/eip-152#test-cases
+    const H: &str = "48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5\
+                     d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b";
+    const M: &str = "6162630000000000000000000000000000000000000000000000000000000000\
+                     0000000000000000000000000000000000000000000000000000000000000000\
+                     0000000000000000000000000000000000000000000000000000000000000000\
+                     0000000000000000000000000000000000000000000000000000000000000000";
+    const T: &str = "03000000000000000000000000000000";
+
+    #[derive(Clone, Debug)]
+    pub struct Eip152Vector {
+        pub name: &'static str,
+        pub input: Vec<u8>,
+        // the output of the precompile, or the error of the parser
+        pub output: Result<H512, Blake2fInputError>,
+    }
+
+    impl Eip152Vector {
+        fn new(name: &'static str, rounds: &str, f: &str, output: Result<&str, Blake2fInputError>) -> Self {
+            Self {
+                name,
+                input: Vec::from_hex(format!("{rounds}{H}{M}{T}{f}")).expect("hex input"),
+                output: output.map(|output| H512::from_str(output).expect("BLAKE2F compression function output is 64-bytes")),
+            }
+        }
+
+        // a valid input of at most MAX_ROUND rounds, that the circuit can compute
+        pub fn provable(&self) -> Option<(Blake2fWitness, H512)> {
+            let witness = Blake2fWitness::from_bytes(&self.input).ok()?;
+            let output = self.output.ok()?;
+            (witness.rounds as usize <= MAX_ROUND).then_some((witness, output))
+        }
+    }
+
     lazy_static::lazy_static! {
-        // https://eips.ethereum.org/EIPS/eip-152#example-usage-in-solidity
-        pub static ref INPUTS_OUTPUTS: (Vec<Blake2fWitness>, Vec<H512>) = {
-            let (h1, h2) = (
-                <[u8; 32]>::from_hex("48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5").expect(""),
-                <[u8; 32]>::from_hex("d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b").expect(""),
-            );
-            let (m1, m2, m3, m4) = (
-                <[u8; 32]>::from_hex("6162630000000000000000000000000000000000000000000000000000000000").expect(""),
-                <[u8; 32]>::from_hex("0000000000000000000000000000000000000000000000000000000000000000").expect(""),
-                <[u8; 32]>::from_hex("0000000000000000000000000000000000000000000000000000000000000000").expect(""),
-                <[u8; 32]>::from_hex("0000000000000000000000000000000000000000000000000000000000000000").expect(""),
-            );
-            (
-                vec![
-                    Blake2fWitness {
-                        rounds: 12,
-                        h: [
-                            u64::from_le_bytes(h1[0x00..0x08].try_into().expect("")),
-                            u64::from_le_bytes(h1[0x08..0x10].try_into().expect("")),
-                            u64::from_le_bytes(h1[0x10..0x18].try_into().expect("")),
-                            u64::from_le_bytes(h1[0x18..0x20].try_into().expect("")),
-                            u64::from_le_bytes(h2[0x00..0x08].try_into().expect("")),
-                            u64::from_le_bytes(h2[0x08..0x10].try_into().expect("")),
-                            u64::from_le_bytes(h2[0x10..0x18].try_into().expect("")),
-                            u64::from_le_bytes(h2[0x18..0x20].try_into().expect("")),
-                        ],
-                        m: [
-                            u64::from_le_bytes(m1[0x00..0x08].try_into().expect("")),
-                            u64::from_le_bytes(m1[0x08..0x10].try_into().expect("")),
-                            u64::from_le_bytes(m1[0x10..0x18].try_into().expect("")),
-                            u64::from_le_bytes(m1[0x18..0x20].try_into().expect("")),
-                            u64::from_le_bytes(m2[0x00..0x08].try_into().expect("")),
-                            u64::from_le_bytes(m2[0x08..0x10].try_into().expect("")),
-                            u64::from_le_bytes(m2[0x10..0x18].try_into().expect("")),
-                            u64::from_le_bytes(m2[0x18..0x20].try_into().expect("")),
-                            u64::from_le_bytes(m3[0x00..0x08].try_into().expect("")),
-                            u64::from_le_bytes(m3[0x08..0x10].try_into().expect("")),
-                            u64::from_le_bytes(m3[0x10..0x18].try_into().expect("")),
-                            u64::from_le_bytes(m3[0x18..0x20].try_into().expect("")),
-                            u64::from_le_bytes(m4[0x00..0x08].try_into().expect("")),
-                            u64::from_le_bytes(m4[0x08..0x10].try_into().expect("")),
-                            u64::from_le_bytes(m4[0x10..0x18].try_into().expect("")),
-                            u64::from_le_bytes(m4[0x18..0x20].try_into().expect("")),
-                        ],
-                        t: [3, 0],
-                        f: true,
-                    }
-                ],
-                vec![
-                    H512::from_str("ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923")
-                    .expect("BLAKE2F compression function output is 64-bytes")
-                ],
-            )
+        // vector 8 of the EIP takes 2^32 - 1 rounds, geth's 8,000,000-round vector is used
+        // instead and only with the `slow-vectors` feature, it is checked natively
+        pub static ref EIP152_VECTORS: Vec<Eip152Vector> = {
+            let mut vectors = vec![
+                Eip152Vector {
+                    name: "vector 0",
+                    input: Vec::new(),
+                    output: Err(Blake2fInputError::InvalidLength(0)),
+                },
+                Eip152Vector::new("vector 1", "00000c", "01", Err(Blake2fInputError::InvalidLength(212))),
+                Eip152Vector::new("vector 2", "000000000c", "01", Err(Blake2fInputError::InvalidLength(214))),
+                Eip152Vector::new("vector 3", "0000000c", "02", Err(Blake2fInputError::InvalidFinalFlag(2))),
+                Eip152Vector::new(
+                    "vector 4",
+                    "00000000",
+                    "01",
+                    Ok("08c9bcf367e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d282e6ad7f520e511f6c3e2b8c68059b9442be0454267ce079217e1319cde05b"),
+                ),
+                Eip152Vector::new(
+                    "vector 5",
+                    "0000000c",
+                    "01",
+                    Ok("ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"),
+                ),
+                Eip152Vector::new(
+                    "vector 6",
+                    "0000000c",
+                    "00",
+                    Ok("75ab69d3190a562c51aef8d88f1c2775876944407270c42c9844252c26d2875298743e7f6d5ea2f2d3e8d226039cd31b4e426ac4f2d3d666a610c2116fde4735"),
+                ),
+                Eip152Vector::new(
+                    "vector 7",
+                    "00000001",
+                    "01",
+                    Ok("b63a380cb2897d521994a85234ee2c181b5f844d2c624c002677e9703449d2fba551b3a8333bcdf5f2f7e08993d53923de3d64fcc68c034e717b9293fed7a421"),
+                ),
+            ];
+            if cfg!(feature = "slow-vectors") {
+                vectors.push(Eip152Vector::new(
+                    "8,000,000 rounds",
+                    "007a1200",
+                    "01",
+                    Ok("6d2ce9e534d50e18ff866ae92d70cceba79bbcd14c63819fe48752c8aca87a4bb7dcc230d22a4047f0486cfcfb50a17b24b2899eb8fca370f22240adb5170189"),
+                ));
+            }
+            vectors
         };
+
+        // the vectors the circuit computes, vectors 4 to 7
+        pub static ref INPUTS_OUTPUTS: (Vec<Blake2fWitness>, Vec<H512>) =
+            EIP152_VECTORS.iter().filter_map(Eip152Vector::provable).unzip();
     }
 
     #[derive(Default)]
     pub struct Blake2fTestCircuit<F> {
         pub inputs: Vec<Blake2fWitness>,
         pub outputs: Vec<H512>,
+        // false without witnesses, the compressions are laid out with unknown inputs
+        known: bool,
         pub _marker: PhantomData<F>,
     }
 
+    impl<F> Blake2fTestCircuit<F> {
+        pub fn new(inputs: Vec<Blake2fWitness>, outputs: Vec<H512>) -> Self {
+            Self { inputs, outputs, known: true, _marker: PhantomData }
+        }
+    }
+
     impl Circuit<bn256::Fr> for Blake2fTestCircuit<bn256::Fr> {
         type Config = Blake2fConfig;
         type FloorPlanner = SimpleFloorPlanner;
 
+        // as many compressions, the keys only depend on their number
         fn without_witnesses(&self) -> Self {
-            Self::default()
+            Self {
+                inputs: vec![Blake2fWitness::default(); self.inputs.len()],
+                outputs: vec![H512::zero(); self.outputs.len()],
+                known: false,
+                _marker: PhantomData,
+            }
         }
 
         fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<bn256::Fr>) -> Self::Config {
@@ -109,7 +212,9 @@ pub mod dev {
             mut layouter: impl Layouter<bn256::Fr>,
         ) -> Result<(), Error> {
             let blake2f_chip = Blake2fChip::construct(config);
-            blake2f_chip.initialize(&mut layouter)?;
+            let iv = blake2f_chip.initialize(&mut layouter)?;
+
+            let value = |v: u64| if self.known { Value::known(v) } else { Value::unknown() };
 
             for (input, output) in self.inputs.iter().zip(self.outputs.iter()) {
                 let f = if input.f {
@@ -118,13 +223,14 @@ pub mod dev {
                     0
                 };
                 let h = blake2f_chip.compress(
-                    &mut layouter, 
-                    input.h.iter().map(|v| Value::known(*v)).collect::<Vec<_>>().try_into().unwrap(),
-                    input.m.iter().map(|v| Value::known(*v)).collect::<Vec<_>>().try_into().unwrap(),
-                    Value::known(input.t[0]),
-                    Value::known(input.t[1]),
-                    Value::known(f),
-                    Value::known(input.rounds as u64),
+                    &mut layouter,
+                    &iv,
+                    input.h.map(value),
+                    input.m.map(value),
+                    value(input.t[0]),
+                    value(input.t[1]),
+                    value(f),
+                    value(input.rounds as u64),
                 )?;
 
                 let mut ex_h = Vec::with_capacity(8);
@@ -152,23 +258,208 @@ pub mod dev {
 
 #[cfg(test)]
 mod tests {
-    use halo2_proofs::{dev::MockProver, halo2curves::{bn256::Fr}};
-    use std::marker::PhantomData;
+    use halo2_exp::native;
+    use halo2_proofs::{
+        dev::MockProver,
+        halo2curves::bn256::{Bn256, Fr, G1Affine},
+        plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, Error, ProvingKey, VerifyingKey},
+        poly::kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+        transcript::{Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer},
+    };
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
 
-    use crate::dev::{Blake2fTestCircuit, INPUTS_OUTPUTS};
+    use crate::dev::{mutation::Mutated, underconstrained, Blake2fTestCircuit, EIP152_VECTORS, INPUTS_OUTPUTS};
+    use crate::{Blake2fInputError, Blake2fWitness};
 
     #[test]
     fn test_blake2f_circuit() {
         let (inputs, outputs) = INPUTS_OUTPUTS.clone();
 
-        let circuit: Blake2fTestCircuit<Fr> = Blake2fTestCircuit {
-            inputs,
-            outputs,
-            _marker: PhantomData,
-        };
+        let circuit: Blake2fTestCircuit<Fr> = Blake2fTestCircuit::new(inputs, outputs);
 
         let k = 17;
         let prover = MockProver::run(k, &circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    // the parser accepts or rejects each vector as the precompile does, and the native F gives
+    // the expected output
+    #[test]
+    fn test_eip152_vectors() {
+        for vector in EIP152_VECTORS.iter() {
+            let witness = Blake2fWitness::from_bytes(&vector.input);
+            match (witness, vector.output) {
+                (Ok(witness), Ok(output)) => {
+                    assert_eq!(witness.to_bytes(), vector.input, "{}", vector.name);
+
+                    let h = native::blake2f(witness.rounds, witness.h, witness.m, witness.t, witness.f);
+                    let h = h.iter().flat_map(|h| h.to_le_bytes()).collect::<Vec<_>>();
+                    assert_eq!(h, output.as_bytes(), "{}", vector.name);
+                }
+                (witness, output) => assert_eq!(witness.err(), output.err(), "{}", vector.name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_input_errors() {
+        let mut input = EIP152_VECTORS[5].input.clone();
+        assert!(Blake2fWitness::from_bytes(&input).is_ok());
+
+        input[212] = 0xff;
+        assert_eq!(Blake2fWitness::from_bytes(&input).unwrap_err(), Blake2fInputError::InvalidFinalFlag(0xff));
+        assert_eq!(
+            Blake2fWitness::from_bytes(&input[..100]).unwrap_err().to_string(),
+            "input is 100 bytes instead of 213"
+        );
+    }
+
+    fn prove<C: Circuit<Fr>>(params: &ParamsKZG<Bn256>, pk: &ProvingKey<G1Affine>, circuit: &C, rng: &mut XorShiftRng) -> Vec<u8> {
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            _,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            _,
+        >(params, pk, std::slice::from_ref(circuit), &[&[]], rng, &mut transcript)
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    }
+
+    fn verify(params: &ParamsKZG<Bn256>, vk: &VerifyingKey<G1Affine>, proof: &[u8]) -> Result<(), Error> {
+        let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+        verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+            SingleStrategy<'_, Bn256>,
+        >(params, vk, SingleStrategy::new(params), &[&[]], &mut transcript)
+    }
+
+    // the parameters and the keys of a circuit of one compression, from its layout alone
+    fn setup() -> (ParamsKZG<Bn256>, ProvingKey<G1Affine>, XorShiftRng) {
+        let k = 17;
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
+        ]);
+        let params = ParamsKZG::<Bn256>::setup(k, &mut rng);
+
+        let circuit = vector_circuit("vector 5").without_witnesses();
+        let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+        (params, pk, rng)
+    }
+
+    // the circuit of vector `name` and a witness of it
+    fn vector_circuit(name: &str) -> Blake2fTestCircuit<Fr> {
+        let vector = EIP152_VECTORS.iter().find(|vector| vector.name == name).expect("vector");
+        let (witness, output) = vector.provable().expect("provable vector");
+        Blake2fTestCircuit::new(vec![witness], vec![output])
+    }
+
+    // each vector the circuit computes is proved and verified with KZG, with the keys of a
+    // circuit of one compression
+    #[test]
+    fn test_eip152_vectors_kzg() {
+        let (params, pk, mut rng) = setup();
+
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        let circuits = inputs
+            .into_iter()
+            .zip(outputs)
+            .map(|(input, output)| Blake2fTestCircuit::<Fr>::new(vec![input], vec![output]))
+            .collect::<Vec<_>>();
+        assert_eq!(circuits.len(), 4);
+
+        for circuit in circuits {
+            let proof = prove(&params, &pk, &circuit, &mut rng);
+            verify(&params, pk.get_vk(), &proof).expect("proof verification should not fail");
+        }
+    }
+
+    // vector 5 proved with its message word m0 or its output word h0 plus one, below the chip
+    #[test]
+    fn test_eip152_tampered_kzg() {
+        let (params, pk, mut rng) = setup();
+
+        let circuit = Mutated(vector_circuit("vector 5"));
+        let cells = circuit.cells(17);
+        assert!(circuit.accepts(17, &[]));
+
+        for annotation in ["assign m0", "assgin h0-val"] {
+            let target = cells.iter().find(|cell| cell.annotation == annotation).expect(annotation);
+            let proof = circuit.with_mutation(&[(target, Fr::one())], |circuit| prove(&params, &pk, circuit, &mut rng));
+            assert!(verify(&params, pk.get_vk(), &proof).is_err(), "{annotation} tampered");
+        }
+    }
+
+    fn assert_no_accepted<T: std::fmt::Display>(accepted: Vec<T>) {
+        let accepted = accepted.iter().map(|mutation| mutation.to_string()).collect::<Vec<_>>();
+        assert!(accepted.is_empty(), "under-constrained cells:\n{}", accepted.join("\n"));
+    }
+
+    fn mutations_per_group() -> usize {
+        std::env::var("MUTATIONS_PER_GROUP")
+            .map_or(2, |n| n.parse().expect("Cannot parse MUTATIONS_PER_GROUP env var as usize"))
+    }
+
+    // Every advice cell plus one must fail `MockProver::verify`, on vector 7 that has a computed
+    // round and padding rounds. It takes a prover run per cell, hours in all, so it only runs
+    // with the `exhaustive-mutations` feature.
+    #[test]
+    #[cfg_attr(not(feature = "exhaustive-mutations"), ignore)]
+    fn test_all_mutations_rejected() {
+        assert_no_accepted(Mutated(vector_circuit("vector 7")).accepted_mutations(17));
+    }
+
+    // `test_all_mutations_rejected` on `MUTATIONS_PER_GROUP` cells per annotation, 2 by default
+    #[test]
+    fn test_mutations_rejected() {
+        assert_no_accepted(Mutated(vector_circuit("vector 7")).accepted_mutations_sampled(17, mutations_per_group()));
+    }
+
+    // Every pair of cells of a gate polynomial, moved so the polynomial still holds, must fail
+    // another constraint. Also behind `exhaustive-mutations`.
+    #[test]
+    #[cfg_attr(not(feature = "exhaustive-mutations"), ignore)]
+    fn test_all_paired_mutations_rejected() {
+        assert_no_accepted(Mutated(vector_circuit("vector 7")).accepted_paired_mutations_sampled(17, usize::MAX));
+    }
+
+    // `test_all_paired_mutations_rejected` on `MUTATIONS_PER_GROUP` pairs per gate polynomial
+    #[test]
+    fn test_paired_mutations_rejected() {
+        assert_no_accepted(Mutated(vector_circuit("vector 7")).accepted_paired_mutations_sampled(17, mutations_per_group()));
+    }
+
+    // rounds, h, m, t and f, assigned by the scheduler from the precompile input
+    fn blake2f_input(annotation: &str) -> bool {
+        ["assign rounds", "assign c0-val", "assign c1-val", "assign flag-val"].contains(&annotation)
+            || (0..16).any(|i| annotation == format!("assign m{i}"))
+            || (0..8).any(|i| annotation == format!("assign v{i}-val"))
+    }
+
+    // The static analysis on vector 7 finds no cell of its scheduler, its computed round or its
+    // padding rounds under-constrained: with rounds, h, m, t and f as inputs, the gates, copies
+    // and lookups determine every other cell.
+    #[test]
+    fn test_underconstrained_cells() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        let i = inputs.iter().position(|input| input.rounds == 1).expect("vector 7");
+        let circuit = Blake2fTestCircuit::<Fr>::new(vec![inputs[i].clone()], vec![outputs[i]]);
+
+        let findings = underconstrained::analyze(&circuit, 17, blake2f_input);
+
+        let listed = findings.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert!(findings.is_empty(), "under-constrained cells:\n{}", listed.join("\n"));
+    }
 }
@@ -0,0 +1,248 @@
+use halo2_proofs::{
+    circuit::{Region, Value},
+    plonk::{Error, Selector},
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use crate::compression::assignment::{assign_xor_slots, get_spread_even, get_spread_old, VARS_PER_ROUND};
+use crate::state::RoundWord;
+use crate::util::xor_maj;
+
+use super::{column_indices, fblamka, row_indices, Argon2Block, Argon2Config, BLOCK_WORDS, COLUMNS, DIAGONALS};
+
+// input state + 4 sub rounds, 16 words of 4 rows each
+pub const ROWS_PER_PERMUTATION: usize = VARS_PER_ROUND * 5;
+// a, b, a ^ b, a & b
+pub const ROWS_PER_XOR: usize = 4 * 4;
+
+impl Argon2Config {
+    pub(super) fn assign_block(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        block: [Value<u64>; BLOCK_WORDS],
+    ) -> Result<Argon2Block, Error> {
+        let mut words = Vec::with_capacity(BLOCK_WORDS);
+
+        for (i, word) in block.into_iter().enumerate() {
+            words.push(self.assign_word(region, || format!("assign block word {i}"), i * 4, word)?);
+        }
+
+        Ok(Argon2Block(words.try_into().unwrap()))
+    }
+
+    pub(super) fn assign_compress(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        x: &Argon2Block,
+        y: &Argon2Block,
+    ) -> Result<Argon2Block, Error> {
+        let mut offset = 0;
+
+        // R = X ^ Y
+        let mut r = Vec::with_capacity(BLOCK_WORDS);
+        for (x, y) in x.0.iter().zip(y.0.iter()) {
+            r.push(self.assign_xor(region, &mut offset, x, y)?);
+        }
+
+        // Q = P(rows), Z = P(columns of Q)
+        let mut q = r.clone();
+        for indices in (0..8).map(row_indices).chain((0..8).map(column_indices)) {
+            let v = indices.map(|i| q[i].clone());
+            let v = self.assign_permutation(region, &mut offset, v)?;
+
+            for (i, v) in indices.into_iter().zip(v) {
+                q[i] = v;
+            }
+        }
+
+        let mut out = Vec::with_capacity(BLOCK_WORDS);
+        for (z, r) in q.iter().zip(r.iter()) {
+            out.push(self.assign_xor(region, &mut offset, z, r)?);
+        }
+
+        Ok(Argon2Block(out.try_into().unwrap()))
+    }
+
+    fn assign_word<A: Fn() -> AR, AR: Into<String>>(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        annotation: A,
+        offset: usize,
+        val: Value<u64>,
+    ) -> Result<RoundWord, Error> {
+        self.s_blamka.s_decompose.enable(region, offset)?;
+        RoundWord::assign_with_lookup(annotation, region, val, offset, self.columns.num, &self.lookup.input)
+    }
+
+    fn copy_word(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: usize,
+        word: &RoundWord,
+    ) -> Result<RoundWord, Error> {
+        self.s_blamka.s_decompose.enable(region, offset)?;
+        word.copy_into_with_lookup(region, offset, &self.columns, &self.lookup.input)
+    }
+
+    fn assign_xor(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: &mut usize,
+        a: &RoundWord,
+        b: &RoundWord,
+    ) -> Result<RoundWord, Error> {
+        let row = *offset;
+
+        self.s_blamka.s_xor.enable(region, row)?;
+
+        self.copy_word(region, row, a)?;
+        self.copy_word(region, row + 4, b)?;
+
+        let even_odd = a.val.value_u64().zip(b.val.value_u64()).map(|(a, b)| xor_maj(a, b, 0));
+
+        let xor = self.assign_word(region, || "a ^ b", row + 8, even_odd.map(|(even, _)| even))?;
+        self.assign_word(region, || "a & b", row + 12, even_odd.map(|(_, odd)| odd))?;
+
+        *offset += ROWS_PER_XOR;
+
+        Ok(xor)
+    }
+
+    fn assign_permutation(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: &mut usize,
+        v: [RoundWord; 16],
+    ) -> Result<[RoundWord; 16], Error> {
+        let columns = &self.columns;
+
+        let mut last_state = Vec::with_capacity(16);
+        for (i, word) in v.iter().enumerate() {
+            last_state.push(self.copy_word(region, *offset + i * 4, word)?);
+        }
+        let mut last_state: [RoundWord; 16] = last_state.try_into().unwrap();
+
+        let mut vs: Value<[u64; 16]> = last_state
+            .iter()
+            .map(|word| word.val.value_u64())
+            .collect::<Value<Vec<_>>>()
+            .map(|vs| vs.try_into().unwrap());
+
+        for sub_round in 1..=4 {
+            let trace = vs.map(|vs| calc_blamka_sub_round(vs, sub_round));
+            vs = trace.map(|(vs, _)| vs);
+
+            for i in 0..16 {
+                let row = *offset + sub_round * VARS_PER_ROUND + i * 4;
+
+                self.get_sub_round_selector(sub_round - 1, i).enable(region, row)?;
+
+                let val = vs.map(|vs| vs[i]);
+                last_state[i] = self.assign_word(region, || "assign new state", row, val)?;
+
+                // xor and rotate: the odd bits and the split limb in the xor slots
+                let (r0, r1) = if sub_round % 2 == 1 { (32, 24) } else { (16, 63) };
+                let rotate = match i {
+                    4..=7 => Some(r1),
+                    12..=15 => Some(r0),
+                    _ => None,
+                };
+
+                match rotate {
+                    Some(rotate) => {
+                        assign_xor_slots(region, &self.lookup.input, row, val, trace.map(|(_, aux)| aux[i][2]), rotate)?;
+
+                        // the xor and rotate gates of BLAKE2f only apply when s_round is one
+                        if let Some(round_columns) = columns.rounds {
+                            region.assign_advice_from_constant(|| "assign s_round 1", round_columns.s_round, row, Fr::one())?;
+                        }
+                    }
+                    // adds: lo32 product, carry
+                    None => {
+                        for k in 0..3 {
+                            region.assign_advice(
+                                || "assign blamka witness",
+                                columns.num,
+                                row + 1 + k,
+                                || trace.map(|(_, aux)| Fr::from_u128(aux[i][k])),
+                            )?;
+                        }
+                    }
+                }
+            }
+        }
+
+        *offset += ROWS_PER_PERMUTATION;
+
+        Ok(last_state)
+    }
+
+    fn get_sub_round_selector(&self, sub_round: usize, i: usize) -> Selector {
+        let diagonal = sub_round >= 2;
+
+        match i {
+            0..=3 if !diagonal => self.s_blamka.s_blamka_offset_neg_12,
+            0..=2 => self.s_blamka.s_blamka_offset_neg_11,
+            3 => self.s_blamka.s_blamka_offset_neg_15,
+
+            8..=11 if !diagonal => self.s_blamka.s_blamka_offset_4,
+            8..=10 => self.s_blamka.s_blamka_offset_5,
+            11 => self.s_blamka.s_blamka_offset_1,
+
+            4..=7 => match sub_round {
+                0 => self.s_r1.s_xor_4_rotate_24,
+                1 => self.s_r2.s_xor_4_rotate_63,
+                2 if i == 7 => self.s_r3.s_xor_1_rotate_24,
+                2 => self.s_r3.s_xor_5_rotate_24,
+                _ if i == 7 => self.s_r4.s_xor_1_rotate_63,
+                _ => self.s_r4.s_xor_5_rotate_63,
+            },
+
+            _ => match sub_round {
+                0 => self.s_r1.s_xor_neg_12_rotate_32,
+                1 => self.s_r2.s_xor_neg_12_rotate_16,
+                2 if i == 15 => self.s_r3.s_xor_neg_15_rotate_32,
+                2 => self.s_r3.s_xor_neg_11_rotate_32,
+                _ if i == 15 => self.s_r4.s_xor_neg_15_rotate_16,
+                _ => self.s_r4.s_xor_neg_11_rotate_16,
+            },
+        }
+    }
+}
+
+// One sub round of the permutation: half of GB on every column (sub rounds 1, 2) or
+// diagonal (sub rounds 3, 4). Returns the new state and, per word, the extra witness of its gate.
+pub fn calc_blamka_sub_round(mut v: [u64; 16], sub_round: usize) -> ([u64; 16], [[u128; 3]; 16]) {
+    assert_ne!(sub_round, 0);
+    assert!(sub_round <= 4);
+
+    let lanes = if sub_round <= 2 { COLUMNS } else { DIAGONALS };
+    let (r0, r1) = if sub_round % 2 == 1 { (32, 24) } else { (16, 63) };
+
+    let mut aux = [[0u128; 3]; 16];
+
+    fn add(v: &mut [u64; 16], a: usize, b: usize) -> [u128; 3] {
+        let product = (v[a] & 0xffffffff) * (v[b] & 0xffffffff);
+        let sum = v[a] as u128 + v[b] as u128 + 2 * product as u128;
+
+        v[a] = fblamka(v[a], v[b]);
+        [product as u128, sum >> 64, 0]
+    }
+
+    fn xor_and_rotate(v: &mut [u64; 16], a: usize, b: usize, rotate: u32) -> [u128; 3] {
+        let [lo, hi] = get_spread_even(v[a], v[b], rotate);
+        let odd = get_spread_old(v[a], v[b]);
+
+        v[a] = (v[a] ^ v[b]).rotate_right(rotate);
+        [hi, lo, odd]
+    }
+
+    for [a, b, c, d] in lanes {
+        aux[a] = add(&mut v, a, b);
+        aux[d] = xor_and_rotate(&mut v, d, a, r0);
+        aux[c] = add(&mut v, c, d);
+        aux[b] = xor_and_rotate(&mut v, b, c, r1);
+    }
+
+    (v, aux)
+}
@@ -0,0 +1,234 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{Chip, Layouter, Value},
+    plonk::{ConstraintSystem, Error},
+};
+use halo2curves::{FieldExt, bn256};
+
+use crate::chip::Columns;
+use crate::compression::gates::{narrow_limbs, BlaMkaGates, SubRound1Gates, SubRound2Gates, SubRound3Gates, SubRound4Gates};
+use crate::spread_table::{SpreadTableChip, SpreadTableConfig};
+use crate::state::RoundWord;
+
+mod assignment;
+#[cfg(test)]
+mod test;
+
+// 1 KiB Argon2 block as 128 little endian u64
+pub const BLOCK_WORDS: usize = 128;
+
+// fBlaMka(x, y) = x + y + 2 * lo32(x) * lo32(y), see RFC 9106 §3.6
+pub fn fblamka(x: u64, y: u64) -> u64 {
+    let product = (x & 0xffffffff) * (y & 0xffffffff);
+    x.wrapping_add(y).wrapping_add(product.wrapping_mul(2))
+}
+
+// half of GB: (32, 24) on the first call, (16, 63) on the second
+fn half_gb(v: &mut [u64; 16], [a, b, c, d]: [usize; 4], r0: u32, r1: u32) {
+    v[a] = fblamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(r0);
+    v[c] = fblamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(r1);
+}
+
+pub(crate) const COLUMNS: [[usize; 4]; 4] = [[0, 4, 8, 12], [1, 5, 9, 13], [2, 6, 10, 14], [3, 7, 11, 15]];
+pub(crate) const DIAGONALS: [[usize; 4]; 4] = [[0, 5, 10, 15], [1, 6, 11, 12], [2, 7, 8, 13], [3, 4, 9, 14]];
+
+// a BLAKE2b round without message words, with fBlaMka as the addition
+pub fn permute(v: &mut [u64; 16]) {
+    for (lanes, rotations) in [(COLUMNS, [(32, 24), (16, 63)]), (DIAGONALS, [(32, 24), (16, 63)])] {
+        for (r0, r1) in rotations {
+            for lane in lanes {
+                half_gb(v, lane, r0, r1);
+            }
+        }
+    }
+}
+
+// word indices of the i-th row / column of the 8x8 matrix of 16-byte registers
+pub(crate) fn row_indices(i: usize) -> [usize; 16] {
+    (0..16).map(|j| 16 * i + j).collect::<Vec<_>>().try_into().unwrap()
+}
+
+pub(crate) fn column_indices(i: usize) -> [usize; 16] {
+    (0..16).map(|j| 2 * i + 16 * (j / 2) + j % 2).collect::<Vec<_>>().try_into().unwrap()
+}
+
+// G(X, Y) of RFC 9106 §3.5: R = X ^ Y, P on rows then columns, Z ^ R
+pub fn compress_block(x: &[u64; BLOCK_WORDS], y: &[u64; BLOCK_WORDS]) -> [u64; BLOCK_WORDS] {
+    let r: Vec<u64> = x.iter().zip(y.iter()).map(|(x, y)| x ^ y).collect();
+    let mut q = r.clone();
+
+    for indices in (0..8).map(row_indices).chain((0..8).map(column_indices)) {
+        let mut v = indices.map(|i| q[i]);
+        permute(&mut v);
+        for (i, v) in indices.into_iter().zip(v) {
+            q[i] = v;
+        }
+    }
+
+    q.iter().zip(r.iter()).map(|(q, r)| q ^ r).collect::<Vec<_>>().try_into().unwrap()
+}
+
+pub struct Argon2<F: FieldExt, CS: Argon2Instructions<F>> {
+    chip: CS,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Argon2Chip: Argon2Instructions<F>> Argon2<F, Argon2Chip> {
+    pub fn new(chip: Argon2Chip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn load_block(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        block: [Value<u64>; BLOCK_WORDS],
+    ) -> Result<Argon2Chip::Block, Error> {
+        self.chip.load_block(layouter, block)
+    }
+
+    pub fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Argon2Chip::Block,
+        y: &Argon2Chip::Block,
+    ) -> Result<Argon2Chip::Block, Error> {
+        self.chip.compress(layouter, x, y)
+    }
+}
+
+pub trait Argon2Instructions<F: FieldExt>: Chip<F> {
+    type Block: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error>;
+
+    fn load_block(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        block: [Value<u64>; BLOCK_WORDS],
+    ) -> Result<Self::Block, Error>;
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Self::Block,
+        y: &Self::Block,
+    ) -> Result<Self::Block, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct Argon2Block(pub [RoundWord; BLOCK_WORDS]);
+
+impl Argon2Block {
+    pub fn value(&self) -> [Value<u64>; BLOCK_WORDS] {
+        self.0.clone().map(|word| word.val.value_u64())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Argon2Config {
+    lookup: SpreadTableConfig,
+    columns: Columns,
+    s_blamka: BlaMkaGates,
+    s_r1: SubRound1Gates,
+    s_r2: SubRound2Gates,
+    s_r3: SubRound3Gates,
+    s_r4: SubRound4Gates,
+}
+
+#[derive(Clone, Debug)]
+pub struct Argon2Chip {
+    config: Argon2Config,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for Argon2Chip {
+    type Config = Argon2Config;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Argon2Chip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let columns = Columns::init(meta);
+
+        let input_dense = meta.advice_column();
+        let input_spread = meta.advice_column();
+
+        for column in [input_dense, input_spread] {
+            meta.enable_equality(column);
+        }
+
+        let mut lookup = SpreadTableChip::configure(meta, input_dense, input_spread);
+        SpreadTableChip::configure_xor(meta, &mut lookup, &narrow_limbs());
+
+        // the xor and rotate gates are shared with BLAKE2f, `s_round` is fixed to one
+        let s_blamka = BlaMkaGates::configure(meta, &columns, &lookup.input);
+        let s_r1 = SubRound1Gates::configure(meta, &columns, &lookup.input);
+        let s_r2 = SubRound2Gates::configure(meta, &columns, &lookup.input);
+        let s_r3 = SubRound3Gates::configure(meta, &columns, &lookup.input);
+        let s_r4 = SubRound4Gates::configure(meta, &columns, &lookup.input);
+
+        Argon2Config { lookup, columns, s_blamka, s_r1, s_r2, s_r3, s_r4 }
+    }
+
+    pub fn load(
+        config: &Argon2Config,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        SpreadTableChip::load(config.lookup.clone(), layouter)
+    }
+}
+
+impl Argon2Instructions<bn256::Fr> for Argon2Chip {
+    type Block = Argon2Block;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<(), Error> {
+        Self::load(&self.config, layouter)
+    }
+
+    fn load_block(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        block: [Value<u64>; BLOCK_WORDS],
+    ) -> Result<Self::Block, Error> {
+        layouter.assign_region(
+            || "argon2 load block",
+            |mut region| self.config.assign_block(&mut region, block),
+        )
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        x: &Self::Block,
+        y: &Self::Block,
+    ) -> Result<Self::Block, Error> {
+        layouter.assign_region(
+            || "argon2 compress",
+            |mut region| self.config.assign_compress(&mut region, x, y),
+        )
+    }
+}
@@ -0,0 +1,145 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::bn256;
+
+use super::{compress_block, fblamka, permute, Argon2, Argon2Chip, Argon2Config, BLOCK_WORDS};
+
+// Argon2id, m = 32 KiB, t = 3, p = 4 (RFC 9106 §5.3): the input block of the first
+// data-independent address block, (pass, lane, slice, m', t, type, counter)
+const ADDRESS_INPUT: [u64; 7] = [0, 0, 0, 32, 3, 2, 1];
+
+// G(0, G(0, ADDRESS_INPUT)), taken from a reference implementation reproducing the RFC tag
+const ADDRESS_BLOCK: [u64; BLOCK_WORDS] = [
+    0x540132317457073a, 0x8db78fabe9d398bb, 0x3be58f11a96a9a2f, 0xeba0b87cbcecb54d,
+    0x4718e59dd4b98da3, 0xb344e1bbe69b1a4b, 0xa5e453687aabaa6a, 0xae337f26915a25e8,
+    0xd2fe0bd157413f7e, 0x52b9c4244b98556d, 0xf42324bf05f10dd7, 0x04196de8ca8e4718,
+    0xb2ef45dc9bc66d00, 0x638ba9f421a150bd, 0x5dda3a64f92e0493, 0x8aa5aa141de62ba5,
+    0x8f162ac88d4cb13e, 0xe3951908c9ff9926, 0x382b430a30454716, 0xe18dba5f0f20f11a,
+    0x780793436894cd01, 0xaff1603ed5da9f25, 0x158c3af3c6f8ed6a, 0x59f7d1a978b5e188,
+    0xd8454db37b257a08, 0x79b77a3d672942c7, 0x8e1e5b1656a7056b, 0x38304090bb5a6b9f,
+    0xcd7b23d62a16ac29, 0x687503bbd7df8913, 0x862c15578e13362e, 0x1ddeba58f2d9021c,
+    0xfc1fa760fbc7b757, 0x09bbd5b73a2bcd73, 0xf28f76e71daad79f, 0xda3c29df838923ab,
+    0x99c3235aeeec56a5, 0x91cdda13bd8444b6, 0x4223e1b5793bb63d, 0xb3f023ec292f6deb,
+    0xf47d64077410b6e0, 0xee3e33b87ecbf0a7, 0x061fdc8f92209a9f, 0xa8168939e5131f20,
+    0xcd02db19c850263a, 0x3f55d1c7768378bd, 0xe425da20d7ad340c, 0x02379ae62c47cf70,
+    0xa3df97c0459ed94b, 0xf36c91b20b4b0fe3, 0x028a0b6acd23616a, 0x4a4fbac22bc1e87a,
+    0xce30a01145312df9, 0xe5d09e1ae5220851, 0x1198b8bf22b009e6, 0x887c492f4251890e,
+    0xa262fb64d15e76b9, 0xfd6ca73da4ffa3bb, 0x158dd645c60ea7cb, 0x927f8f62f56a194e,
+    0x7bb38b877518e111, 0xafcd835aa0515780, 0xef92c6f2674678e8, 0x1cf00812ee42bf9b,
+    0xd0e6c36aa92cea04, 0xc7be1410cf1f4e18, 0x27df6ec01256dfb0, 0xa8e81200773fe678,
+    0x4be914e44cf710ad, 0x79bf0144b2be2fe1, 0x7fdd2fcc1e08e5ae, 0xe4401c6bab6c18b6,
+    0xeaac85071fb19d63, 0x107536cb6a20c964, 0x84c352f86f701b08, 0x396b5d440e9c7f71,
+    0x3cebc648b1eb0061, 0x39ed38c55ceeed79, 0xe30ee84b91998450, 0x959a1ab5604d0e76,
+    0x1cb4d0e58e180b18, 0x7cf278e7f5f84369, 0x89abe9ab4c4fa349, 0x07119f2d452fccbf,
+    0x06898aa579291a29, 0xc7c6bcd746261532, 0xa44bb7199bbbf153, 0x75389ec401a6814a,
+    0x449a0c365b73fbd2, 0x8b49029287c80847, 0x36b7c184fca7a01d, 0x69a2b0939874651b,
+    0xf0a28473591fe116, 0x67c35ac23084eeea, 0x3f0becf496e5a5c8, 0xb6dac3719d68aa83,
+    0x35c28fa1d51f1b44, 0xdb175380978d3f1c, 0x3dac59faeb3241b2, 0x747f5e8d2dd0bba4,
+    0x49de7f336c5d6780, 0x4dc6873e21928d14, 0xaf9dc9a8ae478413, 0xc7e46ffb5e7defb4,
+    0x0b3e015809330f3a, 0xedf32c3c0f569d74, 0xd0898888fb05a896, 0x5773b00d84575b2f,
+    0xdc19f5280d58449c, 0x3285f3979258af96, 0x89bb91f40c0bcb4e, 0x29e6d5a36270fe03,
+    0x185dd748ec8b39e5, 0xfd2347fd1e050f5a, 0xbe66c8640d2a3548, 0x4cbdb873d4d8e0d7,
+    0x85b856327b171715, 0xb41492450edecfcc, 0x0bfba42e7624be68, 0x9892c71418c314c1,
+    0x8572f91208a7e430, 0xfdd2a51d39570137, 0x8c3bc3fc63ed3faa, 0xe613b06d1af90996,
+    0xc6b18079b907767a, 0x9408e9e427c73d1b, 0xeb9670ff0b561f41, 0x7edf5653c4fe3005,
+];
+
+fn address_input() -> [u64; BLOCK_WORDS] {
+    let mut z = [0; BLOCK_WORDS];
+    z[..ADDRESS_INPUT.len()].copy_from_slice(&ADDRESS_INPUT);
+    z
+}
+
+#[derive(Default)]
+struct MyCircuit {
+    input: Vec<u64>,
+    output: Vec<u64>,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = Argon2Config;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        Argon2Chip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Argon2Chip::construct(config);
+        let argon2 = Argon2::new(chip, layouter.namespace(|| "load spread table"))?;
+
+        let to_block = |words: &[u64]| -> [Value<u64>; BLOCK_WORDS] {
+            (0..BLOCK_WORDS)
+                .map(|i| Value::known(words.get(i).copied().unwrap_or_default()))
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap()
+        };
+
+        let zero = argon2.load_block(&mut layouter, [Value::known(0); BLOCK_WORDS])?;
+        let input = argon2.load_block(&mut layouter, to_block(&self.input))?;
+
+        let block = argon2.compress(&mut layouter, &zero, &input)?;
+        let block = argon2.compress(&mut layouter, &zero, &block)?;
+
+        block.value().iter().zip(self.output.iter()).for_each(|(v, ex_v)| {
+            v.map(|v| assert_eq!(v, *ex_v));
+        });
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_fblamka() {
+    assert_eq!(fblamka(1, 2), 7);
+    assert_eq!(fblamka(0xffffffff, 0xffffffff), 18446744065119617024);
+    assert_eq!(fblamka(u64::MAX, u64::MAX), 18446744056529682432);
+}
+
+#[test]
+fn test_permute() {
+    let mut v: [u64; 16] = (0..16).collect::<Vec<_>>().try_into().unwrap();
+    permute(&mut v);
+
+    assert_eq!(
+        v,
+        [
+            4309413561690544949, 10591790002093387956, 4054907587149926025, 12186936810293155319,
+            6987944521133659505, 11961256884274195702, 7717811659818223130, 7145962203480256264,
+            13478285582766688587, 8998272025916050531, 15850783893021893847, 16649433534529282490,
+            16981378724759909900, 5484704648486101607, 13959069891332574114, 5484003764239343556,
+        ]
+    );
+}
+
+#[test]
+fn test_address_block_native() {
+    let zero = [0; BLOCK_WORDS];
+    assert_eq!(compress_block(&zero, &compress_block(&zero, &address_input())), ADDRESS_BLOCK);
+}
+
+#[test]
+fn test_address_block() {
+    let circuit = MyCircuit {
+        input: address_input().to_vec(),
+        output: ADDRESS_BLOCK.to_vec(),
+    };
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    assert_eq!(prover.verify(), Ok(()));
+}
@@ -129,6 +129,13 @@ impl<const LEN: usize> AssignedBits<LEN> {
     }
 }
 
+impl<const LEN: usize> AssignedBits<LEN> {
+    // the value of a word of any width up to 64 bits
+    pub(crate) fn value_word(&self) -> Value<u64> {
+        self.value().map(|bits| lebs2ip::<LEN>(bits))
+    }
+}
+
 impl AssignedBits<16> {
     pub(crate) fn value_u16(&self) -> Value<u16> {
         self.value().map(|v| v.into())
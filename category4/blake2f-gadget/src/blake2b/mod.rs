@@ -0,0 +1,157 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    plonk::Error,
+};
+use halo2curves::FieldExt;
+
+use crate::native::IV;
+use crate::{Blake2fInstructions, MAX_ROUND};
+
+#[cfg(test)]
+mod test;
+
+pub const BLOCK_BYTES: usize = 128;
+pub const BLOCK_WORDS: usize = BLOCK_BYTES / 8;
+
+// The parameter block of RFC 7693 §2.5 and the BLAKE2 paper §2.8, with BLAKE2X's xof_length in
+// bytes 12..16, so `node_offset` must fit in 32 bits when it is set.
+#[derive(Clone, Copy, Debug)]
+pub struct ParamBlock {
+    pub digest_len: u8,
+    pub key_len: u8,
+    pub fanout: u8,
+    pub depth: u8,
+    pub leaf_length: u32,
+    pub node_offset: u64,
+    pub xof_length: u32,
+    pub node_depth: u8,
+    pub inner_length: u8,
+    pub salt: [u8; 16],
+    pub personal: [u8; 16],
+}
+
+impl ParamBlock {
+    // sequential mode
+    pub fn new(digest_len: usize) -> Self {
+        assert!(digest_len > 0 && digest_len <= 64);
+
+        Self {
+            digest_len: digest_len as u8,
+            key_len: 0,
+            fanout: 1,
+            depth: 1,
+            leaf_length: 0,
+            node_offset: 0,
+            xof_length: 0,
+            node_depth: 0,
+            inner_length: 0,
+            salt: [0; 16],
+            personal: [0; 16],
+        }
+    }
+
+    // h0 = IV ^ parameter block
+    pub fn h(&self) -> [u64; 8] {
+        assert!(self.key_len <= 64);
+
+        let mut h = IV;
+        h[0] ^= u64::from_le_bytes([self.digest_len, self.key_len, self.fanout, self.depth, 0, 0, 0, 0])
+            ^ ((self.leaf_length as u64) << 32);
+        h[1] ^= self.node_offset ^ ((self.xof_length as u64) << 32);
+        h[2] ^= self.node_depth as u64 ^ ((self.inner_length as u64) << 8);
+        h[4] ^= u64::from_le_bytes(self.salt[..8].try_into().unwrap());
+        h[5] ^= u64::from_le_bytes(self.salt[8..].try_into().unwrap());
+        h[6] ^= u64::from_le_bytes(self.personal[..8].try_into().unwrap());
+        h[7] ^= u64::from_le_bytes(self.personal[8..].try_into().unwrap());
+        h
+    }
+}
+
+// sequential mode without key, salt or personalization
+pub fn initial_h(digest_len: usize) -> [u64; 8] {
+    ParamBlock::new(digest_len).h()
+}
+
+// the personalization fills the last 16 bytes of the parameter block
+pub fn initial_h_personal(digest_len: usize, personal: &[u8; 16]) -> [u64; 8] {
+    ParamBlock { personal: *personal, ..ParamBlock::new(digest_len) }.h()
+}
+
+// BLAKE2b over message words fixed by the circuit length, built on BLAKE2f compressions
+pub struct Blake2b<F: FieldExt, CS: Blake2fInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Blake2fChip: Blake2fInstructions<F>> Blake2b<F, Blake2fChip> {
+    pub fn new(chip: Blake2fChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            _marker: PhantomData,
+        })
+    }
+
+    // `message` holds `len` bytes as little endian u64 words, the bytes past `len` in the last
+    // word must be zero. The state holds the digest in its first `digest_len` bytes.
+    pub fn hash(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[AssignedCell<F, F>],
+        len: usize,
+        digest_len: usize,
+    ) -> Result<Blake2fChip::State, Error> {
+        hash_blocks(&self.chip, layouter, &self.iv, initial_h(digest_len), message, len)
+    }
+}
+
+// the padded message compressed block by block from `h`
+pub(crate) fn hash_blocks<F: FieldExt, CS: Blake2fInstructions<F>>(
+    chip: &CS,
+    layouter: &mut impl Layouter<F>,
+    iv: &CS::Iv,
+    h: [u64; 8],
+    message: &[AssignedCell<F, F>],
+    len: usize,
+) -> Result<CS::State, Error> {
+    hash_node_blocks(chip, layouter, iv, h, message, len, false)
+}
+
+// `hash_blocks` for a node of a tree hash, the last node flag is set on the final block
+pub(crate) fn hash_node_blocks<F: FieldExt, CS: Blake2fInstructions<F>>(
+    chip: &CS,
+    layouter: &mut impl Layouter<F>,
+    iv: &CS::Iv,
+    h: [u64; 8],
+    message: &[AssignedCell<F, F>],
+    len: usize,
+    last_node: bool,
+) -> Result<CS::State, Error> {
+    assert_eq!(message.len(), (len + 7) / 8);
+
+    let mut state = chip.initial_state(layouter, h)?;
+    let zero = chip.assign_constant(layouter, 0)?;
+
+    // the empty message is one block of zeros
+    let blocks = std::cmp::max(1, (len + BLOCK_BYTES - 1) / BLOCK_BYTES);
+
+    for i in 0..blocks {
+        let last = i == blocks - 1;
+
+        let m: [AssignedCell<F, F>; BLOCK_WORDS] = (0..BLOCK_WORDS)
+            .map(|j| message.get(i * BLOCK_WORDS + j).unwrap_or(&zero).clone())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        let t = if last { len } else { (i + 1) * BLOCK_BYTES };
+        // the first block starts from the initial state, the others from the previous output
+        state = chip.compress_chained(layouter, iv, &state, m, [t as u64, 0], last, last && last_node, MAX_ROUND as u64)?;
+    }
+
+    Ok(state)
+}
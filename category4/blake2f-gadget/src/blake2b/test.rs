@@ -0,0 +1,109 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::bn256;
+
+use super::Blake2b;
+use crate::native::{blake2b, words};
+use crate::{Blake2fChip, Blake2fConfig, Blake2fInstructions};
+
+// `FIXED_ROUNDS` for the layout of `Blake2fChip::configure_fixed_rounds`, otherwise `LANES`
+// lookup pairs
+#[derive(Default)]
+struct MyCircuit<const FIXED_ROUNDS: bool, const LANES: usize> {
+    message: Vec<u8>,
+    digest: Vec<u8>,
+}
+
+impl<const FIXED_ROUNDS: bool, const LANES: usize> Circuit<bn256::Fr> for MyCircuit<FIXED_ROUNDS, LANES> {
+    type Config = Blake2fConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            message: vec![0; self.message.len()],
+            digest: vec![0; self.digest.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        if FIXED_ROUNDS {
+            Blake2fChip::configure_fixed_rounds(meta)
+        } else {
+            Blake2fChip::configure_with_lanes(meta, LANES)
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Blake2fChip::construct(config);
+        let blake2b = Blake2b::new(chip.clone(), layouter.namespace(|| "load spread table"))?;
+
+        let message = words(&self.message)
+            .into_iter()
+            .map(|w| chip.assign_constant(&mut layouter, w))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let state = blake2b.hash(&mut layouter, &message, self.message.len(), self.digest.len())?;
+
+        let expected = words(&self.digest);
+        state.value().iter().zip(expected).for_each(|(h, ex_h)| {
+            h.map(|v| assert_eq!(v, ex_h));
+        });
+
+        Ok(())
+    }
+}
+
+fn run(message: &[u8], digest_len: usize) {
+    run_with::<false, 1>(message, digest_len)
+}
+
+fn run_with<const FIXED_ROUNDS: bool, const LANES: usize>(message: &[u8], digest_len: usize) {
+    let circuit = MyCircuit::<FIXED_ROUNDS, LANES> {
+        message: message.to_vec(),
+        digest: blake2b(message, digest_len),
+    };
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+#[test]
+fn test_blake2b_abc() {
+    run(b"abc", 64);
+}
+
+#[test]
+fn test_blake2b_256_two_blocks() {
+    run(&(0..200).map(|i| i as u8).collect::<Vec<_>>(), 32);
+}
+
+// the second and the third block are chained to the output of the one before
+#[test]
+fn test_blake2b_three_blocks() {
+    run(&(0..300).map(|i| i as u8).collect::<Vec<_>>(), 64);
+}
+
+#[test]
+fn test_blake2b_fixed_rounds() {
+    run_with::<true, 1>(&(0..300).map(|i| i as u8).collect::<Vec<_>>(), 64);
+}
+
+#[test]
+fn test_blake2b_two_lanes() {
+    run_with::<false, 2>(&(0..300).map(|i| i as u8).collect::<Vec<_>>(), 64);
+}
+
+#[test]
+fn test_blake2b_four_lanes() {
+    run_with::<false, 4>(&(0..300).map(|i| i as u8).collect::<Vec<_>>(), 64);
+}
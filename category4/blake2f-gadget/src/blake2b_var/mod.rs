@@ -0,0 +1,460 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use crate::byte_table::{ByteTableChip, ByteTableConfig};
+use crate::blake2b::{initial_h, BLOCK_BYTES, BLOCK_WORDS};
+use crate::transcript::{compose, compose_gate, ROWS_PER_COMPOSE};
+use crate::{Blake2fChip, Blake2fConfig, Blake2fInstructions, Blake2fIv, MAX_ROUND};
+
+#[cfg(test)]
+mod test;
+
+// BLAKE2b of a message whose length is a private cell, bounded by `max_blocks` blocks. Every
+// block is compressed, `t` and `f` follow from the length, and the state after the real last
+// block is selected as the digest.
+pub struct Blake2bVar<F: FieldExt, CS: Blake2bVarInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    max_blocks: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Blake2bVarChip: Blake2bVarInstructions<F>> Blake2bVar<F, Blake2bVarChip> {
+    pub fn new(chip: Blake2bVarChip, mut layouter: impl Layouter<F>, max_blocks: usize) -> Result<Self, Error> {
+        assert!(max_blocks > 0);
+
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            max_blocks,
+            _marker: PhantomData,
+        })
+    }
+
+    // `message` holds `max_blocks * 128` bytes, the ones past `len` must be zero. The digest is
+    // in the first `digest_len` bytes of the returned words.
+    pub fn hash(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+        len: &AssignedCell<F, F>,
+        digest_len: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        assert_eq!(message.len(), self.max_blocks * BLOCK_BYTES);
+
+        let message = self.chip.assign_message(layouter, message, len)?;
+        let one = self.chip.assign_constant(layouter, 1)?;
+
+        let mut h = self.chip.initial_state(layouter, initial_h(digest_len))?;
+        let mut lasts = Vec::with_capacity(self.max_blocks);
+        let mut states = Vec::with_capacity(self.max_blocks);
+
+        for i in 0..self.max_blocks {
+            // the block after the maximum is all padding
+            let pad_next = message.pads.get(i + 1).unwrap_or(&one);
+            let (last, t, f) = self.chip.block_params(layouter, i, &message.pads[i], pad_next, len)?;
+
+            let m = message.words[i * BLOCK_WORDS..(i + 1) * BLOCK_WORDS].to_vec().try_into().unwrap();
+            h = self.chip.compress(layouter, &self.iv, &h, m, &t, &f)?;
+
+            lasts.push(last);
+            states.push(h.clone());
+        }
+
+        let mut digest = self.chip.select(layouter, &lasts, &states)?.to_vec();
+        digest.truncate((digest_len + 7) / 8);
+        Ok(digest)
+    }
+}
+
+// the message words and, for each block, whether it starts in the padding
+#[derive(Clone, Debug)]
+pub struct VarMessage<F: FieldExt> {
+    pub words: Vec<AssignedCell<F, F>>,
+    pub pads: Vec<AssignedCell<F, F>>,
+}
+
+pub trait Blake2bVarInstructions<F: FieldExt>: Chip<F> {
+    // the cells every compression copies
+    type Iv: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
+
+    fn assign_len(&self, layouter: &mut impl Layouter<F>, len: Value<u64>) -> Result<AssignedCell<F, F>, Error>;
+
+    fn assign_constant(&self, layouter: &mut impl Layouter<F>, value: u64) -> Result<AssignedCell<F, F>, Error>;
+
+    // range checked bytes with zeros past `len`, composed into words. Block 0 never starts in
+    // the padding, even for the empty message.
+    fn assign_message(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        bytes: &[Value<u8>],
+        len: &AssignedCell<F, F>,
+    ) -> Result<VarMessage<F>, Error>;
+
+    // last = (1 - pad) * pad_next, t = last ? len : (i + 1) * 128 and f = last ? 2^64 - 1 : 0
+    fn block_params(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        i: usize,
+        pad: &AssignedCell<F, F>,
+        pad_next: &AssignedCell<F, F>,
+        len: &AssignedCell<F, F>,
+    ) -> Result<(AssignedCell<F, F>, AssignedCell<F, F>, AssignedCell<F, F>), Error>;
+
+    fn initial_state(&self, layouter: &mut impl Layouter<F>, h: [u64; 8]) -> Result<[AssignedCell<F, F>; 8], Error>;
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        h: &[AssignedCell<F, F>; 8],
+        m: [AssignedCell<F, F>; BLOCK_WORDS],
+        t: &AssignedCell<F, F>,
+        f: &AssignedCell<F, F>,
+    ) -> Result<[AssignedCell<F, F>; 8], Error>;
+
+    // sum last_i * state_i, exactly one of the flags is set
+    fn select(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lasts: &[AssignedCell<F, F>],
+        states: &[[AssignedCell<F, F>; 8]],
+    ) -> Result<[AssignedCell<F, F>; 8], Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2bVarConfig {
+    blake2f: Blake2fConfig,
+    // byte, is_pad, count and word columns of the message, reused by the other gates
+    advice: [Column<Advice>; 4],
+    byte_table: ByteTableConfig,
+    s_pad: Selector,
+    s_word: Selector,
+    s_block: Selector,
+    s_select: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2bVarChip {
+    config: Blake2bVarConfig,
+    blake2f: Blake2fChip,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for Blake2bVarChip {
+    type Config = Blake2bVarConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Blake2bVarChip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            blake2f: Blake2fChip::construct(config.blake2f.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let blake2f = Blake2fChip::configure(meta);
+
+        let advice = [(); 4].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let [byte, is_pad, count, word] = advice;
+
+        let byte_table = ByteTableChip::configure(meta, byte);
+
+        // the padding flags switch on once, at byte `count`, and zero the bytes after it
+        let s_pad = meta.selector();
+        meta.create_gate("padding", |meta| {
+            let s = meta.query_selector(s_pad);
+            let one = Expression::Constant(Fr::one());
+
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let pad = meta.query_advice(is_pad, Rotation::cur());
+            let pad_prev = meta.query_advice(is_pad, Rotation::prev());
+            let total = meta.query_advice(count, Rotation::cur());
+            let total_prev = meta.query_advice(count, Rotation::prev());
+
+            Constraints::with_selector(
+                s,
+                vec![
+                    pad.clone() * (one.clone() - pad.clone()),
+                    pad_prev * (one.clone() - pad.clone()),
+                    total - total_prev - (one - pad.clone()),
+                    pad * byte,
+                ],
+            )
+        });
+
+        let s_word = meta.selector();
+        compose_gate(meta, "compose word", s_word, word, Fr::from(1 << 8));
+
+        // row 0: pad, pad_next, last, len
+        // row 1: end, t, f
+        let s_block = meta.selector();
+        meta.create_gate("block params", |meta| {
+            let s = meta.query_selector(s_block);
+            let one = Expression::Constant(Fr::one());
+
+            let pad = meta.query_advice(byte, Rotation::cur());
+            let pad_next = meta.query_advice(is_pad, Rotation::cur());
+            let last = meta.query_advice(count, Rotation::cur());
+            let len = meta.query_advice(word, Rotation::cur());
+            let end = meta.query_advice(byte, Rotation::next());
+            let t = meta.query_advice(is_pad, Rotation::next());
+            let f = meta.query_advice(count, Rotation::next());
+
+            Constraints::with_selector(
+                s,
+                vec![
+                    last.clone() - (one.clone() - pad) * pad_next,
+                    t - (last.clone() * len + (one - last.clone()) * end),
+                    f - last * Expression::Constant(Fr::from(u64::MAX)),
+                ],
+            )
+        });
+
+        // acc = acc_prev + last * word
+        let s_select = meta.selector();
+        meta.create_gate("select", |meta| {
+            let s = meta.query_selector(s_select);
+
+            let last = meta.query_advice(byte, Rotation::cur());
+            let word = meta.query_advice(is_pad, Rotation::cur());
+            let acc = meta.query_advice(count, Rotation::cur());
+            let acc_prev = meta.query_advice(count, Rotation::prev());
+
+            Constraints::with_selector(s, vec![acc - acc_prev - last * word])
+        });
+
+        Blake2bVarConfig { blake2f, advice, byte_table, s_pad, s_word, s_block, s_select }
+    }
+
+    pub fn load(
+        config: &Blake2bVarConfig,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        ByteTableChip::load(&config.byte_table, layouter)
+    }
+}
+
+impl Blake2bVarInstructions<bn256::Fr> for Blake2bVarChip {
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        let iv = self.blake2f.initialize(layouter)?;
+        Self::load(&self.config, layouter)?;
+        Ok(iv)
+    }
+
+    fn assign_len(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        len: Value<u64>,
+    ) -> Result<AssignedCell<Fr, Fr>, Error> {
+        layouter.assign_region(|| "blake2b var len", |mut region| {
+            region.assign_advice(|| "assign len", self.config.advice[3], 0, || len.map(Fr::from))
+        })
+    }
+
+    fn assign_constant(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        value: u64,
+    ) -> Result<AssignedCell<Fr, Fr>, Error> {
+        layouter.assign_region(|| "blake2b var constant", |mut region| {
+            region.assign_advice_from_constant(|| "assign constant", self.config.advice[3], 0, Fr::from(value))
+        })
+    }
+
+    fn assign_message(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        bytes: &[Value<u8>],
+        len: &AssignedCell<Fr, Fr>,
+    ) -> Result<VarMessage<Fr>, Error> {
+        let [byte, is_pad, count, word] = self.config.advice;
+        let len_value = len.value().map(|len| len.get_lower_128() as usize);
+
+        let (bytes, pads) = layouter.assign_region(|| "blake2b var message", |mut region| {
+            // row 0 starts the count out of the padding
+            region.assign_advice_from_constant(|| "assign pad", is_pad, 0, Fr::zero())?;
+            let mut total = region.assign_advice_from_constant(|| "assign count", count, 0, Fr::zero())?;
+            let zero = region.assign_advice_from_constant(|| "assign zero", byte, 0, Fr::zero())?;
+
+            let mut assigned = Vec::with_capacity(bytes.len());
+            let mut pads = vec![zero];
+
+            for (i, value) in bytes.iter().enumerate() {
+                let row = i + 1;
+                self.config.byte_table.enable(&mut region, row)?;
+                self.config.s_pad.enable(&mut region, row)?;
+
+                let pad = len_value.map(|len| Fr::from((i >= len) as u64));
+                let pad = region.assign_advice(|| "assign pad", is_pad, row, || pad)?;
+                if i > 0 && i % BLOCK_BYTES == 0 {
+                    pads.push(pad.clone());
+                }
+
+                let value = value.map(|b| Fr::from(b as u64));
+                assigned.push(region.assign_advice(|| "assign byte", byte, row, || value)?);
+
+                let next = total.value().copied() + Value::known(Fr::one()) - pad.value().copied();
+                total = region.assign_advice(|| "assign count", count, row, || next)?;
+            }
+
+            region.constrain_equal(total.cell(), len.cell())?;
+            Ok((assigned, pads))
+        })?;
+
+        let words = layouter.assign_region(|| "blake2b var words", |mut region| {
+            bytes
+                .chunks(8)
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let limbs: Vec<_> = chunk.iter().map(|b| (b.value().copied(), b.cell())).collect();
+                    compose(&mut region, word, i * ROWS_PER_COMPOSE, self.config.s_word, Fr::from(1 << 8), &limbs)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        Ok(VarMessage { words, pads })
+    }
+
+    fn block_params(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        i: usize,
+        pad: &AssignedCell<Fr, Fr>,
+        pad_next: &AssignedCell<Fr, Fr>,
+        len: &AssignedCell<Fr, Fr>,
+    ) -> Result<(AssignedCell<Fr, Fr>, AssignedCell<Fr, Fr>, AssignedCell<Fr, Fr>), Error> {
+        let [c0, c1, c2, c3] = self.config.advice;
+
+        layouter.assign_region(|| "blake2b var block params", |mut region| {
+            self.config.s_block.enable(&mut region, 0)?;
+
+            pad.copy_advice(|| "copy pad", &mut region, c0, 0)?;
+            pad_next.copy_advice(|| "copy next pad", &mut region, c1, 0)?;
+            len.copy_advice(|| "copy len", &mut region, c3, 0)?;
+
+            let end = ((i + 1) * BLOCK_BYTES) as u64;
+            region.assign_advice_from_constant(|| "assign block end", c0, 1, Fr::from(end))?;
+
+            let last = pad.value().zip(pad_next.value()).map(|(pad, next)| (Fr::one() - pad) * next);
+            let last = region.assign_advice(|| "assign last", c2, 0, || last)?;
+
+            let t = last.value().zip(len.value()).map(|(last, len)| if *last == Fr::one() { *len } else { Fr::from(end) });
+            let t = region.assign_advice(|| "assign t", c1, 1, || t)?;
+
+            let f = last.value().map(|last| *last * Fr::from(u64::MAX));
+            let f = region.assign_advice(|| "assign f", c2, 1, || f)?;
+
+            Ok((last, t, f))
+        })
+    }
+
+    fn initial_state(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        h: [u64; 8],
+    ) -> Result<[AssignedCell<Fr, Fr>; 8], Error> {
+        layouter.assign_region(|| "blake2b var initial state", |mut region| {
+            let h = h
+                .iter()
+                .enumerate()
+                .map(|(i, h)| region.assign_advice_from_constant(|| "assign h", self.config.advice[3], i, Fr::from(*h)))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(h.try_into().unwrap())
+        })
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Blake2fIv,
+        h: &[AssignedCell<Fr, Fr>; 8],
+        m: [AssignedCell<Fr, Fr>; BLOCK_WORDS],
+        t: &AssignedCell<Fr, Fr>,
+        f: &AssignedCell<Fr, Fr>,
+    ) -> Result<[AssignedCell<Fr, Fr>; 8], Error> {
+        let zero = self.assign_constant(layouter, 0)?;
+        let rounds = self.assign_constant(layouter, MAX_ROUND as u64)?;
+
+        let state = self.blake2f.compress_cells(layouter, iv, h.clone(), m, [t.clone(), zero], f.clone(), rounds)?;
+
+        // the state words are copied out, so the next block takes them as cells
+        layouter.assign_region(|| "blake2b var state", |mut region| {
+            let h = state
+                .value()
+                .iter()
+                .zip(state.cells())
+                .enumerate()
+                .map(|(i, (word, cell))| {
+                    let word = region.assign_advice(|| "copy state word", self.config.advice[3], i, || word.map(Fr::from))?;
+                    region.constrain_equal(word.cell(), cell)?;
+                    Ok(word)
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            Ok(h.try_into().unwrap())
+        })
+    }
+
+    fn select(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        lasts: &[AssignedCell<Fr, Fr>],
+        states: &[[AssignedCell<Fr, Fr>; 8]],
+    ) -> Result<[AssignedCell<Fr, Fr>; 8], Error> {
+        let [c0, c1, c2, _] = self.config.advice;
+
+        layouter.assign_region(|| "blake2b var select", |mut region| {
+            let mut digest = Vec::with_capacity(8);
+
+            // one accumulation of max_blocks rows per word
+            for j in 0..8 {
+                let offset = j * (lasts.len() + 1);
+                let mut acc = region.assign_advice_from_constant(|| "assign zero", c2, offset, Fr::zero())?;
+
+                for (i, (last, state)) in lasts.iter().zip(states.iter()).enumerate() {
+                    let row = offset + i + 1;
+                    self.config.s_select.enable(&mut region, row)?;
+
+                    last.copy_advice(|| "copy last", &mut region, c0, row)?;
+                    state[j].copy_advice(|| "copy state word", &mut region, c1, row)?;
+
+                    let value = acc.value().copied() + last.value().copied() * state[j].value().copied();
+                    acc = region.assign_advice(|| "assign acc", c2, row, || value)?;
+                }
+
+                digest.push(acc);
+            }
+
+            Ok(digest.try_into().unwrap())
+        })
+    }
+}
@@ -0,0 +1,126 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2curves::bn256;
+
+use super::{Blake2bVar, Blake2bVarChip, Blake2bVarConfig, Blake2bVarInstructions};
+use crate::blake2b::BLOCK_BYTES;
+use crate::native::{blake2b, words};
+
+const MAX_BLOCKS: usize = 2;
+
+struct MyCircuit {
+    // MAX_BLOCKS * 128 bytes, zero padded past `len`
+    message: Vec<u8>,
+    len: u64,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = (Blake2bVarConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            message: vec![0; MAX_BLOCKS * BLOCK_BYTES],
+            len: 0,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (Blake2bVarChip::configure(meta), instance)
+    }
+
+    fn synthesize(
+        &self,
+        (config, instance): Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Blake2bVarChip::construct(config);
+        let blake2b = Blake2bVar::new(chip.clone(), layouter.namespace(|| "load tables"), MAX_BLOCKS)?;
+
+        let len = chip.assign_len(&mut layouter, Value::known(self.len))?;
+        let message = self.message.iter().map(|b| Value::known(*b)).collect::<Vec<_>>();
+
+        let digest = blake2b.hash(&mut layouter, &message, &len, 64)?;
+
+        for (i, word) in digest.iter().enumerate() {
+            layouter.constrain_instance(word.cell(), instance, i)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn run(message: Vec<u8>, len: usize, digest: &[u8]) -> bool {
+    let circuit = MyCircuit { message, len: len as u64 };
+
+    let instance = words(digest).into_iter().map(bn256::Fr::from).collect();
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![instance]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+// the first `len` bytes of 0, 1, 2, .. and zeros up to the maximum
+fn padded(len: usize) -> Vec<u8> {
+    (0..MAX_BLOCKS * BLOCK_BYTES).map(|i| if i < len { i as u8 } else { 0 }).collect()
+}
+
+fn run_len(len: usize) -> bool {
+    let message = padded(len);
+    let digest = blake2b(&message[..len], 64);
+
+    run(message, len, &digest)
+}
+
+#[test]
+fn test_blake2b_var_empty() {
+    assert!(run_len(0));
+}
+
+#[test]
+fn test_blake2b_var_short() {
+    assert!(run_len(3));
+}
+
+// the first block is the last one, the second one is all padding
+#[test]
+fn test_blake2b_var_one_block() {
+    assert!(run_len(BLOCK_BYTES));
+}
+
+#[test]
+fn test_blake2b_var_one_block_and_a_byte() {
+    assert!(run_len(BLOCK_BYTES + 1));
+}
+
+#[test]
+fn test_blake2b_var_max_blocks() {
+    assert!(run_len(MAX_BLOCKS * BLOCK_BYTES));
+}
+
+// the same digest claimed for a shorter length
+#[test]
+fn test_blake2b_var_wrong_len() {
+    let message = padded(BLOCK_BYTES + 1);
+    let digest = blake2b(&message[..BLOCK_BYTES + 1], 64);
+
+    assert!(!run(message, BLOCK_BYTES, &digest));
+}
+
+// a byte past the length would change the last block
+#[test]
+fn test_blake2b_var_dirty_padding() {
+    let mut message = padded(3);
+    let digest = blake2b(&message[..3], 64);
+    message[3] = 1;
+
+    assert!(!run(message, 3, &digest));
+}
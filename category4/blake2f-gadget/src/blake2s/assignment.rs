@@ -0,0 +1,160 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Region},
+    plonk::Error,
+};
+use halo2curves::{FieldExt, bn256};
+
+use crate::{
+    bits::{AssignedBits, Bits},
+    words::{AddTerm, Words},
+};
+
+use super::{Blake2sConfig, Blake2sState, BLOCK_WORDS, IV, LIMBS, SIGMA};
+
+impl Blake2sConfig {
+    fn words(&self) -> Words<'_, 32> {
+        Words {
+            num: self.num,
+            s_decompose: self.gates.s_decompose,
+            s_add: self.gates.s_add(),
+            lookup: &self.lookup.input,
+        }
+    }
+
+    pub(super) fn assign_h(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        h: &[u32; 8],
+    ) -> Result<[AssignedBits<32>; 8], Error> {
+        let mut state = Vec::with_capacity(8);
+
+        for (i, h) in h.iter().enumerate() {
+            let word = region.assign_advice_from_constant(
+                || format!("assign h{i}"),
+                self.num,
+                i,
+                Bits::<32>::from(*h),
+            )?;
+            state.push(AssignedBits(word));
+        }
+
+        Ok(state.try_into().unwrap())
+    }
+
+    pub(super) fn assign_compress(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        state: &Blake2sState,
+        m: &[AssignedCell<bn256::Fr, bn256::Fr>; BLOCK_WORDS],
+        t: u64,
+        f: bool,
+    ) -> Result<Blake2sState, Error> {
+        let words = self.words();
+        let mut offset = 0;
+
+        // the message words are decomposed once, every G copies them as terms
+        let mut message = Vec::with_capacity(BLOCK_WORDS);
+        for (i, cell) in m.iter().enumerate() {
+            let val = cell.value().map(|v| v.get_lower_128() as u64);
+            let word = words.assign_word(region, || format!("assign m{i}"), offset, val)?;
+            region.constrain_equal(word.cell(), cell.cell())?;
+
+            message.push(word);
+            offset += LIMBS;
+        }
+
+        // v[12..16] are constants once t and f are fixed
+        let mut iv = IV;
+        iv[4] ^= t as u32;
+        iv[5] ^= (t >> 32) as u32;
+        if f {
+            iv[6] = !iv[6];
+        }
+
+        let mut v = state.0.to_vec();
+        for (i, iv) in iv.iter().enumerate() {
+            let word = region.assign_advice_from_constant(
+                || format!("assign v{}", i + 8),
+                self.num,
+                offset,
+                Bits::<32>::from(*iv),
+            )?;
+            v.push(AssignedBits(word));
+            offset += 1;
+        }
+
+        for s in SIGMA.iter() {
+            self.assign_g(region, &mut offset, &mut v, [0, 4, 8, 12], &message[s[0]], &message[s[1]])?;
+            self.assign_g(region, &mut offset, &mut v, [1, 5, 9, 13], &message[s[2]], &message[s[3]])?;
+            self.assign_g(region, &mut offset, &mut v, [2, 6, 10, 14], &message[s[4]], &message[s[5]])?;
+            self.assign_g(region, &mut offset, &mut v, [3, 7, 11, 15], &message[s[6]], &message[s[7]])?;
+            self.assign_g(region, &mut offset, &mut v, [0, 5, 10, 15], &message[s[8]], &message[s[9]])?;
+            self.assign_g(region, &mut offset, &mut v, [1, 6, 11, 12], &message[s[10]], &message[s[11]])?;
+            self.assign_g(region, &mut offset, &mut v, [2, 7, 8, 13], &message[s[12]], &message[s[13]])?;
+            self.assign_g(region, &mut offset, &mut v, [3, 4, 9, 14], &message[s[14]], &message[s[15]])?;
+        }
+
+        // h' = h ^ v[0..8] ^ v[8..16]
+        let mut hs = Vec::with_capacity(8);
+        for (i, h) in state.0.iter().enumerate() {
+            let (xor, _) = words.assign_xor_maj(region, &mut offset, self.gates.s_xor_3, [h, &v[i], &v[i + 8]])?;
+            hs.push(xor);
+        }
+
+        Ok(Blake2sState(hs.try_into().unwrap()))
+    }
+
+    // G of RFC 7693 §3.1 with R1..R4 = 16, 12, 8, 7
+    fn assign_g(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: &mut usize,
+        v: &mut [AssignedBits<32>],
+        [a, b, c, d]: [usize; 4],
+        x: &AssignedBits<32>,
+        y: &AssignedBits<32>,
+    ) -> Result<(), Error> {
+        let words = self.words();
+        let [r1, r2, r3, r4] = &self.gates.rotr;
+
+        v[a] = words.assign_add(region, offset, &[AddTerm::Word(&v[a]), AddTerm::Word(&v[b]), AddTerm::Word(x)])?;
+        let xor = self.assign_xor(region, offset, &v[d], &v[a])?;
+        v[d] = words.assign_sigma(region, offset, r1, &xor)?;
+        v[c] = words.assign_add(region, offset, &[AddTerm::Word(&v[c]), AddTerm::Word(&v[d])])?;
+        let xor = self.assign_xor(region, offset, &v[b], &v[c])?;
+        v[b] = words.assign_sigma(region, offset, r2, &xor)?;
+
+        v[a] = words.assign_add(region, offset, &[AddTerm::Word(&v[a]), AddTerm::Word(&v[b]), AddTerm::Word(y)])?;
+        let xor = self.assign_xor(region, offset, &v[d], &v[a])?;
+        v[d] = words.assign_sigma(region, offset, r3, &xor)?;
+        v[c] = words.assign_add(region, offset, &[AddTerm::Word(&v[c]), AddTerm::Word(&v[d])])?;
+        let xor = self.assign_xor(region, offset, &v[b], &v[c])?;
+        v[b] = words.assign_sigma(region, offset, r4, &xor)?;
+
+        Ok(())
+    }
+
+    fn assign_xor(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: &mut usize,
+        a: &AssignedBits<32>,
+        b: &AssignedBits<32>,
+    ) -> Result<AssignedBits<32>, Error> {
+        let words = self.words();
+        let row = *offset;
+
+        self.gates.s_xor.enable(region, row)?;
+
+        words.copy_word(region, row, a)?;
+        words.copy_word(region, row + LIMBS, b)?;
+
+        let ab = a.value_word().zip(b.value_word());
+        let xor = words.assign_word(region, || "a ^ b", row + 2 * LIMBS, ab.map(|(a, b)| a ^ b))?;
+        words.assign_word(region, || "a & b", row + 3 * LIMBS, ab.map(|(a, b)| a & b))?;
+
+        *offset += 4 * LIMBS;
+
+        Ok(xor)
+    }
+}
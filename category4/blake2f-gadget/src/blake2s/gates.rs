@@ -0,0 +1,94 @@
+use halo2_proofs::{
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Expression, Selector},
+};
+use halo2curves::bn256::{self, Fr};
+
+use crate::sha256::gates::{add_gate, decompose_gate, maj_gate, query_word, sigma_gate, SigmaGate, SigmaLayout, Shift};
+use crate::spread_table::SpreadInputs;
+
+use super::{LIMBS, WORD_BITS};
+
+// the rotations of G (RFC 7693 §3.1), each a sigma gate with a single shift
+pub const ROTATIONS: [usize; 4] = [16, 12, 8, 7];
+
+#[derive(Clone, Debug)]
+pub struct Blake2sGates {
+    pub s_decompose: Selector,
+
+    pub s_xor: Selector,
+    // h ^ v[i] ^ v[i + 8], the even half of maj
+    pub s_xor_3: Selector,
+
+    pub rotr: [SigmaGate; 4],
+
+    pub s_add_2: Selector,
+    pub s_add_3: Selector,
+}
+
+impl Blake2sGates {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        num: Column<Advice>,
+        lookup: &SpreadInputs,
+    ) -> Self {
+        let s_decompose = meta.selector();
+        let s_xor = meta.selector();
+        let s_xor_3 = meta.selector();
+        let s_add_2 = meta.selector();
+        let s_add_3 = meta.selector();
+
+        decompose_gate("decompose a u32 to 2 * u16", meta, s_decompose, num, lookup, LIMBS);
+
+        xor_gate("xor", meta, s_xor, lookup, WORD_BITS);
+        maj_gate("xor 3 words", meta, s_xor_3, lookup, WORD_BITS);
+
+        // x >>> r cuts x at r, the odd word of the single shifted spread is zero
+        let rotr = ROTATIONS.map(|r| sigma_gate("rotr", meta, num, lookup, SigmaLayout::new(WORD_BITS, &[Shift::Rotr(r)])));
+
+        add_gate::<2>("add 2 words", meta, s_add_2, num, lookup, WORD_BITS);
+        add_gate::<3>("add 3 words", meta, s_add_3, num, lookup, WORD_BITS);
+
+        Self {
+            s_decompose,
+            s_xor,
+            s_xor_3,
+            rotr,
+            s_add_2,
+            s_add_3,
+        }
+    }
+
+    // the add gates by their number of terms, see `Words::s_add`
+    pub(crate) fn s_add(&self) -> [Option<Selector>; 8] {
+        let mut s_add = [None; 8];
+        s_add[2] = Some(self.s_add_2);
+        s_add[3] = Some(self.s_add_3);
+        s_add
+    }
+}
+
+// row       | dense/spread
+// 0         | a
+// limbs     | b
+// 2 * limbs | a ^ b
+// 3 * limbs | a & b
+pub(crate) fn xor_gate(
+    name: &'static str,
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    selector: Selector,
+    lookup: &SpreadInputs,
+    word_bits: usize,
+) {
+    let limbs = word_bits / 16;
+
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+
+        let (_, a) = query_word(meta, lookup, 0, limbs);
+        let (_, b) = query_word(meta, lookup, limbs, limbs);
+        let (_, even) = query_word(meta, lookup, 2 * limbs, limbs);
+        let (_, odd) = query_word(meta, lookup, 3 * limbs, limbs);
+
+        Constraints::with_selector(s, vec![a + b - even - odd * Expression::Constant(Fr::from(2))])
+    });
+}
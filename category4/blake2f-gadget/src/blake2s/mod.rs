@@ -0,0 +1,236 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Cell, Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed},
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use crate::bits::AssignedBits;
+use crate::spread_table::{SpreadTableChip, SpreadTableConfig};
+
+mod assignment;
+mod gates;
+#[cfg(test)]
+pub(crate) mod test;
+
+use gates::Blake2sGates;
+
+pub(crate) const WORD_BITS: usize = 32;
+pub(crate) const LIMBS: usize = WORD_BITS / 16;
+pub(crate) const ROUNDS: usize = 10;
+pub const BLOCK_BYTES: usize = 64;
+pub const BLOCK_WORDS: usize = BLOCK_BYTES / 4;
+
+// same IV as SHA-256 (RFC 7693 §2.6)
+pub(crate) const IV: [u32; 8] = crate::sha256::IV;
+
+// the BLAKE2b permutations, of which BLAKE2s uses all 10 once
+pub(crate) const SIGMA: [[usize; 16]; ROUNDS] = crate::native::SIGMA;
+
+// h0 = IV ^ parameter block, sequential mode without key, salt or personalization
+pub fn initial_h(digest_len: usize) -> [u32; 8] {
+    assert!(digest_len > 0 && digest_len <= 32);
+
+    let mut h = IV;
+    h[0] ^= 0x01010000 ^ digest_len as u32;
+    h
+}
+
+// BLAKE2s over message words fixed by the circuit length
+pub struct Blake2s<F: FieldExt, CS: Blake2sInstructions<F>> {
+    chip: CS,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Blake2sChip: Blake2sInstructions<F>> Blake2s<F, Blake2sChip> {
+    pub fn new(chip: Blake2sChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            _marker: PhantomData,
+        })
+    }
+
+    // `message` holds `len` bytes as little endian u32 words, the bytes past `len` in the last
+    // word must be zero. The state holds the digest in its first `digest_len` bytes.
+    pub fn hash(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[AssignedCell<F, F>],
+        len: usize,
+        digest_len: usize,
+    ) -> Result<Blake2sChip::State, Error> {
+        hash_blocks(&self.chip, layouter, initial_h(digest_len), message, len)
+    }
+}
+
+// the padded message compressed block by block from `h`
+pub(crate) fn hash_blocks<F: FieldExt, CS: Blake2sInstructions<F>>(
+    chip: &CS,
+    layouter: &mut impl Layouter<F>,
+    h: [u32; 8],
+    message: &[AssignedCell<F, F>],
+    len: usize,
+) -> Result<CS::State, Error> {
+    assert_eq!(message.len(), (len + 3) / 4);
+
+    let mut state = chip.initial_state(layouter, h)?;
+    let zero = chip.assign_constant(layouter, 0)?;
+
+    // the empty message is one block of zeros
+    let blocks = std::cmp::max(1, (len + BLOCK_BYTES - 1) / BLOCK_BYTES);
+
+    for i in 0..blocks {
+        let last = i == blocks - 1;
+
+        let m: [AssignedCell<F, F>; BLOCK_WORDS] = (0..BLOCK_WORDS)
+            .map(|j| message.get(i * BLOCK_WORDS + j).unwrap_or(&zero).clone())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        let t = if last { len } else { (i + 1) * BLOCK_BYTES };
+        state = chip.compress_assigned(layouter, &state, m, t as u64, last)?;
+    }
+
+    Ok(state)
+}
+
+pub trait Blake2sInstructions<F: FieldExt>: Chip<F> {
+    type State: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error>;
+
+    // h fixed by the circuit, e.g. the IV xored with a parameter block
+    fn initial_state(&self, layouter: &mut impl Layouter<F>, h: [u32; 8]) -> Result<Self::State, Error>;
+
+    fn assign_constant(&self, layouter: &mut impl Layouter<F>, word: u32) -> Result<AssignedCell<F, F>, Error>;
+
+    // the message words are range checked to 32 bits, `t` and `f` are constants of the circuit
+    fn compress_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        h: &Self::State,
+        m: [AssignedCell<F, F>; BLOCK_WORDS],
+        t: u64,
+        f: bool,
+    ) -> Result<Self::State, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2sState(pub [AssignedBits<32>; 8]);
+
+impl Blake2sState {
+    pub fn cells(&self) -> [Cell; 8] {
+        self.0.clone().map(|word| word.cell())
+    }
+
+    pub fn value(&self) -> [Value<u32>; 8] {
+        self.0.clone().map(|word| word.value_u32())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2sConfig {
+    lookup: SpreadTableConfig,
+    num: Column<Advice>,
+    constants: Column<Fixed>,
+    gates: Blake2sGates,
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2sChip {
+    config: Blake2sConfig,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for Blake2sChip {
+    type Config = Blake2sConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Blake2sChip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let num = meta.advice_column();
+        let constants = meta.fixed_column();
+
+        let input_dense = meta.advice_column();
+        let input_spread = meta.advice_column();
+
+        meta.enable_equality(num);
+        meta.enable_constant(constants);
+
+        // the 32-bit xor, rotation and addition gates of SHA-256 on a fresh spread table
+        let lookup = SpreadTableChip::configure(meta, input_dense, input_spread);
+        let gates = Blake2sGates::configure(meta, num, &lookup.input);
+
+        Blake2sConfig { lookup, num, constants, gates }
+    }
+
+    pub fn load(
+        config: &Blake2sConfig,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        SpreadTableChip::load(config.lookup.clone(), layouter)
+    }
+}
+
+impl Blake2sInstructions<bn256::Fr> for Blake2sChip {
+    type State = Blake2sState;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<(), Error> {
+        Self::load(&self.config, layouter)
+    }
+
+    fn initial_state(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        h: [u32; 8],
+    ) -> Result<Self::State, Error> {
+        layouter.assign_region(|| "blake2s initial state", |mut region| {
+            self.config.assign_h(&mut region, &h).map(Blake2sState)
+        })
+    }
+
+    fn assign_constant(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        word: u32,
+    ) -> Result<AssignedCell<bn256::Fr, bn256::Fr>, Error> {
+        layouter.assign_region(|| "blake2s constant", |mut region| {
+            region.assign_advice_from_constant(|| "assign constant", self.config.num, 0, Fr::from(word as u64))
+        })
+    }
+
+    fn compress_assigned(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        h: &Self::State,
+        m: [AssignedCell<bn256::Fr, bn256::Fr>; BLOCK_WORDS],
+        t: u64,
+        f: bool,
+    ) -> Result<Self::State, Error> {
+        layouter.assign_region(
+            || "blake2s compress",
+            |mut region| self.config.assign_compress(&mut region, h, &m, t, f),
+        )
+    }
+}
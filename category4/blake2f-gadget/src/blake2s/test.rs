@@ -0,0 +1,155 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::bn256;
+
+use super::{initial_h, Blake2s, Blake2sChip, Blake2sConfig, Blake2sInstructions, BLOCK_BYTES, BLOCK_WORDS, IV, SIGMA};
+
+// native F of RFC 7693 §3.2 with the BLAKE2s word size
+pub(crate) fn compress(h: &mut [u32; 8], m: &[u32; BLOCK_WORDS], t: u64, f: bool) {
+    let mut v = [0u32; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+
+    v[12] ^= t as u32;
+    v[13] ^= (t >> 32) as u32;
+    if f {
+        v[14] = !v[14];
+    }
+
+    fn g(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(12);
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+        v[d] = (v[d] ^ v[a]).rotate_right(8);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(7);
+    }
+
+    for s in SIGMA.iter() {
+        g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+pub(crate) fn words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks(4)
+        .map(|chunk| chunk.iter().rev().fold(0u32, |acc, b| (acc << 8) | *b as u32))
+        .collect()
+}
+
+// native BLAKE2s without key
+pub(crate) fn blake2s(message: &[u8], digest_len: usize) -> Vec<u8> {
+    let mut h = initial_h(digest_len);
+
+    let blocks = std::cmp::max(1, (message.len() + BLOCK_BYTES - 1) / BLOCK_BYTES);
+    for i in 0..blocks {
+        let last = i == blocks - 1;
+        let chunk = &message[i * BLOCK_BYTES..std::cmp::min(message.len(), (i + 1) * BLOCK_BYTES)];
+
+        let mut m = [0u32; BLOCK_WORDS];
+        for (m, w) in m.iter_mut().zip(words(chunk)) {
+            *m = w;
+        }
+
+        let t = if last { message.len() } else { (i + 1) * BLOCK_BYTES };
+        compress(&mut h, &m, t as u64, last);
+    }
+
+    h.iter().flat_map(|h| h.to_le_bytes()).take(digest_len).collect()
+}
+
+#[derive(Default)]
+struct MyCircuit {
+    message: Vec<u8>,
+    digest: Vec<u8>,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = Blake2sConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            message: vec![0; self.message.len()],
+            digest: vec![0; self.digest.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        Blake2sChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Blake2sChip::construct(config);
+        let blake2s = Blake2s::new(chip.clone(), layouter.namespace(|| "load spread table"))?;
+
+        let message = words(&self.message)
+            .into_iter()
+            .map(|w| chip.assign_constant(&mut layouter, w))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let state = blake2s.hash(&mut layouter, &message, self.message.len(), self.digest.len())?;
+
+        let expected = words(&self.digest);
+        state.value().iter().zip(expected).for_each(|(h, ex_h)| {
+            h.map(|v| assert_eq!(v, ex_h));
+        });
+
+        Ok(())
+    }
+}
+
+fn run(message: &[u8], digest_len: usize) {
+    let circuit = MyCircuit {
+        message: message.to_vec(),
+        digest: blake2s(message, digest_len),
+    };
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+// RFC 7693 Appendix B
+#[test]
+fn test_native_blake2s() {
+    assert_eq!(
+        blake2s(b"abc", 32),
+        [
+            0x50, 0x8C, 0x5E, 0x8C, 0x32, 0x7C, 0x14, 0xE2, 0xE1, 0xA7, 0x2B, 0xA3, 0x4E, 0xEB, 0x45, 0x2F,
+            0x37, 0x45, 0x8B, 0x20, 0x9E, 0xD6, 0x3A, 0x29, 0x4D, 0x99, 0x9B, 0x4C, 0x86, 0x67, 0x59, 0x82,
+        ]
+    );
+}
+
+#[test]
+fn test_blake2s_abc() {
+    run(b"abc", 32);
+}
+
+#[test]
+fn test_blake2s_128_two_blocks() {
+    run(&(0..100).map(|i| i as u8).collect::<Vec<_>>(), 16);
+}
@@ -0,0 +1,303 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use crate::blake2b::{hash_node_blocks, ParamBlock, BLOCK_BYTES, BLOCK_WORDS};
+use crate::{Blake2fChip, Blake2fConfig, Blake2fInstructions, Blake2fIv};
+
+#[cfg(test)]
+mod test;
+
+// BLAKE2bp hashes 4 interleaved stripes of blocks
+pub const PARALLELISM: usize = 4;
+
+// leaf digests and BLAKE2Xb output blocks are full BLAKE2b-512 digests
+const INNER_BYTES: usize = 64;
+const INNER_WORDS: usize = INNER_BYTES / 8;
+
+// BLAKE2bp of the BLAKE2 paper §2.9 over message words fixed by the circuit length. Leaf `i`
+// hashes blocks i, i + 4, .. and the root hashes the 4 leaf digests.
+pub struct Blake2bp<F: FieldExt, CS: Blake2TreeInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Blake2TreeChip: Blake2TreeInstructions<F>> Blake2bp<F, Blake2TreeChip> {
+    pub fn new(chip: Blake2TreeChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            _marker: PhantomData,
+        })
+    }
+
+    // `key` and `message` hold `key_len` and `len` bytes as little endian u64 words, the bytes
+    // past the length in their last word must be zero. The digest is in the first `digest_len`
+    // bytes of the returned words.
+    pub fn hash(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        key: &[AssignedCell<F, F>],
+        key_len: usize,
+        message: &[AssignedCell<F, F>],
+        len: usize,
+        digest_len: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        assert_eq!(message.len(), (len + 7) / 8);
+
+        let key_block = key_block(&self.chip, layouter, key, key_len)?;
+        let blocks = (len + BLOCK_BYTES - 1) / BLOCK_BYTES;
+
+        let mut leaves = Vec::with_capacity(PARALLELISM * INNER_WORDS);
+        for i in 0..PARALLELISM {
+            let param = ParamBlock {
+                key_len: key_len as u8,
+                fanout: PARALLELISM as u8,
+                depth: 2,
+                node_offset: i as u64,
+                inner_length: INNER_BYTES as u8,
+                ..ParamBlock::new(digest_len)
+            };
+
+            // only the last block of the message can be partial, and it ends its stripe
+            let mut stripe = key_block.clone();
+            let mut stripe_len = key_len_bytes(key_len);
+            for b in (i..blocks).step_by(PARALLELISM) {
+                stripe.extend_from_slice(&message[b * BLOCK_WORDS..std::cmp::min(message.len(), (b + 1) * BLOCK_WORDS)]);
+                stripe_len += std::cmp::min(BLOCK_BYTES, len - b * BLOCK_BYTES);
+            }
+
+            // a leaf outputs its inner length whatever the digest length
+            leaves.extend(self.chip.hash_node(layouter, &self.iv, &param, &stripe, stripe_len, i == PARALLELISM - 1)?);
+        }
+
+        // the root takes the key length but not the key block
+        let param = ParamBlock {
+            key_len: key_len as u8,
+            fanout: PARALLELISM as u8,
+            depth: 2,
+            node_depth: 1,
+            inner_length: INNER_BYTES as u8,
+            ..ParamBlock::new(digest_len)
+        };
+        let mut root = self.chip.hash_node(layouter, &self.iv, &param, &leaves, PARALLELISM * INNER_BYTES, true)?;
+
+        root.truncate((digest_len + 7) / 8);
+        Ok(root)
+    }
+}
+
+// BLAKE2Xb, the XOF of the BLAKE2X paper: H0 is BLAKE2b-512 with `xof_length` in its parameter
+// block, and output block `i` is BLAKE2b of H0 at node offset `i`.
+pub struct Blake2xb<F: FieldExt, CS: Blake2TreeInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Blake2TreeChip: Blake2TreeInstructions<F>> Blake2xb<F, Blake2TreeChip> {
+    pub fn new(chip: Blake2TreeChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            _marker: PhantomData,
+        })
+    }
+
+    // Inputs as in `Blake2bp::hash`. The output is `out_len` bytes as little endian words, the
+    // last one only holds the bytes up to `out_len`.
+    pub fn hash(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        key: &[AssignedCell<F, F>],
+        key_len: usize,
+        message: &[AssignedCell<F, F>],
+        len: usize,
+        out_len: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        // 2^32 - 1 stands for an unknown length, which a circuit never has
+        assert!(out_len > 0 && out_len < u32::MAX as usize);
+
+        let key_block = key_block(&self.chip, layouter, key, key_len)?;
+
+        let param = ParamBlock {
+            key_len: key_len as u8,
+            xof_length: out_len as u32,
+            ..ParamBlock::new(INNER_BYTES)
+        };
+        let h0 = self.chip.hash_node(layouter, &self.iv, &param, &[key_block, message.to_vec()].concat(), key_len_bytes(key_len) + len, false)?;
+
+        let mut out = Vec::with_capacity((out_len + 7) / 8);
+        for i in 0..(out_len + INNER_BYTES - 1) / INNER_BYTES {
+            let param = ParamBlock {
+                fanout: 0,
+                depth: 0,
+                leaf_length: INNER_BYTES as u32,
+                node_offset: i as u64,
+                xof_length: out_len as u32,
+                inner_length: INNER_BYTES as u8,
+                ..ParamBlock::new(std::cmp::min(INNER_BYTES, out_len - i * INNER_BYTES))
+            };
+
+            let block = self.chip.hash_node(layouter, &self.iv, &param, &h0, INNER_BYTES, false)?;
+            out.extend(block.into_iter().take((param.digest_len as usize + 7) / 8));
+        }
+
+        Ok(out)
+    }
+}
+
+// a key is a block of its own, zero padded
+fn key_len_bytes(key_len: usize) -> usize {
+    if key_len > 0 { BLOCK_BYTES } else { 0 }
+}
+
+fn key_block<F: FieldExt, CS: Blake2TreeInstructions<F>>(
+    chip: &CS,
+    layouter: &mut impl Layouter<F>,
+    key: &[AssignedCell<F, F>],
+    key_len: usize,
+) -> Result<Vec<AssignedCell<F, F>>, Error> {
+    assert!(key_len <= 64);
+    assert_eq!(key.len(), (key_len + 7) / 8);
+
+    if key_len == 0 {
+        return Ok(vec![]);
+    }
+
+    let zero = chip.assign_constant(layouter, 0)?;
+    Ok((0..BLOCK_WORDS).map(|i| key.get(i).unwrap_or(&zero).clone()).collect())
+}
+
+pub trait Blake2TreeInstructions<F: FieldExt>: Chip<F> {
+    // the cells every compression copies
+    type Iv: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
+
+    fn assign_words(&self, layouter: &mut impl Layouter<F>, words: &[Value<u64>]) -> Result<Vec<AssignedCell<F, F>>, Error>;
+
+    fn assign_constant(&self, layouter: &mut impl Layouter<F>, word: u64) -> Result<AssignedCell<F, F>, Error>;
+
+    // BLAKE2b of one tree node from its parameter block, as the 8 words of the final state
+    fn hash_node(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        param: &ParamBlock,
+        message: &[AssignedCell<F, F>],
+        len: usize,
+        last_node: bool,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2TreeConfig {
+    blake2f: Blake2fConfig,
+    word: Column<Advice>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2TreeChip {
+    config: Blake2TreeConfig,
+    blake2f: Blake2fChip,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for Blake2TreeChip {
+    type Config = Blake2TreeConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Blake2TreeChip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            blake2f: Blake2fChip::construct(config.blake2f.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let blake2f = Blake2fChip::configure(meta);
+
+        let word = meta.advice_column();
+        meta.enable_equality(word);
+
+        Blake2TreeConfig { blake2f, word }
+    }
+}
+
+impl Blake2TreeInstructions<bn256::Fr> for Blake2TreeChip {
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        self.blake2f.initialize(layouter)
+    }
+
+    fn assign_words(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        words: &[Value<u64>],
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        layouter.assign_region(|| "blake2 tree words", |mut region| {
+            words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| region.assign_advice(|| "assign word", self.config.word, i, || word.map(Fr::from)))
+                .collect()
+        })
+    }
+
+    fn assign_constant(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        word: u64,
+    ) -> Result<AssignedCell<Fr, Fr>, Error> {
+        self.blake2f.assign_constant(layouter, word)
+    }
+
+    fn hash_node(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Blake2fIv,
+        param: &ParamBlock,
+        message: &[AssignedCell<Fr, Fr>],
+        len: usize,
+        last_node: bool,
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        let state = hash_node_blocks(&self.blake2f, layouter, iv, param.h(), message, len, last_node)?;
+
+        // the digest words are copied out of the state, so they can be hashed again
+        layouter.assign_region(|| "blake2 tree digest", |mut region| {
+            state
+                .value()
+                .iter()
+                .zip(state.cells())
+                .enumerate()
+                .map(|(i, (word, cell))| {
+                    let word = region.assign_advice(|| "copy digest word", self.config.word, i, || word.map(Fr::from))?;
+                    region.constrain_equal(word.cell(), cell)?;
+                    Ok(word)
+                })
+                .collect()
+        })
+    }
+}
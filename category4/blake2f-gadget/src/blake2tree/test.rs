@@ -0,0 +1,233 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2curves::bn256;
+
+use super::{Blake2TreeChip, Blake2TreeConfig, Blake2TreeInstructions, Blake2bp, Blake2xb, INNER_BYTES, PARALLELISM};
+use crate::blake2b::{ParamBlock, BLOCK_BYTES};
+use crate::native::{hash_node, words};
+
+// a key is hashed as a zero padded block of its own
+fn key_block(key: &[u8]) -> Vec<u8> {
+    let mut block = key.to_vec();
+    if !key.is_empty() {
+        block.resize(BLOCK_BYTES, 0);
+    }
+    block
+}
+
+// native BLAKE2bp of blake2bp-ref.c
+fn blake2bp(message: &[u8], key: &[u8], digest_len: usize) -> Vec<u8> {
+    let mut leaves = vec![];
+    for i in 0..PARALLELISM {
+        let param = ParamBlock {
+            key_len: key.len() as u8,
+            fanout: PARALLELISM as u8,
+            depth: 2,
+            node_offset: i as u64,
+            inner_length: INNER_BYTES as u8,
+            ..ParamBlock::new(digest_len)
+        };
+
+        let stripe: Vec<u8> = message.chunks(BLOCK_BYTES).skip(i).step_by(PARALLELISM).flatten().copied().collect();
+        leaves.extend(hash_node(param.h(), &[key_block(key), stripe].concat(), INNER_BYTES, i == PARALLELISM - 1));
+    }
+
+    let param = ParamBlock {
+        key_len: key.len() as u8,
+        fanout: PARALLELISM as u8,
+        depth: 2,
+        node_depth: 1,
+        inner_length: INNER_BYTES as u8,
+        ..ParamBlock::new(digest_len)
+    };
+    hash_node(param.h(), &leaves, digest_len, true)
+}
+
+// native BLAKE2Xb of blake2xb-ref.c
+fn blake2xb(message: &[u8], key: &[u8], out_len: usize) -> Vec<u8> {
+    let param = ParamBlock {
+        key_len: key.len() as u8,
+        xof_length: out_len as u32,
+        ..ParamBlock::new(INNER_BYTES)
+    };
+    let h0 = hash_node(param.h(), &[key_block(key), message.to_vec()].concat(), INNER_BYTES, false);
+
+    let mut out = vec![];
+    for i in 0..(out_len + INNER_BYTES - 1) / INNER_BYTES {
+        let param = ParamBlock {
+            fanout: 0,
+            depth: 0,
+            leaf_length: INNER_BYTES as u32,
+            node_offset: i as u64,
+            xof_length: out_len as u32,
+            inner_length: INNER_BYTES as u8,
+            ..ParamBlock::new(std::cmp::min(INNER_BYTES, out_len - i * INNER_BYTES))
+        };
+        out.extend(hash_node(param.h(), &h0, param.digest_len as usize, false));
+    }
+
+    out
+}
+
+// the inputs of the KAT files: in = 00 01 02 .., key = 00 01 .. 3f
+fn kat_input(len: usize) -> Vec<u8> {
+    (0..len).map(|i| i as u8).collect()
+}
+
+fn kat_key() -> Vec<u8> {
+    kat_input(64)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Mode {
+    Blake2bp,
+    Blake2xb,
+}
+
+struct MyCircuit {
+    mode: Mode,
+    key: Vec<u8>,
+    message: Vec<u8>,
+    out_len: usize,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = (Blake2TreeConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            mode: self.mode,
+            key: vec![0; self.key.len()],
+            message: vec![0; self.message.len()],
+            out_len: self.out_len,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (Blake2TreeChip::configure(meta), instance)
+    }
+
+    fn synthesize(
+        &self,
+        (config, instance): Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Blake2TreeChip::construct(config);
+
+        let key = words(&self.key).into_iter().map(Value::known).collect::<Vec<_>>();
+        let message = words(&self.message).into_iter().map(Value::known).collect::<Vec<_>>();
+
+        let out = match self.mode {
+            Mode::Blake2bp => {
+                let blake2bp = Blake2bp::new(chip.clone(), layouter.namespace(|| "load spread table"))?;
+                let key = chip.assign_words(&mut layouter, &key)?;
+                let message = chip.assign_words(&mut layouter, &message)?;
+
+                blake2bp.hash(&mut layouter, &key, self.key.len(), &message, self.message.len(), self.out_len)?
+            }
+            Mode::Blake2xb => {
+                let blake2xb = Blake2xb::new(chip.clone(), layouter.namespace(|| "load spread table"))?;
+                let key = chip.assign_words(&mut layouter, &key)?;
+                let message = chip.assign_words(&mut layouter, &message)?;
+
+                blake2xb.hash(&mut layouter, &key, self.key.len(), &message, self.message.len(), self.out_len)?
+            }
+        };
+
+        for (i, word) in out.iter().enumerate() {
+            layouter.constrain_instance(word.cell(), instance, i)?;
+        }
+
+        Ok(())
+    }
+}
+
+// `expected` is a whole number of words
+fn run(mode: Mode, key: &[u8], message: &[u8], expected: &[u8]) -> bool {
+    assert_eq!(expected.len() % 8, 0);
+
+    let circuit = MyCircuit {
+        mode,
+        key: key.to_vec(),
+        message: message.to_vec(),
+        out_len: expected.len(),
+    };
+
+    let instance = words(expected).into_iter().map(bn256::Fr::from).collect();
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![instance]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+// the first entries of blake2bp-kat.txt and blake2xb-kat.txt, the latter has a 256-byte input
+// and out lengths from 1
+#[test]
+fn test_native_kat() {
+    assert_eq!(
+        blake2bp(&kat_input(0), &kat_key(), 64),
+        [
+            0x9d, 0x94, 0x61, 0x07, 0x3e, 0x4e, 0xb6, 0x40, 0xa2, 0x55, 0x35, 0x7b, 0x83, 0x9f, 0x39, 0x4b,
+            0x83, 0x8c, 0x6f, 0xf5, 0x7c, 0x9b, 0x68, 0x6a, 0x3f, 0x76, 0x10, 0x7c, 0x10, 0x66, 0x72, 0x8f,
+            0x3c, 0x99, 0x56, 0xbd, 0x78, 0x5c, 0xbc, 0x3b, 0xf7, 0x9d, 0xc2, 0xab, 0x57, 0x8c, 0x5a, 0x0c,
+            0x06, 0x3b, 0x9d, 0x9c, 0x40, 0x58, 0x48, 0xde, 0x1d, 0xbe, 0x82, 0x1c, 0xd0, 0x5c, 0x94, 0x0a,
+        ]
+    );
+    assert_eq!(
+        blake2bp(&kat_input(1), &kat_key(), 64),
+        [
+            0xff, 0x8e, 0x90, 0xa3, 0x7b, 0x94, 0x62, 0x39, 0x32, 0xc5, 0x9f, 0x75, 0x59, 0xf2, 0x60, 0x35,
+            0x02, 0x9c, 0x37, 0x67, 0x32, 0xcb, 0x14, 0xd4, 0x16, 0x02, 0x00, 0x1c, 0xbb, 0x73, 0xad, 0xb7,
+            0x92, 0x93, 0xa2, 0xdb, 0xda, 0x5f, 0x60, 0x70, 0x30, 0x25, 0x14, 0x4d, 0x15, 0x8e, 0x27, 0x35,
+            0x52, 0x95, 0x96, 0x25, 0x1c, 0x73, 0xc0, 0x34, 0x5c, 0xa6, 0xfc, 0xcb, 0x1f, 0xb1, 0xe9, 0x7e,
+        ]
+    );
+
+    let input = kat_input(256);
+    assert_eq!(blake2xb(&input, &kat_key(), 1), [0x64]);
+    assert_eq!(blake2xb(&input, &kat_key(), 2), [0xf4, 0x57]);
+    assert_eq!(blake2xb(&input, &kat_key(), 3), [0xe8, 0xc0, 0x45]);
+    assert_eq!(blake2xb(&input, &kat_key(), 8), [0x07, 0x4a, 0x02, 0xfa, 0x58, 0xd7, 0xc7, 0xc0]);
+}
+
+#[test]
+fn test_blake2bp_kat() {
+    let expected = blake2bp(&kat_input(1), &kat_key(), 64);
+    assert!(run(Mode::Blake2bp, &kat_key(), &kat_input(1), &expected));
+}
+
+// 5 blocks, leaf 0 gets two of them and the last one is partial
+#[test]
+fn test_blake2bp_stripes() {
+    let message = kat_input(600);
+    assert!(run(Mode::Blake2bp, &[], &message, &blake2bp(&message, &[], 32)));
+}
+
+#[test]
+fn test_blake2xb_kat() {
+    let expected = blake2xb(&kat_input(256), &kat_key(), 8);
+    assert!(run(Mode::Blake2xb, &kat_key(), &kat_input(256), &expected));
+}
+
+// three output blocks, the last one 8 bytes long
+#[test]
+fn test_blake2xb_long_output() {
+    assert!(run(Mode::Blake2xb, &[], b"abc", &blake2xb(b"abc", &[], 136)));
+}
+
+#[test]
+fn test_blake2xb_wrong_output() {
+    let mut expected = blake2xb(b"abc", &[], 136);
+    expected[130] ^= 1;
+
+    assert!(!run(Mode::Blake2xb, &[], b"abc", &expected));
+}
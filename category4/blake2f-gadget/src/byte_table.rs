@@ -0,0 +1,78 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{Chip, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
+    poly::Rotation,
+};
+use halo2curves::bn256::{self, Fr};
+
+// A 256-row table range checking the cells of one advice column to a byte, at the rows the
+// selector is enabled. The byte oriented chips each configure one over their byte column.
+#[derive(Clone, Debug)]
+pub(crate) struct ByteTableConfig {
+    pub input: Column<Advice>,
+    s_byte: Selector,
+    table: TableColumn,
+}
+
+impl ByteTableConfig {
+    // range checks the cell of `input` at `offset`
+    pub fn enable(&self, region: &mut Region<'_, bn256::Fr>, offset: usize) -> Result<(), Error> {
+        self.s_byte.enable(region, offset)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ByteTableChip {
+    config: ByteTableConfig,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for ByteTableChip {
+    type Config = ByteTableConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl ByteTableChip {
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        input: Column<Advice>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let table = meta.lookup_table_column();
+        let s_byte = meta.complex_selector();
+
+        meta.lookup("byte", |meta| {
+            let s = meta.query_selector(s_byte);
+            let byte = meta.query_advice(input, Rotation::cur());
+
+            vec![(s * byte, table)]
+        });
+
+        ByteTableConfig { input, s_byte, table }
+    }
+
+    pub fn load(
+        config: &ByteTableConfig,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "byte table",
+            |mut table| {
+                for byte in 0..(1 << 8) {
+                    table.assign_cell(|| "byte", config.table, byte, || Value::known(Fr::from(byte as u64)))?;
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
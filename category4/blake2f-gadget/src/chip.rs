@@ -2,7 +2,7 @@ use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use halo2_proofs::{
-    circuit::{Chip, Value},
+    circuit::{AssignedCell, Chip, Value},
     plonk::{Error, Column, Advice, ConstraintSystem, Fixed, VirtualCells, Expression}, poly::Rotation,
 };
 use halo2_proofs::circuit::Layouter;
@@ -10,8 +10,17 @@ use halo2_proofs::circuit::Layouter;
 use halo2curves::{FieldExt, bn256::{self, Fr}};
 
 use crate::{compression::CompressionConfig, spread_table::SpreadInputs};
+use crate::compression::assignment::{ROWS_PER_ROUND, VARS_PER_ROUND};
+use crate::compression::gates::narrow_limbs;
 use crate::scheduler::SchedulerConfig;
-use crate::{Blake2fInstructions, spread_table::{SpreadTableChip, SpreadTableConfig}};
+use crate::scheduler::assignment::{Blake2fIv, CHAINED_SCHEDULER_ROWS, SCHEDULER_ROWS};
+use crate::MAX_ROUND;
+use crate::{Blake2fInstructions, Blake2fState, Blake2fTrace, spread_table::{SpreadTableChip, SpreadTableConfig}};
+use crate::bits::{AssignedBits, Bits};
+use crate::state::RoundWord;
+
+// round 0, the rounds and the final xor, below the scheduler in the same region
+const COMPRESS_ROWS: usize = (MAX_ROUND + 1) * ROWS_PER_ROUND + 8 * 4;
 
 #[derive(Clone, Debug)]
 pub struct Blake2fChip {
@@ -22,14 +31,16 @@ pub struct Blake2fChip {
 // TODO: check generic
 impl Blake2fInstructions<bn256::Fr> for  Blake2fChip {
     type CSU64 = Value<u64>;
+    type Iv = Blake2fIv;
 
-    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<(), Error> {
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
         Self::load(&self.config, layouter)
     }
 
     fn compress(
         &self,
         layouter: &mut impl halo2_proofs::circuit::Layouter<bn256::Fr>,
+        iv: &Self::Iv,
         h: [Self::CSU64; 8],
         m: [Self::CSU64; 16],
         c0: Self::CSU64,
@@ -37,8 +48,179 @@ impl Blake2fInstructions<bn256::Fr> for  Blake2fChip {
         flag: Self::CSU64,
         rounds: Self::CSU64,
     ) -> Result<[Self::CSU64; 8], Error> {
-        let init_state = self.config.scheduler.process(layouter, h, m, c0, c1, flag, rounds)?;
-        self.config.compression.compress(layouter, init_state, rounds, m)
+        let trace = self.trace(h, m, [c0, c1], flag, 0, rounds)?;
+
+        layouter.assign_region(|| "compress", |mut region| {
+            let trace = trace.as_ref();
+            let init_state = self.config.scheduler.process(&mut region, iv, h, m, c0, c1, flag, rounds, trace)?;
+            self.config.compression.compress(&mut region, init_state, rounds, trace)
+        })
+    }
+
+    type State = Blake2fState;
+
+    fn initial_state(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        h: [u64; 8],
+    ) -> Result<Self::State, Error> {
+        let num = self.config.scheduler.columns.num;
+
+        layouter.assign_region(|| "blake2f initial state", |mut region| {
+            let mut state = Vec::with_capacity(8);
+            for (i, h) in h.iter().enumerate() {
+                let val = region.assign_advice_from_constant(|| format!("assign h{i}"), num, i, Bits::<64>::from(*h))?;
+                state.push(RoundWord::new(AssignedBits(val), None));
+            }
+
+            Ok(Blake2fState(state.try_into().unwrap()))
+        })
+    }
+
+    fn assign_constant(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        word: u64,
+    ) -> Result<AssignedCell<bn256::Fr, bn256::Fr>, Error> {
+        let num = self.config.scheduler.columns.num;
+
+        layouter.assign_region(|| "blake2f constant", |mut region| {
+            region.assign_advice_from_constant(|| "assign constant word", num, 0, Fr::from(word))
+        })
+    }
+
+    fn compress_assigned(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Self::Iv,
+        h: &Self::State,
+        m: [AssignedCell<bn256::Fr, bn256::Fr>; 16],
+        t: [u64; 2],
+        f: bool,
+        rounds: u64,
+    ) -> Result<Self::State, Error> {
+        self.compress_node(layouter, iv, h, m, t, f, false, rounds)
+    }
+
+    fn compress_cells(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Self::Iv,
+        h: [AssignedCell<bn256::Fr, bn256::Fr>; 8],
+        m: [AssignedCell<bn256::Fr, bn256::Fr>; 16],
+        t: [AssignedCell<bn256::Fr, bn256::Fr>; 2],
+        f: AssignedCell<bn256::Fr, bn256::Fr>,
+        rounds: AssignedCell<bn256::Fr, bn256::Fr>,
+    ) -> Result<Self::State, Error> {
+        let value = |cell: &AssignedCell<bn256::Fr, bn256::Fr>| cell.value().map(|v| v.get_lower_128() as u64);
+        let trace = self.trace(
+            h.clone().map(|h| value(&h)),
+            m.clone().map(|m| value(&m)),
+            t.clone().map(|t| value(&t)),
+            value(&f),
+            0,
+            value(&rounds),
+        )?;
+
+        let h = layouter.assign_region(|| "compress", |mut region| {
+            let trace = trace.as_ref();
+            let init_state = self.config.scheduler.process(
+                &mut region,
+                iv,
+                h.clone().map(|h| value(&h)),
+                m.clone().map(|m| value(&m)),
+                value(&t[0]),
+                value(&t[1]),
+                value(&f),
+                value(&rounds),
+                trace,
+            )?;
+
+            self.config.compression.compress_cells(
+                &mut region,
+                init_state,
+                &h.clone().map(|h| h.cell()),
+                &m.clone().map(|m| m.cell()),
+                &t.clone().map(|t| t.cell()),
+                f.cell(),
+                rounds.cell(),
+                trace,
+            )
+        })?;
+
+        Ok(Blake2fState(h))
+    }
+
+    fn compress_node(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Self::Iv,
+        h: &Self::State,
+        m: [AssignedCell<bn256::Fr, bn256::Fr>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+    ) -> Result<Self::State, Error> {
+        let trace = self.assigned_trace(h, &m, t, f, last_node, rounds)?;
+        self.compress_node_with_trace(layouter, iv, h, m, t, f, last_node, rounds, trace.as_ref())
+    }
+
+    fn compress_chained(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Self::Iv,
+        h: &Self::State,
+        m: [AssignedCell<bn256::Fr, bn256::Fr>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+    ) -> Result<Self::State, Error> {
+        let trace = self.assigned_trace(h, &m, t, f, last_node, rounds)?;
+        self.compress_with_trace(layouter, iv, h, m, t, f, last_node, rounds, trace)
+    }
+
+    fn compress_with_trace(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Self::Iv,
+        h: &Self::State,
+        m: [AssignedCell<bn256::Fr, bn256::Fr>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+        trace: Value<Blake2fTrace>,
+    ) -> Result<Self::State, Error> {
+        self.check_rounds(Value::known(rounds))?;
+        let trace = trace.as_ref();
+
+        // e.g. the initial state, its words were never decomposed
+        if h.0.iter().any(|word| word.halves.is_none()) {
+            return self.compress_node_with_trace(layouter, iv, h, m, t, f, last_node, rounds, trace);
+        }
+
+        let flag = if f { u64::MAX } else { 0 };
+
+        let h = layouter.assign_region(|| "compress chained", |mut region| {
+            let init_state = self.config.scheduler.process_chained(
+                &mut region,
+                iv,
+                &h.0,
+                m.clone().map(|m| m.value().map(|m| m.get_lower_128() as u64)),
+                Value::known(t[0]),
+                Value::known(t[1]),
+                Value::known(flag),
+                if last_node { u64::MAX } else { 0 },
+                Value::known(rounds),
+                trace,
+            )?;
+
+            self.config.compression.compress_chained(&mut region, init_state, &m, t, flag, rounds, trace)
+        })?;
+
+        Ok(Blake2fState(h))
     }
 }
 
@@ -65,6 +247,12 @@ pub struct Blake2fConfig {
 
 
 impl Blake2fChip {
+    // rows of the `num` column taken by one compression, the scheduler, the rounds and the final
+    // xor, with one lookup pair
+    pub const ROWS_PER_COMPRESSION: usize = SCHEDULER_ROWS + COMPRESS_ROWS;
+    // a compression through `compress_chained` after another one
+    pub const ROWS_PER_CHAINED_COMPRESSION: usize = CHAINED_SCHEDULER_ROWS + COMPRESS_ROWS;
+
     pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
         Self {
             config,
@@ -76,15 +264,53 @@ impl Blake2fChip {
         meta: &mut ConstraintSystem<bn256::Fr>,
     ) -> <Self as Chip<bn256::Fr>>::Config {
         let columns = Columns::init(meta);
+        Self::configure_with_columns(meta, columns)
+    }
+
+    // A layout for `rounds` always equal to MAX_ROUND, e.g. Zcash or BLAKE2b hashing: no
+    // `round` and `s_round` columns and no padding rounds, every subround gate is active.
+    // A compression with other rounds is unsatisfiable.
+    pub fn configure_fixed_rounds(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let columns = Columns::init_fixed_rounds(meta);
+        Self::configure_with_columns(meta, columns)
+    }
+
+    // A layout with `lanes` lookup pairs side by side, 2 or 4, so a word takes 2 or 1 rows
+    // instead of 4. The gates read the limbs of a word from the parallel columns.
+    pub fn configure_with_lanes(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        lanes: usize,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let columns = Columns::init_with_lanes(meta, lanes);
+        Self::configure_with_columns(meta, columns)
+    }
 
+    fn configure_with_columns(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        columns: Columns,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
         let input_dense = meta.advice_column();
         let input_spread = meta.advice_column();
 
-        for column in [input_dense, input_spread] {
-            meta.enable_equality(column);
+        let parallel = columns.parallel.iter().map(|_| (meta.advice_column(), meta.advice_column())).collect::<Vec<_>>();
+
+        for (dense, spread) in std::iter::once((input_dense, input_spread)).chain(parallel.iter().copied()) {
+            meta.enable_equality(dense);
+            meta.enable_equality(spread);
         }
 
-        let lookup = SpreadTableChip::configure(meta, input_dense, input_spread);
+        // a padding round skips the lookup of the words it only carries, the fixed rounds
+        // layout has none and looks every row up
+        let enable = columns.rounds.map(|_| {
+            let enable = meta.advice_column();
+            meta.enable_equality(enable);
+            enable
+        });
+
+        let mut lookup = SpreadTableChip::configure_with_enable(meta, input_dense, input_spread, parallel, enable);
+        SpreadTableChip::configure_xor(meta, &mut lookup, &narrow_limbs());
         let lookup_inputs = lookup.input.clone();
 
         let compression = CompressionConfig::configure(meta, lookup_inputs.clone(), columns.clone());
@@ -93,11 +319,117 @@ impl Blake2fChip {
         Blake2fConfig { lookup, scheduler, compression }
     }
 
+    // loads the spread table and assigns the IV, whose cells every compression copies
     pub fn load(
         config: &Blake2fConfig,
         layouter: &mut impl Layouter<bn256::Fr>,
-    ) -> Result<(), Error> {
-        SpreadTableChip::load(config.lookup.clone(), layouter)
+    ) -> Result<Blake2fIv, Error> {
+        SpreadTableChip::load(config.lookup.clone(), layouter)?;
+        config.scheduler.load_iv(layouter)
+    }
+
+    // `ROWS_PER_COMPRESSION` of the layout of this chip
+    pub fn rows_per_compression(&self) -> usize {
+        self.config.scheduler.rows(false) + self.config.compression.rows()
+    }
+
+    pub fn rows_per_chained_compression(&self) -> usize {
+        self.config.scheduler.rows(true) + self.config.compression.rows()
+    }
+
+    // At most MAX_ROUND rounds are laid out, a call with more, valid for the precompile, is an
+    // error of the synthesis. The fixed rounds layout pins the rounds cell to MAX_ROUND instead.
+    fn check_rounds(&self, rounds: Value<u64>) -> Result<(), Error> {
+        let mut laid_out = true;
+        if !self.config.scheduler.columns.fixed_rounds() {
+            rounds.map(|rounds| laid_out = rounds <= MAX_ROUND as u64);
+        }
+
+        if laid_out { Ok(()) } else { Err(Error::Synthesis) }
+    }
+
+    // The trace of a compression from its input values, computed natively before the region is
+    // assigned. The fixed rounds layout computes MAX_ROUND rounds whatever the rounds cell holds.
+    fn trace(
+        &self,
+        h: [Value<u64>; 8],
+        m: [Value<u64>; 16],
+        t: [Value<u64>; 2],
+        flag: Value<u64>,
+        last_node: u64,
+        rounds: Value<u64>,
+    ) -> Result<Value<Blake2fTrace>, Error> {
+        self.check_rounds(rounds)?;
+
+        let fixed_rounds = self.config.scheduler.columns.fixed_rounds();
+        let words = |words: &[Value<u64>]| words.iter().copied().collect::<Value<Vec<u64>>>();
+
+        Ok(words(&h).zip(words(&m)).zip(words(&t)).zip(flag).zip(rounds).map(|((((h, m), t), flag), rounds)| {
+            let rounds = if fixed_rounds { MAX_ROUND } else { rounds as usize };
+            Blake2fTrace::new_node(
+                h.try_into().unwrap(),
+                m.try_into().unwrap(),
+                t.try_into().unwrap(),
+                flag,
+                last_node,
+                rounds,
+            )
+        }))
+    }
+
+    // `trace` for the inputs of `compress_node` and `compress_chained`
+    fn assigned_trace(
+        &self,
+        h: &Blake2fState,
+        m: &[AssignedCell<bn256::Fr, bn256::Fr>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+    ) -> Result<Value<Blake2fTrace>, Error> {
+        self.trace(
+            h.value(),
+            m.clone().map(|m| m.value().map(|m| m.get_lower_128() as u64)),
+            t.map(Value::known),
+            Value::known(if f { u64::MAX } else { 0 }),
+            if last_node { u64::MAX } else { 0 },
+            Value::known(rounds),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compress_node_with_trace(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Blake2fIv,
+        h: &Blake2fState,
+        m: [AssignedCell<bn256::Fr, bn256::Fr>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+        trace: Value<&Blake2fTrace>,
+    ) -> Result<Blake2fState, Error> {
+        let flag = if f { u64::MAX } else { 0 };
+
+        let h = layouter.assign_region(|| "compress", |mut region| {
+            let init_state = self.config.scheduler.process_node(
+                &mut region,
+                iv,
+                h.value(),
+                m.clone().map(|m| m.value().map(|m| m.get_lower_128() as u64)),
+                Value::known(t[0]),
+                Value::known(t[1]),
+                Value::known(flag),
+                if last_node { u64::MAX } else { 0 },
+                Value::known(rounds),
+                trace,
+            )?;
+
+            self.config.compression.compress_assigned(&mut region, init_state, &h.0, &m, t, flag, rounds, trace)
+        })?;
+
+        Ok(Blake2fState(h))
     }
 }
 
@@ -105,6 +437,16 @@ impl Blake2fChip {
 pub struct Columns {
     pub num: Column<Advice>,
     pub constants: Column<Fixed>,
+    // None when the number of rounds is fixed to MAX_ROUND
+    pub rounds: Option<RoundColumns>,
+    // more `num` columns, one for each parallel lookup pair
+    pub parallel: Vec<Column<Advice>>,
+}
+
+// `s_round` is 1 on the rows of a round that is computed and 0 on a padding round,
+// `round` counts the computed rounds
+#[derive(Debug, Clone, Copy)]
+pub struct RoundColumns {
     pub round: Column<Advice>,
     pub s_round: Column<Advice>,
 }
@@ -113,6 +455,17 @@ impl Columns {
     pub fn init(
         meta: &mut ConstraintSystem<bn256::Fr>,
     ) -> Self {
+        Self::init_with_lanes(meta, 1)
+    }
+
+    // `lanes` columns for the 4 cells of a word, next to as many lookup pairs for its 4 limbs,
+    // a word takes 4 / lanes rows
+    pub fn init_with_lanes(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        lanes: usize,
+    ) -> Self {
+        assert!([1, 2, 4].contains(&lanes), "a word is split over 1, 2 or 4 lanes");
+
         let num = meta.advice_column();
 
         let s_round = meta.advice_column();
@@ -120,14 +473,72 @@ impl Columns {
 
         let constants = meta.fixed_column();
 
+        let parallel = (1..lanes).map(|_| meta.advice_column()).collect::<Vec<_>>();
 
-        for c in [num, s_round, round] {
+        for c in [num, s_round, round].into_iter().chain(parallel.iter().copied()) {
             meta.enable_equality(c);
         }
 
         meta.enable_constant(constants);
 
-        Self { num, constants, round, s_round }
+        Self { num, constants, rounds: Some(RoundColumns { round, s_round }), parallel }
+    }
+
+    // without the `round` and `s_round` columns, every round is computed
+    pub fn init_fixed_rounds(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> Self {
+        let num = meta.advice_column();
+        let constants = meta.fixed_column();
+
+        meta.enable_equality(num);
+        meta.enable_constant(constants);
+
+        Self { num, constants, rounds: None, parallel: vec![] }
+    }
+
+    pub fn fixed_rounds(&self) -> bool {
+        self.rounds.is_none()
+    }
+
+    pub fn lanes(&self) -> usize {
+        1 + self.parallel.len()
+    }
+
+    // rows of a word with lookup
+    pub fn word_rows(&self) -> usize {
+        4 / self.lanes()
+    }
+
+    pub fn rows_per_round(&self) -> usize {
+        VARS_PER_ROUND * self.word_rows()
+    }
+
+    // rotation of the word `words` words below the current one
+    pub(crate) fn word(&self, words: i32) -> Rotation {
+        Rotation(words * self.word_rows() as i32)
+    }
+
+    // A word with lookup has 4 cells, its value in cell 0 and e.g. `m` and carry in cells 1-3.
+    // The bits of an xor are in the xor slots of the lookup instead. Cell `i` is in the column and the row below the word's first row.
+    pub(crate) fn cell(&self, i: usize) -> (Column<Advice>, usize) {
+        let lane = i % self.lanes();
+        let column = if lane == 0 { self.num } else { self.parallel[lane - 1] };
+
+        (column, i / self.lanes())
+    }
+
+    pub(crate) fn query_cell(&self, meta: &mut VirtualCells<bn256::Fr>, words: i32, i: usize) -> Expression<bn256::Fr> {
+        let (column, row) = self.cell(i);
+        meta.query_advice(column, Rotation(self.word(words).0 + row as i32))
+    }
+
+    // `s_round` of the current row, always 1 when the rounds are fixed
+    pub(crate) fn query_s_round(&self, meta: &mut VirtualCells<bn256::Fr>) -> Expression<bn256::Fr> {
+        match self.rounds {
+            Some(rounds) => meta.query_advice(rounds.s_round, Rotation::cur()),
+            None => Expression::Constant(Fr::one()),
+        }
     }
 }
 
@@ -144,13 +555,27 @@ pub(crate) fn query_table(
     let mut spreads = Vec::new();
 
     for i in 0..4 {
-         dense.push(meta.query_advice(lookup.dense, Rotation(rotation.0 + i)));
-         spreads.push(meta.query_advice(lookup.spread, Rotation(rotation.0 + i)));
+         let (dense_column, spread_column, row) = lookup.limb(i);
+         dense.push(meta.query_advice(dense_column, Rotation(rotation.0 + row as i32)));
+         spreads.push(meta.query_advice(spread_column, Rotation(rotation.0 + row as i32)));
     }
 
     (dense.try_into().unwrap(), spreads.try_into().unwrap())
 }
 
+// the dense and spread cells of xor slot `slot` of the word at `rotation`
+pub(crate) fn query_xor(
+    meta: &mut VirtualCells<bn256::Fr>,
+    lookup: &SpreadInputs,
+    rotation: Rotation,
+    slot: usize,
+) -> (Expression<bn256::Fr>, Expression<bn256::Fr>) {
+    let (dense_column, spread_column, row) = lookup.xor_limb(slot);
+    let rotation = Rotation(rotation.0 + row as i32);
+
+    (meta.query_advice(dense_column, rotation), meta.query_advice(spread_column, rotation))
+}
+
 // little endian
 //            lo          hi
 // bits_num :[16, 16, 16, 16]
@@ -258,7 +683,7 @@ mod tests {
                 mut layouter: impl Layouter<bn256::Fr>,
             ) -> Result<(), Error> {
                 let blake2f_chip = Blake2fChip::construct(config);
-                blake2f_chip.initialize(&mut layouter)?;
+                let iv = blake2f_chip.initialize(&mut layouter)?;
                 let f = if self.inputs.f {
                     0xFFFFFFFFFFFFFFFF_u64
                 }else {
@@ -266,6 +691,7 @@ mod tests {
                 };
                 let h = blake2f_chip.compress(
                     &mut layouter,
+                    &iv,
                     self.inputs.h.iter().map(|v| Value::known(*v)).collect::<Vec<_>>().try_into().unwrap(),
                     self.inputs.m.iter().map(|v| Value::known(*v)).collect::<Vec<_>>().try_into().unwrap(),
                     Value::known(self.inputs.t[0]),
@@ -304,10 +730,7 @@ mod tests {
         0xD1A2FFDB6FBB124B, 0x2D79AB2A39C5877D, 0x95CC3345DED552C2,
         0x5A92F1DBA88AD318, 0x239900D4ED8623B9];
 
-        // let h = (0..8).map(|_| fastrand::u64(..)).collect::<Vec<_>>();
-        // let t = (0..2).map(|_| fastrand::u64(..)).collect::<Vec<_>>();
-        // let m = (0..16).map(|_| fastrand::u64(..)).collect::<Vec<_>>();
-        // let f = fastrand::bool();
+        // random inputs are in `differential`
         let circuit: MyCircuit = MyCircuit {
             inputs: Blake2fWitness {
                 rounds,
@@ -325,4 +748,251 @@ mod tests {
         };
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    // the fixed rounds layout only computes MAX_ROUND rounds
+    #[test]
+    fn test_fixed_rounds_other_rounds() {
+        struct MyCircuit {
+            rounds: u64,
+        }
+
+        impl Circuit<bn256::Fr> for MyCircuit {
+            type Config = Blake2fConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self { rounds: 0 }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+                Blake2fChip::configure_fixed_rounds(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<bn256::Fr>,
+            ) -> Result<(), Error> {
+                let blake2f_chip = Blake2fChip::construct(config);
+                let iv = blake2f_chip.initialize(&mut layouter)?;
+
+                blake2f_chip.compress(
+                    &mut layouter,
+                    &iv,
+                    [Value::known(0); 8],
+                    [Value::known(0); 16],
+                    Value::known(0),
+                    Value::known(0),
+                    Value::known(0),
+                    Value::known(self.rounds),
+                )?;
+
+                Ok(())
+            }
+        }
+
+        let verify = |rounds| MockProver::<bn256::Fr>::run(17, &MyCircuit { rounds }, vec![]).unwrap().verify();
+
+        assert_eq!(verify(MAX_ROUND as u64), Ok(()));
+        assert!(verify(10).is_err());
+    }
+
+    // padding rounds copy the state without its limbs, the final h_xor decomposes the last subround
+    #[test]
+    fn test_padding_rounds() {
+        struct MyCircuit {
+            rounds: u64,
+        }
+
+        impl Circuit<bn256::Fr> for MyCircuit {
+            type Config = Blake2fConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self { rounds: 0 }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+                Blake2fChip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<bn256::Fr>,
+            ) -> Result<(), Error> {
+                let blake2f_chip = Blake2fChip::construct(config);
+                let iv = blake2f_chip.initialize(&mut layouter)?;
+
+                let h = blake2f_chip.compress(
+                    &mut layouter,
+                    &iv,
+                    [Value::known(1); 8],
+                    [Value::known(2); 16],
+                    Value::known(3),
+                    Value::known(4),
+                    Value::known(0xFFFFFFFFFFFFFFFF),
+                    Value::known(self.rounds),
+                )?;
+
+                // without rounds h ^= h ^ v8..v15
+                if self.rounds == 0 {
+                    let iv = crate::native::IV;
+                    let expected = [iv[0], iv[1], iv[2], iv[3], iv[4] ^ 3, iv[5] ^ 4, !iv[6], iv[7]];
+                    for (h, expected) in h.iter().zip(expected) {
+                        h.assert_if_known(|h| *h == expected);
+                    }
+                }
+
+                Ok(())
+            }
+        }
+
+        for rounds in [0, 1, MAX_ROUND as u64 - 1] {
+            let prover = MockProver::<bn256::Fr>::run(17, &MyCircuit { rounds }, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    // the heights of the compress regions as laid out, the IV is loaded once by `initialize`
+    #[test]
+    fn test_rows_per_compression() {
+        #[derive(Default)]
+        struct MyCircuit<const LANES: usize>;
+
+        impl<const LANES: usize> Circuit<bn256::Fr> for MyCircuit<LANES> {
+            type Config = Blake2fConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+                Blake2fChip::configure_with_lanes(meta, LANES)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<bn256::Fr>,
+            ) -> Result<(), Error> {
+                let blake2f_chip = Blake2fChip::construct(config);
+                let iv = blake2f_chip.initialize(&mut layouter)?;
+
+                let m = (0..16)
+                    .map(|i| blake2f_chip.assign_constant(&mut layouter, i))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let m: [_; 16] = m.try_into().unwrap();
+
+                let h = blake2f_chip.initial_state(&mut layouter, crate::native::IV)?;
+                let h = blake2f_chip.compress_node(&mut layouter, &iv, &h, m.clone(), [128, 0], false, false, MAX_ROUND as u64)?;
+                let h = blake2f_chip.compress_node(&mut layouter, &iv, &h, m.clone(), [256, 0], false, false, MAX_ROUND as u64)?;
+                blake2f_chip.compress_chained(&mut layouter, &iv, &h, m, [384, 0], true, false, MAX_ROUND as u64)?;
+
+                Ok(())
+            }
+        }
+
+        fn heights<const LANES: usize>() -> (Vec<usize>, Vec<usize>) {
+            let layout = crate::layout::measure(&MyCircuit::<LANES>).unwrap();
+            assert_eq!(layout.regions("blake2f iv").count(), 1);
+
+            let rows = |name| layout.regions(name).map(|region| region.rows).collect();
+            (rows("compress"), rows("compress chained"))
+        }
+
+        assert_eq!(heights::<1>(), (vec![Blake2fChip::ROWS_PER_COMPRESSION; 2], vec![Blake2fChip::ROWS_PER_CHAINED_COMPRESSION]));
+        assert_eq!(Blake2fChip::ROWS_PER_COMPRESSION, 3409);
+        assert_eq!(Blake2fChip::ROWS_PER_CHAINED_COMPRESSION, 3401);
+
+        assert_eq!(heights::<2>(), (vec![1711; 2], vec![1703]));
+        assert_eq!(heights::<4>(), (vec![862; 2], vec![854]));
+
+        let prover = MockProver::<bn256::Fr>::run(17, &MyCircuit::<1>, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // a padding round takes the rows of a computed one but only looks up the last subround,
+    // which the final h_xor reads
+    #[test]
+    fn test_padding_rounds_lookups() {
+        struct MyCircuit {
+            rounds: u64,
+        }
+
+        impl Circuit<bn256::Fr> for MyCircuit {
+            type Config = Blake2fConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self { rounds: 0 }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+                Blake2fChip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<bn256::Fr>,
+            ) -> Result<(), Error> {
+                let blake2f_chip = Blake2fChip::construct(config);
+                let iv = blake2f_chip.initialize(&mut layouter)?;
+
+                blake2f_chip.compress(
+                    &mut layouter,
+                    &iv,
+                    [Value::known(1); 8],
+                    [Value::known(2); 16],
+                    Value::known(3),
+                    Value::known(4),
+                    Value::known(0),
+                    Value::known(self.rounds),
+                )?;
+
+                Ok(())
+            }
+        }
+
+        let measure = |rounds| crate::layout::measure(&MyCircuit { rounds }).unwrap();
+        let (padded, computed) = (measure(0), measure(MAX_ROUND as u64));
+
+        assert_eq!(padded.rows, computed.rows);
+        assert_eq!(
+            padded.regions("compress").map(|region| region.rows).collect::<Vec<_>>(),
+            vec![Blake2fChip::ROWS_PER_COMPRESSION],
+        );
+        assert_eq!(
+            computed.regions("compress").map(|region| region.rows).collect::<Vec<_>>(),
+            vec![Blake2fChip::ROWS_PER_COMPRESSION],
+        );
+
+        // the scheduler words with lookup, round 0, the last subround and the final h_xor, in the
+        // limb pair and the 2 xor pairs. The 2 narrow lookups of each b word do not depend on the
+        // rounds, their selectors are enabled in padding rounds too.
+        let always = 9 * 4 + ROWS_PER_ROUND + 16 * 4 + 8 * 4;
+        let narrow = 2 * MAX_ROUND * 4 * 4;
+        assert_eq!(padded.lookups, 3 * always + narrow);
+        assert_eq!(computed.lookups, 3 * (always + MAX_ROUND * ROWS_PER_ROUND - 16 * 4) + narrow);
+
+        for rounds in [0, MAX_ROUND as u64] {
+            let prover = MockProver::<bn256::Fr>::run(17, &MyCircuit { rounds }, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_rows_per_compression_with_lanes() {
+        let rows = |lanes| {
+            let mut meta = ConstraintSystem::<bn256::Fr>::default();
+            let chip = Blake2fChip::construct(Blake2fChip::configure_with_lanes(&mut meta, lanes));
+            (chip.rows_per_compression(), chip.rows_per_chained_compression())
+        };
+
+        assert_eq!(rows(1), (Blake2fChip::ROWS_PER_COMPRESSION, Blake2fChip::ROWS_PER_CHAINED_COMPRESSION));
+        assert_eq!(rows(2), (1711, 1703));
+        assert_eq!(rows(4), (862, 854));
+    }
 }
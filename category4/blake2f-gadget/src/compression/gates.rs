@@ -1,35 +1,67 @@
 use halo2_proofs::{
-    plonk::{ConstraintSystem, Constraints, Expression, Selector},
+    plonk::{Column, ConstraintSystem, Constraints, Expression, Fixed, Selector, VirtualCells},
     poly::Rotation,
 };
-use halo2curves::FieldExt;
+use halo2curves::{group::ff::Field, FieldExt};
 use halo2curves::{bn256::{self, Fr}};
 
 use crate::{compression::SpreadInputs, MAX_ROUND};
 use crate::compression::compose_dense_from_bits;
-use crate::compression::ROWS_PER_ROUND;
-use crate::chip::{Columns, query_table, compose_spread_from_bits};
+use crate::chip::{Columns, query_table, query_xor, compose_spread_from_bits, compose_val_from_bits_num};
+use crate::spread_table::{XOR_HIGH, XOR_LOW, XOR_ODD};
 
 
-// TODD: move to global
-#[derive(Debug, Clone)]
-pub struct RoundGates {
-    pub s_add_offset_1: Selector,
-    pub s_add_offset_4: Selector,
-    pub s_add_offset_5: Selector,
+// The operand of a main round operation is the word `base + shift`, where the base is fixed
+// per operation and the shift is read from the `opcode` column.
+// shifts: none in the column step, +1 in the diagonal step but -3 for the last word of a group
+pub(crate) const SHIFTS: [i32; 3] = [0, 1, -3];
 
-    pub s_add_m_and_offset_neg_15: Selector,
-    pub s_add_m_and_offset_neg_12: Selector,
-    pub s_add_m_and_offset_neg_11: Selector,
+// the rotations of the b and d words, the first in the odd subrounds and the second in the even ones
+pub(crate) const B_ROTATIONS: [usize; 2] = [24, 63];
+pub(crate) const D_ROTATIONS: [usize; 2] = [32, 16];
 
-    pub s_decompose: Selector, // normal decompose
-
-    // check decompose when Fp(s_round) == 1 otherwise check state copy
-    pub s_decompose_or_copy_in_main_round: Selector,
+// The limb a rotation right by R splits and the width of its low part, which ends the word once
+// rotated back. None when R is a multiple of 16, the limbs are only moved.
+pub(crate) fn split_limb(rotate: usize) -> Option<(usize, usize)> {
+    let (limb, width) = ((64 - rotate) / 16, (64 - rotate) % 16);
+    (width != 0).then_some((limb, width))
+}
 
+// the xor slots and widths of the narrow lookups, the low and high parts of each split limb
+pub(crate) fn narrow_limbs() -> Vec<(usize, usize)> {
+    B_ROTATIONS
+        .iter()
+        .chain(D_ROTATIONS.iter())
+        .filter_map(|&rotate| split_limb(rotate))
+        .flat_map(|(_, width)| [(XOR_LOW, width), (XOR_HIGH, 16 - width)])
+        .collect()
+}
 
-    // enable s_round in main round
-    pub s_check_s_round: Selector,
+// TODD: move to global
+#[derive(Debug, Clone)]
+pub struct RoundGates {
+    // operand shift of the operation in the row, one of `SHIFTS`
+    pub opcode: Column<Fixed>,
+    // R for the xor and rotate R rows
+    pub rotation: Column<Fixed>,
+
+    // a = a + b + m, base -12
+    pub s_add_m: Selector,
+    // b = (b ^ c) >>> R, base +4
+    pub s_xor_b: Selector,
+    // c = c + d, base +4
+    pub s_add: Selector,
+    // d = (d ^ a) >>> R, base -12
+    pub s_xor_d: Selector,
+
+    // check decompose when Fp(s_round) == 1 otherwise check state copy,
+    // and the round columns when the rounds are not fixed
+    pub s_main_round: Selector,
+    // the limbs of the word are looked up when s_round is 1 and zeros otherwise, on every word of
+    // the rounds but the last subround, which the final h_xor reads
+    pub s_lookup: Selector,
+    // the words of round 0 that are not copied with their limbs are decomposed again
+    pub s_decompose: Selector,
 }
 
 impl RoundGates {
@@ -39,95 +71,100 @@ impl RoundGates {
         columns: &Columns,
         lookup: &SpreadInputs,
     ) -> Self {
-        let s_add_offset_1 = meta.selector();
-        let s_add_offset_4 = meta.selector();
-        let s_add_offset_5 = meta.selector();
+        let opcode = meta.fixed_column();
+        let rotation = meta.fixed_column();
 
-        let s_add_m_and_offset_neg_15 = meta.selector();
-        let s_add_m_and_offset_neg_12 = meta.selector();
-        let s_add_m_and_offset_neg_11 = meta.selector();
+        let s_add_m = meta.selector();
+        let s_xor_b = meta.selector();
+        let s_add = meta.selector();
+        let s_xor_d = meta.selector();
 
-        let s_decompose_or_copy_in_main_round = meta.selector();
-        let s_decompose = meta.selector();
         let s_main_round = meta.selector();
-        let s_digest = meta.selector();
+        let s_lookup = meta.selector();
+        let s_decompose = meta.selector();
+
+        meta.create_gate("decompose in round 0", |meta| {
+            let s = meta.query_selector(s_decompose);
 
-        let s_check_s_round = meta.selector();
+            let val = columns.query_cell(meta, 0, 0);
+            let (dense, _) = query_table(meta, lookup, Rotation::cur());
+
+            Constraints::with_selector(s, vec![val - compose_dense_from_bits(&dense)])
+        });
 
         meta.create_gate("decompose or copy in main round", |meta| {
-            let s = meta.query_selector(s_decompose_or_copy_in_main_round);
-            let s_round = meta.query_advice(columns.s_round, Rotation::cur());
+            let s = meta.query_selector(s_main_round);
+            let s_round = columns.query_s_round(meta);
 
-            let old_val = meta.query_advice(columns.num, Rotation(-16 * 4));
-            let val = meta.query_advice(columns.num, Rotation::cur());
+            let old_val = columns.query_cell(meta, -16, 0);
+            let val = columns.query_cell(meta, 0, 0);
 
             let (val_dense_bits, _) = query_table(meta, &lookup, Rotation::cur());
 
             let dense_val = compose_dense_from_bits(&val_dense_bits);
 
             let decompose_check = s_round.clone() * (val.clone() - dense_val);
-            let copy_check = (Expression::Constant(Fr::one()) - s_round) * (old_val - val);
+            let mut checks = vec![decompose_check];
 
-            Constraints::with_selector(s, vec![
-                decompose_check,
-                copy_check,
-            ])
-        });
+            // no padding round when the rounds are fixed
+            if let Some(rounds) = columns.rounds {
+                let one = Expression::Constant(Fr::one());
 
-        meta.create_gate("decompose check", |meta| {
-            let s = meta.query_selector(s_decompose);
+                checks.push((one.clone() - s_round.clone()) * (old_val - val));
 
-            let val = meta.query_advice(columns.num, Rotation::cur());
+                let round = meta.query_advice(rounds.round, Rotation::cur());
 
-            let (dense_bits, _) = query_table(meta, &lookup, Rotation::cur());
-            let dense_val = compose_dense_from_bits(&dense_bits);
+                // TODO: make next constant
+                let s_round_prev = meta.query_advice(rounds.s_round, Rotation(-(columns.rows_per_round() as i32)));
+                let round_prev = meta.query_advice(rounds.round, Rotation(-(columns.rows_per_round() as i32)));
 
-            Constraints::with_selector(s, vec![
-                val - dense_val,
-            ])
-        });
+                checks.extend([
+                    (s_round.clone() * (one.clone() - s_round.clone())), // s_round must be a boolean
+                    (s_round.clone() * (one.clone() - s_round_prev.clone())), // if s_round is one, then the prev s_round must be one
+                    // if s_round: cur_round - prev_cound = 1
+                    (s_round.clone() * (round.clone() - round_prev.clone() - one.clone())),
+                    // if not s_round: cur_round == prev_round
+                    (one.clone() - s_round.clone()) * (round_prev.clone() - round.clone()),
+                ]);
+            }
 
-        meta.create_gate("check round as a selector", |meta| {
-            let s_check_s_round = meta.query_selector(s_check_s_round);
+            Constraints::with_selector(s, checks)
+        });
 
-            let s_round = meta.query_advice(columns.s_round, Rotation::cur());
-            let round = meta.query_advice(columns.round, Rotation::cur());
+        // a padding round skips the lookup of the words it only carries
+        if let (Some(_), Some(enable)) = (columns.rounds, lookup.enable) {
+            meta.create_gate("lookup in main round", |meta| {
+                let s = meta.query_selector(s_lookup);
+                let s_round = columns.query_s_round(meta);
+                let padding = Expression::Constant(Fr::one()) - s_round.clone();
 
-            // TODO: make next constant
-            let s_round_prev = meta.query_advice(columns.s_round, Rotation(-(ROWS_PER_ROUND as i32)));
-            let round_prev = meta.query_advice(columns.round, Rotation(-(ROWS_PER_ROUND as i32)));
+                let (dense, spread) = query_table(meta, lookup, Rotation::cur());
 
-            let one = Expression::Constant(Fr::one());
+                let mut checks = (0..columns.word_rows())
+                    .map(|row| meta.query_advice(enable, Rotation(row as i32)) - s_round.clone())
+                    .collect::<Vec<_>>();
+                checks.extend(dense.into_iter().chain(spread).map(|limb| padding.clone() * limb));
 
-            Constraints::with_selector(s_check_s_round, vec![
-                (s_round.clone() * (one.clone() - s_round.clone())), // s_round must be a boolean
-                (s_round.clone() * (one.clone() - s_round_prev.clone())), // if s_round is one, then the prev s_round must be one
-                // if s_round: cur_round - prev_cound = 1
-                (s_round.clone() * (round.clone() - round_prev.clone() - one.clone())),
-                // if not s_round: cur_round == prev_round
-                (one.clone() - s_round.clone()) * (round_prev.clone() - round.clone()),
-            ])
-        });
+                Constraints::with_selector(s, checks)
+            });
+        }
 
-        offset_add_gate::<1, false>("offset_add_1", meta, s_add_offset_1, &columns);
-        offset_add_gate::<4, false>("offset_add_4", meta, s_add_offset_4, &columns);
-        offset_add_gate::<5, false>("offset_add_5", meta, s_add_offset_5, &columns);
+        offset_add_gate::<-12, true>("add b and m", meta, s_add_m, opcode, &columns);
+        offset_add_gate::<4, false>("add d", meta, s_add, opcode, &columns);
 
-        offset_add_gate::<-15, true>("offset_add_1_and_m", meta, s_add_m_and_offset_neg_15, &columns);
-        offset_add_gate::<-12, true>("offset_add_4_and_m", meta, s_add_m_and_offset_neg_12, &columns);
-        offset_add_gate::<-11, true>("offset_add_5_and_m", meta, s_add_m_and_offset_neg_11, &columns);
+        opcode_xor_and_rotate_gate::<-12>("xor a and rotate", meta, s_xor_d, opcode, rotation, D_ROTATIONS, columns, lookup);
+        opcode_xor_and_rotate_gate::<4>("xor c and rotate", meta, s_xor_b, opcode, rotation, B_ROTATIONS, columns, lookup);
 
         RoundGates {
-            s_add_offset_1,
-            s_add_offset_4,
-            s_add_offset_5,
-            s_add_m_and_offset_neg_15,
-            s_add_m_and_offset_neg_12,
-            s_add_m_and_offset_neg_11,
+            opcode,
+            rotation,
+            s_add_m,
+            s_xor_b,
+            s_add,
+            s_xor_d,
+            s_main_round,
+            s_lookup,
             s_decompose,
-            s_decompose_or_copy_in_main_round,
-
-            s_check_s_round,
         }
     }
 }
@@ -235,6 +272,7 @@ impl SubRound4Gates {
 
 #[derive(Debug, Clone)]
 pub struct HxorGates {
+    // decomposes h, v_i and v_(i+8) and checks h is the xor of h_prev, v_i and v_(i+8)
     pub s_h_triple_xor: Selector,
 }
 
@@ -248,14 +286,23 @@ impl HxorGates {
         meta.create_gate("h_xor", |meta| {
             let s = meta.query_selector(s_h_triple_xor);
 
-            let (_, h_prev_spread) = query_table(meta, lookup, Rotation(-1 * (MAX_ROUND as i32+1) * ROWS_PER_ROUND as i32));
-            let (_, v_l_spread) = query_table(meta, lookup, Rotation(-16 * 4));
-            let (_, v_r_spread) = query_table(meta, lookup, Rotation(-8 * 4));
+            let (_, h_prev_spread) = query_table(meta, lookup, Rotation(-1 * (MAX_ROUND as i32+1) * columns.rows_per_round() as i32));
+            let (v_l_dense, v_l_spread) = query_table(meta, lookup, columns.word(-16));
+            let (v_r_dense, v_r_spread) = query_table(meta, lookup, columns.word(-8));
 
-            // h_spread is the even bits
+            // the last subround is decomposed again when it is a padding round
+            let v_l = columns.query_cell(meta, -16, 0);
+            let v_r = columns.query_cell(meta, -8, 0);
+            let v_l_dense = compose_dense_from_bits(&v_l_dense);
+            let v_r_dense = compose_dense_from_bits(&v_r_dense);
+
+            // h_spread is the even bits, the odd bits are the carries of the sum in the xor slots
             let (_, h_spread) = query_table(meta, lookup, Rotation::cur());
-            // let new_spread_bits = query_columns::<4>(meta, &columns.spread_bits, Rotation::cur());
-            let h_odd_spread = meta.query_advice(columns.num, Rotation(3));
+            let h_odd_spread = query_odd(meta, lookup);
+
+            let h = columns.query_cell(meta, 0, 0);
+            let (h_dense, _) = query_table(meta, lookup, Rotation::cur());
+            let h_dense = compose_dense_from_bits(&h_dense);
 
             let h_prev_spread = compose_spread_from_bits(&h_prev_spread);
             let v_l_spread = compose_spread_from_bits(&v_l_spread);
@@ -265,6 +312,9 @@ impl HxorGates {
             Constraints::with_selector(
                 s,
                 vec![
+                    h - h_dense,
+                    v_l - v_l_dense,
+                    v_r - v_r_dense,
                     h_prev_spread + v_l_spread + v_r_spread -
                         (h_spread + h_odd_spread * Expression::Constant(Fr::from(2))),
                 ],
@@ -278,91 +328,341 @@ impl HxorGates {
 }
 
 
-fn offset_add_gate<const OFFSET: i32, const ADD_M: bool>(
+// Argon2 main round: the same state layout as `RoundGates` without the message words,
+// every `a + b` is replaced by BlaMka `a + b + 2 * lo32(a) * lo32(b)`.
+#[derive(Debug, Clone)]
+pub struct BlaMkaGates {
+    pub s_blamka_offset_1: Selector,
+    pub s_blamka_offset_4: Selector,
+    pub s_blamka_offset_5: Selector,
+
+    pub s_blamka_offset_neg_15: Selector,
+    pub s_blamka_offset_neg_12: Selector,
+    pub s_blamka_offset_neg_11: Selector,
+
+    pub s_decompose: Selector,
+
+    // a ^ b of two words 4 rows apart
+    pub s_xor: Selector,
+}
+
+impl BlaMkaGates {
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        columns: &Columns,
+        lookup: &SpreadInputs,
+    ) -> Self {
+        let s_blamka_offset_1 = meta.selector();
+        let s_blamka_offset_4 = meta.selector();
+        let s_blamka_offset_5 = meta.selector();
+
+        let s_blamka_offset_neg_15 = meta.selector();
+        let s_blamka_offset_neg_12 = meta.selector();
+        let s_blamka_offset_neg_11 = meta.selector();
+
+        let s_decompose = meta.selector();
+        let s_xor = meta.selector();
+
+        meta.create_gate("blamka decompose check", |meta| {
+            let s = meta.query_selector(s_decompose);
+
+            let val = columns.query_cell(meta, 0, 0);
+
+            let (dense_bits, _) = query_table(meta, &lookup, Rotation::cur());
+            let dense_val = compose_dense_from_bits(&dense_bits);
+
+            Constraints::with_selector(s, vec![
+                val - dense_val,
+            ])
+        });
+
+        // word 0: a, word 1: b, word 2: a ^ b, word 3: a & b
+        meta.create_gate("blamka xor", |meta| {
+            let s = meta.query_selector(s_xor);
+
+            let (_, a) = query_table(meta, lookup, Rotation::cur());
+            let (_, b) = query_table(meta, lookup, columns.word(1));
+            let (_, even) = query_table(meta, lookup, columns.word(2));
+            let (_, odd) = query_table(meta, lookup, columns.word(3));
+
+            Constraints::with_selector(s, vec![
+                compose_spread_from_bits(&a) + compose_spread_from_bits(&b)
+                    - compose_spread_from_bits(&even)
+                    - compose_spread_from_bits(&odd) * Expression::Constant(Fr::from(2)),
+            ])
+        });
+
+        blamka_add_gate::<1>("blamka_add_1", meta, s_blamka_offset_1, columns, lookup);
+        blamka_add_gate::<4>("blamka_add_4", meta, s_blamka_offset_4, columns, lookup);
+        blamka_add_gate::<5>("blamka_add_5", meta, s_blamka_offset_5, columns, lookup);
+
+        blamka_add_gate::<-15>("blamka_add_neg_15", meta, s_blamka_offset_neg_15, columns, lookup);
+        blamka_add_gate::<-12>("blamka_add_neg_12", meta, s_blamka_offset_neg_12, columns, lookup);
+        blamka_add_gate::<-11>("blamka_add_neg_11", meta, s_blamka_offset_neg_11, columns, lookup);
+
+        Self {
+            s_blamka_offset_1,
+            s_blamka_offset_4,
+            s_blamka_offset_5,
+            s_blamka_offset_neg_15,
+            s_blamka_offset_neg_12,
+            s_blamka_offset_neg_11,
+            s_decompose,
+            s_xor,
+        }
+    }
+}
+
+// sum of the operands `word(BASE + shift)` weighted by the Lagrange basis of `SHIFTS`,
+// which is one for the shift in the opcode and zero for the others
+fn select_operand<const BASE: i32>(
+    opcode: Expression<Fr>,
+    mut operand: impl FnMut(i32) -> Expression<Fr>,
+) -> Expression<Fr> {
+    SHIFTS.iter().fold(Expression::Constant(Fr::zero()), |acc, &shift| {
+        acc + lagrange_basis(opcode.clone(), &SHIFTS, shift) * operand(BASE + shift)
+    })
+}
+
+// the Lagrange basis of `keys` for `key` at `x`, one when x is `key` and zero at the other keys
+fn lagrange_basis(x: Expression<Fr>, keys: &[i32], key: i32) -> Expression<Fr> {
+    keys.iter().filter(|&&other| other != key).fold(Expression::Constant(Fr::one()), |basis, &other| {
+        let denominator = Field::invert(&shift_to_fr(key - other)).unwrap();
+        basis * (x.clone() - Expression::Constant(shift_to_fr(other))) * Expression::Constant(denominator)
+    })
+}
+
+// the spread of the odd bits of the xor in the xor slots of the current word
+pub(crate) fn query_odd(meta: &mut VirtualCells<Fr>, lookup: &SpreadInputs) -> Expression<Fr> {
+    let odd = (0..4).map(|i| query_xor(meta, lookup, Rotation::cur(), XOR_ODD + i).1).collect::<Vec<_>>();
+    compose_spread_from_bits(&odd)
+}
+
+// The spread of the xor the current word is the rotation right by R of: its limbs moved back
+// left by R, the limb R splits from its low and high parts in the xor slots. The parts are looked
+// up below 2^width and 2^(16 - width), so with the check that they make the limb every part is
+// in its bits. The check is None when no limb is split.
+fn unrotate(
+    meta: &mut VirtualCells<Fr>,
+    lookup: &SpreadInputs,
+    rotate: usize,
+) -> (Expression<Fr>, Option<Expression<Fr>>) {
+    let (dense, spread) = query_table(meta, lookup, Rotation::cur());
+    let split = split_limb(rotate);
+
+    let xor = (0..4)
+        .filter(|&i| split.map(|(limb, _)| limb) != Some(i))
+        .fold(Expression::Constant(Fr::zero()), |acc, i| {
+            acc + spread[i].clone() * Expression::Constant(Fr::from_u128(1 << (2 * ((16 * i + rotate) % 64))))
+        });
+
+    match split {
+        None => (xor, None),
+        Some((limb, width)) => {
+            let (low_dense, low_spread) = query_xor(meta, lookup, Rotation::cur(), XOR_LOW);
+            let (high_dense, high_spread) = query_xor(meta, lookup, Rotation::cur(), XOR_HIGH);
+
+            let xor = xor + high_spread + low_spread * Expression::Constant(Fr::from_u128(1 << (2 * (64 - width))));
+            let check = dense[limb].clone() - low_dense - high_dense * Expression::Constant(Fr::from(1 << width));
+
+            (xor, Some(check))
+        }
+    }
+}
+
+pub(crate) fn shift_to_fr(shift: i32) -> Fr {
+    if shift < 0 {
+        -Fr::from(shift.unsigned_abs() as u64)
+    } else {
+        Fr::from(shift as u64)
+    }
+}
+
+fn offset_add_gate<const BASE: i32, const ADD_M: bool>(
     name: &'static str,
     meta: &mut ConstraintSystem<bn256::Fr>,
     selector: Selector,
+    opcode: Column<Fixed>,
     columns: &Columns,
 ) {
     if ADD_M {
-        assert!(OFFSET < 0);
+        assert!(BASE < 0);
     }
 
     meta.create_gate(name, |meta| {
         let s = meta.query_selector(selector);
+        let opcode = meta.query_fixed(opcode, Rotation::cur());
 
-        let prev_val = meta.query_advice(columns.num, Rotation(-16 * 4));
-        let rhs = meta.query_advice(columns.num, Rotation(OFFSET * 4));
+        let prev_val = columns.query_cell(meta, -16, 0);
+        let rhs = select_operand::<BASE>(opcode, |offset| columns.query_cell(meta, offset, 0));
 
-        let new_val = meta.query_advice(columns.num, Rotation::cur());
+        let new_val = columns.query_cell(meta, 0, 0);
 
-        // let m = if ADD_M {
-        //     meta.query_advice(columns.num, Rotation)
-        // }
         let m = if ADD_M {
-            meta.query_advice(columns.num, Rotation::next())
+            columns.query_cell(meta, 0, 1)
         } else {
             Expression::Constant(bn256::Fr::zero())
         };
 
-        let carry = meta.query_advice(columns.num, Rotation(2));
+        let carry = columns.query_cell(meta, 0, 2);
 
-        let s_round = meta.query_advice(columns.s_round, Rotation::cur());
+        let s_round = columns.query_s_round(meta);
 
-        // TODO: check carry in [0, 1] ?
+        // the carry of old_val + rhs is 0 or 1, adding m makes it up to 2, and 0 in a padding round
+        let carry_range = (0..=(ADD_M as u64 + 1)).fold(Expression::Constant(Fr::one()), |acc, i| {
+            acc * (carry.clone() - Expression::Constant(Fr::from(i)))
+        });
 
         // cur + 2^64 * carry = old_val + rhs + m(optional)
 
-        let mut check = s_round.clone() *
+        let check = s_round.clone() *
             (
-                new_val + carry * Expression::Constant(Fr::from_u128(1 << 64))
+                new_val + carry.clone() * Expression::Constant(Fr::from_u128(1 << 64))
                     - prev_val - rhs - m
             );
+        let mut checks = vec![check, carry_range];
 
-        Constraints::with_selector(s, vec![check])
+        // the carry of a padding round is zero, nothing else reads it
+        if columns.rounds.is_some() {
+            checks.push((Expression::Constant(Fr::one()) - s_round) * carry);
+        }
+
+        Constraints::with_selector(s, checks)
     });
 }
 
-pub(crate) fn xor_and_rotate_gate<const XOR: i32, const R: usize>(
+// `xor_and_rotate_gate` with the operand shift from `opcode` and the rotation from `rotation`,
+// one of `rotations`: the unrotated spread and the split check of each are weighted by their
+// Lagrange basis at `rotation`
+#[allow(clippy::too_many_arguments)]
+fn opcode_xor_and_rotate_gate<const BASE: i32>(
     name: &'static str,
     meta: &mut ConstraintSystem<bn256::Fr>,
     selector: Selector,
+    opcode: Column<Fixed>,
+    rotation: Column<Fixed>,
+    rotations: [usize; 2],
     columns: &Columns,
     lookup: &SpreadInputs,
 ) {
     meta.create_gate(name, |meta| {
         let s = meta.query_selector(selector);
+        let opcode = meta.query_fixed(opcode, Rotation::cur());
+        let rotation = meta.query_fixed(rotation, Rotation::cur());
+
+        let (_, old_spread_bits) = query_table(meta, lookup, columns.word(-16));
+
+        let v_s_old = compose_spread_from_bits(&old_spread_bits);
+        let v_s_rhs = select_operand::<BASE>(opcode, |offset| {
+            let (_, rhs_spread_bits) = query_table(meta, lookup, columns.word(offset));
+            compose_spread_from_bits(&rhs_spread_bits)
+        });
+        let v_s_odd = query_odd(meta, lookup);
+
+        let keys = rotations.map(|rotate| rotate as i32);
+        let unrotated = rotations.map(|rotate| {
+            let basis = lagrange_basis(rotation.clone(), &keys, rotate as i32);
+            let (xor, check) = unrotate(meta, lookup, rotate);
+            (basis.clone() * xor, check.map(|check| basis * check))
+        });
+
+        let v_s_xor = unrotated.iter().fold(Expression::Constant(Fr::zero()), |acc, (xor, _)| acc + xor.clone());
+
+        let s_round = columns.query_s_round(meta);
+
+        let check_xor = (v_s_xor + v_s_odd * Expression::Constant(bn256::Fr::from(2))) - v_s_old - v_s_rhs;
 
-        let v_s_even_0 = meta.query_advice(columns.num, Rotation::next());
-        let v_s_even_1 = meta.query_advice(columns.num, Rotation(2));
-        let v_s_odd = meta.query_advice(columns.num, Rotation(3));
+        let mut checks = vec![s_round.clone() * check_xor];
+        checks.extend(unrotated.into_iter().filter_map(|(_, check)| check).map(|check| s_round.clone() * check));
 
-        let (_, cur_spread_bits) = query_table(meta, lookup, Rotation::cur());
+        // the xor slots of a padding round are zero, nothing else reads them
+        if columns.rounds.is_some() {
+            let padding = Expression::Constant(bn256::Fr::one()) - s_round;
 
-        let (_, old_spread_bits) = query_table(meta, lookup, Rotation(-16 * 4));
-        let (_, rhs_spread_bits) = query_table(meta, lookup, Rotation(XOR * 4));
+            let split = rotations.iter().any(|&rotate| split_limb(rotate).is_some());
+            let slots = if split { XOR_HIGH + 1 } else { XOR_LOW };
+
+            for slot in 0..slots {
+                let (dense, spread) = query_xor(meta, lookup, Rotation::cur(), slot);
+                checks.extend([dense, spread].map(|cell| padding.clone() * cell));
+            }
+        }
+
+        Constraints::with_selector(s, checks)
+    });
+}
+
+// (old ^ word(XOR)) >>> R: the current word rotated back, plus twice the odd bits, is the sum of
+// the spreads of the operands
+pub(crate) fn xor_and_rotate_gate<const XOR: i32, const R: usize>(
+    name: &'static str,
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    selector: Selector,
+    columns: &Columns,
+    lookup: &SpreadInputs,
+) {
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+
+        let (_, old_spread_bits) = query_table(meta, lookup, columns.word(-16));
+        let (_, rhs_spread_bits) = query_table(meta, lookup, columns.word(XOR));
 
         let v_s_old = compose_spread_from_bits(&old_spread_bits);
-        let v_s_cur = compose_spread_from_bits(&cur_spread_bits);
         let v_s_rhs = compose_spread_from_bits(&rhs_spread_bits);
+        let v_s_odd = query_odd(meta, lookup);
 
-        // TODO: change assign
-        let even_before_rotate = v_s_even_0.clone() * Expression::Constant(bn256::Fr::from_u128(1 << (R * 2))) + v_s_even_1.clone();
-        let even_after_rotate = v_s_even_1 * Expression::Constant(bn256::Fr::from_u128(1 << (128 - R * 2))) + v_s_even_0;
+        let (v_s_xor, check_split) = unrotate(meta, lookup, R);
 
-        let s_round = meta.query_advice(columns.s_round, Rotation::cur());
+        let s_round = columns.query_s_round(meta);
 
-        let check_xor = (even_before_rotate + v_s_odd * Expression::Constant(bn256::Fr::from(2)))
+        let check_xor = (v_s_xor + v_s_odd * Expression::Constant(bn256::Fr::from(2)))
             - v_s_old - v_s_rhs;
 
-        let check_spread_equality = even_after_rotate - v_s_cur;
+        let mut checks = vec![s_round.clone() * check_xor];
+        checks.extend(check_split.map(|check| s_round * check));
+
+        Constraints::with_selector(s, checks)
+    });
+}
+
+// cell 0: new_val
+// cell 1: lo32(prev_val) * lo32(rhs)
+// cell 2: carry
+fn blamka_add_gate<const OFFSET: i32>(
+    name: &'static str,
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    selector: Selector,
+    columns: &Columns,
+    lookup: &SpreadInputs,
+) {
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+
+        let prev_val = columns.query_cell(meta, -16, 0);
+        let rhs = columns.query_cell(meta, OFFSET, 0);
+
+        let new_val = columns.query_cell(meta, 0, 0);
+        let product = columns.query_cell(meta, 0, 1);
+        let carry = columns.query_cell(meta, 0, 2);
+
+        // the low 32 bits are the first two looked up limbs
+        let (prev_dense, _) = query_table(meta, lookup, columns.word(-16));
+        let (rhs_dense, _) = query_table(meta, lookup, columns.word(OFFSET));
+
+        let prev_lo = compose_val_from_bits_num(&prev_dense[..2], &[16, 16]);
+        let rhs_lo = compose_val_from_bits_num(&rhs_dense[..2], &[16, 16]);
+
+        // a + b + 2 * (2^32 - 1)^2 < 2^66
+        let carry_range = (0..4).fold(Expression::Constant(Fr::one()), |acc, i| {
+            acc * (carry.clone() - Expression::Constant(Fr::from(i)))
+        });
 
-        Constraints::with_selector(
-            s,
-            vec![
-                s_round.clone() * check_xor,
-                s_round.clone() * check_spread_equality,
-            ],
-        )
+        // cur + 2^64 * carry = old_val + rhs + 2 * lo32(old_val) * lo32(rhs)
+        Constraints::with_selector(s, vec![
+            product.clone() - prev_lo * rhs_lo,
+            new_val + carry * Expression::Constant(Fr::from_u128(1 << 64))
+                - prev_val - rhs - product * Expression::Constant(Fr::from(2)),
+            carry_range,
+        ])
     });
 }
@@ -1,15 +1,17 @@
 use halo2_proofs::{
     plonk::{ConstraintSystem, Constraints, Expression, Selector, Error},
     poly::Rotation,
-    circuit::{Layouter, Value},
+    circuit::{AssignedCell, Cell, Region, Value},
 };
 use halo2curves::{FieldExt, bn256::{self, Fr}};
 
 use crate::{spread_table::SpreadInputs, chip::compose_dense_from_bits};
 use crate::chip::Columns;
+use crate::state::{match_state_as_array, RoundWord};
+use crate::{Blake2fTrace, MAX_ROUND};
 
-mod assignment;
-mod gates;
+pub(crate) mod assignment;
+pub(crate) mod gates;
 mod test;
 
 use gates::*;
@@ -21,10 +23,6 @@ pub struct CompressionConfig {
     columns: Columns,
     lookup: SpreadInputs,
     s_global: RoundGates,
-    s_r1: SubRound1Gates,
-    s_r2: SubRound2Gates,
-    s_r3: SubRound3Gates,
-    s_r4: SubRound4Gates,
 
     s_h_xor: HxorGates,
 }
@@ -36,11 +34,9 @@ impl CompressionConfig {
         // message_schedule: Column<Advice>,
         columns: Columns,
     ) -> Self {
+        assert_eq!(lookup.lanes(), columns.lanes(), "a lookup pair for each num column");
+
         let s_global = RoundGates::configure(meta, &columns, &lookup);
-        let s_r1 = SubRound1Gates::configure(meta, &columns, &lookup);
-        let s_r2 = SubRound2Gates::configure(meta, &columns, &lookup);
-        let s_r3 = SubRound3Gates::configure(meta, &columns, &lookup);
-        let s_r4 = SubRound4Gates::configure(meta, &columns, &lookup);
 
         let s_h_xor = HxorGates::configure(meta, &columns, &lookup);
 
@@ -48,24 +44,107 @@ impl CompressionConfig {
             columns,
             lookup,
             s_global,
-            s_r1,
-            s_r2,
-            s_r3,
-            s_r4,
             s_h_xor,
         }
     }
 
+    // rows below the scheduler: round 0, the rounds and the final xor
+    pub fn rows(&self) -> usize {
+        (MAX_ROUND + 1) * self.columns.rows_per_round() + 8 * self.columns.word_rows()
+    }
+
+    // The compress methods continue the region the scheduler started, `init_state` is its output.
+    // They copy the rounds and the final xor from `trace`, the trace of the same inputs.
     pub fn compress(
         &self,
-        layouter: &mut impl Layouter<bn256::Fr>,
+        region: &mut Region<'_, bn256::Fr>,
         init_state: crate::InitializedState,
         rounds: Value<u64>,
-        m: [Value<u64>; 16], //TODO: assigned bits?
+        trace: Value<&Blake2fTrace>,
     ) -> Result<[Value<u64>; 8], Error>{
-        layouter.assign_region(|| "compress", |mut region| {
-            let updated_state = self.assign_round(&mut region, init_state.clone(), rounds, m)?;
-            self.final_h_xor(&mut region, init_state.clone(), updated_state.clone())
-        })
+        self.assign_round(region, init_state.clone(), rounds, trace)?;
+        self.final_h_xor(region, init_state, trace)
+    }
+
+    // Same as `compress`, but `h` and `m` are copied from cells assigned elsewhere and the
+    // counter, flag and rounds are pinned to constants, so compressions can be chained.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compress_assigned(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        init_state: crate::InitializedState,
+        h: &[RoundWord; 8],
+        m: &[AssignedCell<Fr, Fr>; 16],
+        t: [u64; 2],
+        f: u64,
+        rounds: u64,
+        trace: Value<&Blake2fTrace>,
+    ) -> Result<[RoundWord; 8], Error> {
+        let v = match_state_as_array(init_state.state.clone());
+        for (h, v) in h.iter().zip(v.iter()) {
+            region.constrain_equal(h.val.cell(), v.val.cell())?;
+        }
+
+        self.compress_chained(region, init_state, m, t, f, rounds, trace)
+    }
+
+    // `compress_assigned` for a state from `SchedulerConfig::process_chained`, whose v0-v7 are
+    // already the words of the previous compression.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compress_chained(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        init_state: crate::InitializedState,
+        m: &[AssignedCell<Fr, Fr>; 16],
+        t: [u64; 2],
+        f: u64,
+        rounds: u64,
+        trace: Value<&Blake2fTrace>,
+    ) -> Result<[RoundWord; 8], Error> {
+        for (m, assigned_m) in m.iter().zip(init_state.m.iter()) {
+            region.constrain_equal(m.cell(), assigned_m.cell())?;
+        }
+
+        for (t, assigned_t) in t.iter().zip(init_state.t.iter()) {
+            region.constrain_constant(assigned_t.val.cell(), Fr::from(*t))?;
+        }
+        region.constrain_constant(init_state.f.val.cell(), Fr::from(f))?;
+        region.constrain_constant(init_state.round.cell(), Fr::from(rounds))?;
+
+        self.assign_round(region, init_state.clone(), Value::known(rounds), trace)?;
+        self.final_h_xor_assigned(region, init_state, trace)
+    }
+
+    // Same as `compress_assigned`, but the counter, flag and rounds are copied from cells too, as
+    // a precompile reads every input from its calldata.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compress_cells(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        init_state: crate::InitializedState,
+        h: &[Cell; 8],
+        m: &[Cell; 16],
+        t: &[Cell; 2],
+        f: Cell,
+        rounds: Cell,
+        trace: Value<&Blake2fTrace>,
+    ) -> Result<[RoundWord; 8], Error> {
+        let v = match_state_as_array(init_state.state.clone());
+        for (h, v) in h.iter().zip(v.iter()) {
+            region.constrain_equal(*h, v.val.cell())?;
+        }
+        for (m, assigned_m) in m.iter().zip(init_state.m.iter()) {
+            region.constrain_equal(*m, assigned_m.cell())?;
+        }
+
+        for (t, assigned_t) in t.iter().zip(init_state.t.iter()) {
+            region.constrain_equal(*t, assigned_t.val.cell())?;
+        }
+        region.constrain_equal(f, init_state.f.val.cell())?;
+        region.constrain_equal(rounds, init_state.round.cell())?;
+
+        let rounds = init_state.round.value().map(|r| r.get_lower_128() as u64);
+        self.assign_round(region, init_state.clone(), rounds, trace)?;
+        self.final_h_xor_assigned(region, init_state, trace)
     }
 }
@@ -0,0 +1,281 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use crate::byte_table::{ByteTableChip, ByteTableConfig};
+use crate::transcript::{compose, compose_gate, ROWS_PER_COMPOSE};
+use crate::{Blake2fChip, Blake2fConfig, Blake2fInstructions, Blake2fIv, Blake2fState};
+
+mod table;
+#[cfg(test)]
+mod test;
+
+pub use table::{Eip152Table, Eip152TableChip, Eip152TableConfig, Eip152TableInstructions, PrecompileTable};
+
+// rounds (4) || h (64) || m (128) || t (16) || f (1)
+pub const INPUT_BYTES: usize = 213;
+pub const OUTPUT_BYTES: usize = 64;
+
+const ROUNDS_BYTES: usize = 4;
+const F_OFFSET: usize = INPUT_BYTES - 1;
+
+// The BLAKE2F precompile of EIP-152 over its calldata bytes: the input is parsed into the words
+// of the compression and the output state is encoded back into 64 bytes.
+pub struct Eip152<F: FieldExt, CS: Eip152Instructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Eip152Chip: Eip152Instructions<F>> Eip152<F, Eip152Chip> {
+    pub fn new(chip: Eip152Chip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn call(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let input = self.chip.parse_input(layouter, input)?;
+        let state = self.chip.compress(layouter, &self.iv, &input)?;
+        self.chip.encode_output(layouter, &state)
+    }
+}
+
+// the compression inputs parsed from the calldata
+#[derive(Clone, Debug)]
+pub struct Eip152Input<F: FieldExt> {
+    pub rounds: AssignedCell<F, F>,
+    pub h: [AssignedCell<F, F>; 8],
+    pub m: [AssignedCell<F, F>; 16],
+    pub t: [AssignedCell<F, F>; 2],
+    // the flag word, 0 or 2^64 - 1
+    pub f: AssignedCell<F, F>,
+}
+
+pub trait Eip152Instructions<F: FieldExt>: Chip<F> {
+    type State: Clone + std::fmt::Debug;
+    // the cells every compression copies
+    type Iv: Clone + std::fmt::Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
+
+    // range checked bytes
+    fn assign_bytes(&self, layouter: &mut impl Layouter<F>, bytes: &[Value<u8>]) -> Result<Vec<AssignedCell<F, F>>, Error>;
+
+    // big endian rounds, little endian words and an f byte of 0 or 1
+    fn parse_input(&self, layouter: &mut impl Layouter<F>, input: &[AssignedCell<F, F>]) -> Result<Eip152Input<F>, Error>;
+
+    // `Error::Synthesis` for more than MAX_ROUND rounds, a valid input the circuit cannot prove
+    fn compress(&self, layouter: &mut impl Layouter<F>, iv: &Self::Iv, input: &Eip152Input<F>) -> Result<Self::State, Error>;
+
+    // the 8 state words as 64 little endian bytes
+    fn encode_output(&self, layouter: &mut impl Layouter<F>, state: &Self::State) -> Result<Vec<AssignedCell<F, F>>, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct Eip152Config {
+    blake2f: Blake2fConfig,
+    bytes: Column<Advice>,
+    byte_table: ByteTableConfig,
+    s_word: Selector,
+    s_flag: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct Eip152Chip {
+    config: Eip152Config,
+    blake2f: Blake2fChip,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for Eip152Chip {
+    type Config = Eip152Config;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Eip152Chip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            blake2f: Blake2fChip::construct(config.blake2f.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let blake2f = Blake2fChip::configure(meta);
+
+        let bytes = meta.advice_column();
+        meta.enable_equality(bytes);
+
+        let byte_table = ByteTableChip::configure(meta, bytes);
+
+        // 8 little endian bytes to a word, or the 4 big endian bytes of rounds reversed
+        let s_word = meta.selector();
+        compose_gate(meta, "compose word", s_word, bytes, Fr::from(1 << 8));
+
+        // row 0 the f byte, row 1 the flag word of the compression
+        let s_flag = meta.selector();
+        meta.create_gate("flag", |meta| {
+            let s = meta.query_selector(s_flag);
+            let f = meta.query_advice(bytes, Rotation::cur());
+            let flag = meta.query_advice(bytes, Rotation::next());
+
+            let one = Expression::Constant(Fr::one());
+            let max = Expression::Constant(Fr::from(u64::MAX));
+
+            Constraints::with_selector(s, vec![f.clone() * (one - f.clone()), flag - f * max])
+        });
+
+        Eip152Config { blake2f, bytes, byte_table, s_word, s_flag }
+    }
+
+    pub fn load(
+        config: &Eip152Config,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        ByteTableChip::load(&config.byte_table, layouter)
+    }
+}
+
+impl Eip152Instructions<bn256::Fr> for Eip152Chip {
+    type State = Blake2fState;
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        let iv = self.blake2f.initialize(layouter)?;
+        Self::load(&self.config, layouter)?;
+        Ok(iv)
+    }
+
+    fn assign_bytes(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        bytes: &[Value<u8>],
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        layouter.assign_region(|| "eip152 bytes", |mut region| {
+            bytes
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| {
+                    self.config.byte_table.enable(&mut region, i)?;
+                    region.assign_advice(|| "assign byte", self.config.bytes, i, || byte.map(|b| Fr::from(b as u64)))
+                })
+                .collect()
+        })
+    }
+
+    fn parse_input(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        input: &[AssignedCell<Fr, Fr>],
+    ) -> Result<Eip152Input<Fr>, Error> {
+        assert_eq!(input.len(), INPUT_BYTES);
+
+        layouter.assign_region(|| "eip152 input", |mut region| {
+            let radix = Fr::from(1 << 8);
+            let limbs = |bytes: &[AssignedCell<Fr, Fr>]| -> Vec<_> {
+                bytes.iter().map(|byte| (byte.value().copied(), byte.cell())).collect()
+            };
+
+            // the rounds are big endian, their upper 4 limbs are zero
+            let zero = region.assign_advice_from_constant(|| "assign zero", self.config.bytes, 0, Fr::zero())?;
+            let mut rounds: Vec<_> = input[..ROUNDS_BYTES].iter().rev().cloned().collect();
+            rounds.resize(ROWS_PER_COMPOSE - 1, zero);
+            let rounds = compose(&mut region, self.config.bytes, 1, self.config.s_word, radix, &limbs(&rounds))?;
+
+            // h, m and t are 26 consecutive little endian words
+            let words = input[ROUNDS_BYTES..F_OFFSET]
+                .chunks(8)
+                .enumerate()
+                .map(|(i, word)| {
+                    let offset = 1 + (i + 1) * ROWS_PER_COMPOSE;
+                    compose(&mut region, self.config.bytes, offset, self.config.s_word, radix, &limbs(word))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // an f byte other than 0 or 1 is an invalid input
+            let offset = 1 + (words.len() + 1) * ROWS_PER_COMPOSE;
+            self.config.s_flag.enable(&mut region, offset)?;
+            let f = region.assign_advice(|| "copy f", self.config.bytes, offset, || input[F_OFFSET].value().copied())?;
+            region.constrain_equal(f.cell(), input[F_OFFSET].cell())?;
+
+            let flag = f.value().map(|f| if *f == Fr::zero() { Fr::zero() } else { Fr::from(u64::MAX) });
+            let flag = region.assign_advice(|| "assign flag", self.config.bytes, offset + 1, || flag)?;
+
+            Ok(Eip152Input {
+                rounds,
+                h: words[..8].to_vec().try_into().unwrap(),
+                m: words[8..24].to_vec().try_into().unwrap(),
+                t: words[24..].to_vec().try_into().unwrap(),
+                f: flag,
+            })
+        })
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Blake2fIv,
+        input: &Eip152Input<Fr>,
+    ) -> Result<Self::State, Error> {
+        self.blake2f.compress_cells(
+            layouter,
+            iv,
+            input.h.clone(),
+            input.m.clone(),
+            input.t.clone(),
+            input.f.clone(),
+            input.rounds.clone(),
+        )
+    }
+
+    fn encode_output(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        state: &Self::State,
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        // the state words are recomposed from range checked bytes
+        layouter.assign_region(|| "eip152 output", |mut region| {
+            let mut output = Vec::with_capacity(OUTPUT_BYTES);
+
+            for (i, (word, cell)) in state.value().iter().zip(state.cells()).enumerate() {
+                let offset = i * ROWS_PER_COMPOSE;
+                self.config.s_word.enable(&mut region, offset)?;
+
+                for j in 0..8 {
+                    self.config.byte_table.enable(&mut region, offset + j)?;
+                    let byte = word.map(|w| Fr::from((w >> (8 * j)) & 0xff));
+                    output.push(region.assign_advice(|| "assign output byte", self.config.bytes, offset + j, || byte)?);
+                }
+
+                let composed = region.assign_advice(|| "copy state word", self.config.bytes, offset + 8, || word.map(Fr::from))?;
+                region.constrain_equal(composed.cell(), cell)?;
+            }
+
+            Ok(output)
+        })
+    }
+}
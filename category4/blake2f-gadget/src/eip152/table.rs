@@ -0,0 +1,256 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter, Value},
+    plonk::{
+        Advice, Challenge, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, SecondPhase,
+        Selector, VirtualCells,
+    },
+    poly::Rotation,
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use super::{Eip152Chip, Eip152Config, Eip152Input, Eip152Instructions};
+use crate::{Blake2fIv, Blake2fState};
+
+// The rows an EVM circuit looks up to check a BLAKE2F call: (q_enable, id, input RLC, output RLC).
+// The id is copied from the cell the caller labels the call with, the RLCs are second phase advice.
+#[derive(Clone, Copy, Debug)]
+pub struct PrecompileTable {
+    pub q_enable: Column<Fixed>,
+    pub id: Column<Advice>,
+    pub input_rlc: Column<Advice>,
+    pub output_rlc: Column<Advice>,
+}
+
+impl PrecompileTable {
+    pub fn construct(meta: &mut ConstraintSystem<bn256::Fr>) -> Self {
+        let q_enable = meta.fixed_column();
+        let id = meta.advice_column();
+        let input_rlc = meta.advice_column_in(SecondPhase);
+        let output_rlc = meta.advice_column_in(SecondPhase);
+
+        meta.enable_equality(id);
+        meta.enable_equality(input_rlc);
+        meta.enable_equality(output_rlc);
+
+        Self { q_enable, id, input_rlc, output_rlc }
+    }
+
+    // the right hand side of a `lookup_any` into the table
+    pub fn table_exprs(&self, meta: &mut VirtualCells<'_, bn256::Fr>) -> Vec<Expression<bn256::Fr>> {
+        vec![
+            meta.query_fixed(self.q_enable, Rotation::cur()),
+            meta.query_advice(self.id, Rotation::cur()),
+            meta.query_advice(self.input_rlc, Rotation::cur()),
+            meta.query_advice(self.output_rlc, Rotation::cur()),
+        ]
+    }
+}
+
+// `Eip152` that records a table row for each call
+pub struct Eip152Table<F: FieldExt, CS: Eip152TableInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    calls: Vec<(AssignedCell<F, F>, AssignedCell<F, F>, AssignedCell<F, F>)>,
+}
+
+impl<F: FieldExt, Eip152TableChip: Eip152TableInstructions<F>> Eip152Table<F, Eip152TableChip> {
+    pub fn new(chip: Eip152TableChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            calls: vec![],
+        })
+    }
+
+    pub fn call(
+        &mut self,
+        layouter: &mut impl Layouter<F>,
+        id: &AssignedCell<F, F>,
+        input: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let parsed = self.chip.parse_input(layouter, input)?;
+        let state = self.chip.compress(layouter, &self.iv, &parsed)?;
+        let output = self.chip.encode_output(layouter, &state)?;
+
+        let input_rlc = self.chip.rlc(layouter, input)?;
+        let output_rlc = self.chip.rlc(layouter, &output)?;
+        self.calls.push((id.clone(), input_rlc, output_rlc));
+
+        Ok(output)
+    }
+
+    // one row per call, in call order
+    pub fn assign_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.chip.assign_table(layouter, &self.calls)
+    }
+}
+
+pub trait Eip152TableInstructions<F: FieldExt>: Eip152Instructions<F> {
+    // sum bytes[i] * r^(n - 1 - i), the first byte gets the highest power
+    fn rlc(&self, layouter: &mut impl Layouter<F>, bytes: &[AssignedCell<F, F>]) -> Result<AssignedCell<F, F>, Error>;
+
+    fn assign_table(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        calls: &[(AssignedCell<F, F>, AssignedCell<F, F>, AssignedCell<F, F>)],
+    ) -> Result<(), Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct Eip152TableConfig {
+    eip152: Eip152Config,
+    rlc: Column<Advice>,
+    s_rlc: Selector,
+    challenge: Challenge,
+    table: PrecompileTable,
+}
+
+#[derive(Clone, Debug)]
+pub struct Eip152TableChip {
+    config: Eip152TableConfig,
+    eip152: Eip152Chip,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for Eip152TableChip {
+    type Config = Eip152TableConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Eip152TableChip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            eip152: Eip152Chip::construct(config.eip152.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    // The table and the challenge are owned by the caller, so the EVM circuit can take the same
+    // RLCs and look them up. The challenge must be usable after the first phase, where the bytes
+    // are committed.
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        table: PrecompileTable,
+        challenge: Challenge,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let eip152 = Eip152Chip::configure(meta);
+
+        let rlc = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(rlc);
+
+        // acc = acc_prev * r + byte, the accumulator starts from a zero constant
+        let s_rlc = meta.selector();
+        meta.create_gate("rlc", |meta| {
+            let s = meta.query_selector(s_rlc);
+            let r = meta.query_challenge(challenge);
+            let byte = meta.query_advice(eip152.bytes, Rotation::cur());
+            let acc = meta.query_advice(rlc, Rotation::cur());
+            let prev = meta.query_advice(rlc, Rotation::prev());
+
+            Constraints::with_selector(s, vec![acc - (prev * r + byte)])
+        });
+
+        Eip152TableConfig { eip152, rlc, s_rlc, challenge, table }
+    }
+
+    pub fn eip152(&self) -> &Eip152Chip {
+        &self.eip152
+    }
+}
+
+impl Eip152Instructions<bn256::Fr> for Eip152TableChip {
+    type State = Blake2fState;
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        self.eip152.initialize(layouter)
+    }
+
+    fn assign_bytes(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        bytes: &[Value<u8>],
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        self.eip152().assign_bytes(layouter, bytes)
+    }
+
+    fn parse_input(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        input: &[AssignedCell<Fr, Fr>],
+    ) -> Result<Eip152Input<Fr>, Error> {
+        self.eip152().parse_input(layouter, input)
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Blake2fIv,
+        input: &Eip152Input<Fr>,
+    ) -> Result<Self::State, Error> {
+        self.eip152().compress(layouter, iv, input)
+    }
+
+    fn encode_output(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        state: &Self::State,
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        self.eip152().encode_output(layouter, state)
+    }
+}
+
+impl Eip152TableInstructions<bn256::Fr> for Eip152TableChip {
+    fn rlc(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        bytes: &[AssignedCell<Fr, Fr>],
+    ) -> Result<AssignedCell<Fr, Fr>, Error> {
+        let r = layouter.get_challenge(self.config.challenge);
+
+        layouter.assign_region(|| "rlc", |mut region| {
+            let mut acc = region.assign_advice_from_constant(|| "assign zero", self.config.rlc, 0, Fr::zero())?;
+
+            for (i, byte) in bytes.iter().enumerate() {
+                self.config.s_rlc.enable(&mut region, i + 1)?;
+                byte.copy_advice(|| "copy byte", &mut region, self.config.eip152.bytes, i + 1)?;
+
+                let value = acc.value().copied() * r + byte.value().copied();
+                acc = region.assign_advice(|| "assign rlc", self.config.rlc, i + 1, || value)?;
+            }
+
+            Ok(acc)
+        })
+    }
+
+    fn assign_table(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        calls: &[(AssignedCell<Fr, Fr>, AssignedCell<Fr, Fr>, AssignedCell<Fr, Fr>)],
+    ) -> Result<(), Error> {
+        let table = self.config.table;
+
+        layouter.assign_region(|| "blake2f precompile table", |mut region| {
+            for (i, (id, input_rlc, output_rlc)) in calls.iter().enumerate() {
+                region.assign_fixed(|| "enable row", table.q_enable, i, || Value::known(Fr::one()))?;
+                id.copy_advice(|| "copy id", &mut region, table.id, i)?;
+                input_rlc.copy_advice(|| "copy input rlc", &mut region, table.input_rlc, i)?;
+                output_rlc.copy_advice(|| "copy output rlc", &mut region, table.output_rlc, i)?;
+            }
+
+            Ok(())
+        })
+    }
+}
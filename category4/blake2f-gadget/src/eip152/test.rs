@@ -0,0 +1,295 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Advice, Challenge, Circuit, Column, ConstraintSystem, Error, FirstPhase, Instance, SecondPhase, Selector},
+    poly::Rotation,
+};
+use halo2curves::bn256;
+
+use crate::MAX_ROUND;
+
+use super::{
+    Eip152, Eip152Chip, Eip152Config, Eip152Instructions, Eip152Table, Eip152TableChip, Eip152TableConfig,
+    PrecompileTable, INPUT_BYTES, OUTPUT_BYTES,
+};
+
+// https://eips.ethereum.org/EIPS/eip-152#test-cases, h and m of vectors 4 to 7
+const H: [u8; 64] = [
+    0x48, 0xc9, 0xbd, 0xf2, 0x67, 0xe6, 0x09, 0x6a, 0x3b, 0xa7, 0xca, 0x84, 0x85, 0xae, 0x67, 0xbb,
+    0x2b, 0xf8, 0x94, 0xfe, 0x72, 0xf3, 0x6e, 0x3c, 0xf1, 0x36, 0x1d, 0x5f, 0x3a, 0xf5, 0x4f, 0xa5,
+    0xd1, 0x82, 0xe6, 0xad, 0x7f, 0x52, 0x0e, 0x51, 0x1f, 0x6c, 0x3e, 0x2b, 0x8c, 0x68, 0x05, 0x9b,
+    0x6b, 0xbd, 0x41, 0xfb, 0xab, 0xd9, 0x83, 0x1f, 0x79, 0x21, 0x7e, 0x13, 0x19, 0xcd, 0xe0, 0x5b,
+];
+
+// test vector 5
+const OUTPUT_F1: [u8; OUTPUT_BYTES] = [
+    0xba, 0x80, 0xa5, 0x3f, 0x98, 0x1c, 0x4d, 0x0d, 0x6a, 0x27, 0x97, 0xb6, 0x9f, 0x12, 0xf6, 0xe9,
+    0x4c, 0x21, 0x2f, 0x14, 0x68, 0x5a, 0xc4, 0xb7, 0x4b, 0x12, 0xbb, 0x6f, 0xdb, 0xff, 0xa2, 0xd1,
+    0x7d, 0x87, 0xc5, 0x39, 0x2a, 0xab, 0x79, 0x2d, 0xc2, 0x52, 0xd5, 0xde, 0x45, 0x33, 0xcc, 0x95,
+    0x18, 0xd3, 0x8a, 0xa8, 0xdb, 0xf1, 0x92, 0x5a, 0xb9, 0x23, 0x86, 0xed, 0xd4, 0x00, 0x99, 0x23,
+];
+
+// test vector 6
+const OUTPUT_F0: [u8; OUTPUT_BYTES] = [
+    0x75, 0xab, 0x69, 0xd3, 0x19, 0x0a, 0x56, 0x2c, 0x51, 0xae, 0xf8, 0xd8, 0x8f, 0x1c, 0x27, 0x75,
+    0x87, 0x69, 0x44, 0x40, 0x72, 0x70, 0xc4, 0x2c, 0x98, 0x44, 0x25, 0x2c, 0x26, 0xd2, 0x87, 0x52,
+    0x98, 0x74, 0x3e, 0x7f, 0x6d, 0x5e, 0xa2, 0xf2, 0xd3, 0xe8, 0xd2, 0x26, 0x03, 0x9c, 0xd3, 0x1b,
+    0x4e, 0x42, 0x6a, 0xc4, 0xf2, 0xd3, 0xd6, 0x66, 0xa6, 0x10, 0xc2, 0x11, 0x6f, 0xde, 0x47, 0x35,
+];
+
+// rounds || h || "abc" padded || t = 3 || f
+fn input(rounds: u32, f: u8) -> Vec<u8> {
+    let mut m = b"abc".to_vec();
+    m.resize(128, 0);
+
+    let t = [3u64.to_le_bytes(), 0u64.to_le_bytes()].concat();
+
+    [&rounds.to_be_bytes()[..], &H[..], &m[..], &t[..], &[f][..]].concat()
+}
+
+struct MyCircuit {
+    input: Vec<u8>,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = (Eip152Config, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            input: vec![0; INPUT_BYTES],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (Eip152Chip::configure(meta), instance)
+    }
+
+    fn synthesize(
+        &self,
+        (config, instance): Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Eip152Chip::construct(config);
+        let eip152 = Eip152::new(chip.clone(), layouter.namespace(|| "load tables"))?;
+
+        let input = chip.assign_bytes(&mut layouter, &self.input.iter().map(|b| Value::known(*b)).collect::<Vec<_>>())?;
+        let output = eip152.call(&mut layouter, &input)?;
+
+        for (i, byte) in output.iter().enumerate() {
+            layouter.constrain_instance(byte.cell(), instance, i)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn run(input: Vec<u8>, output: &[u8]) -> bool {
+    let circuit = MyCircuit { input };
+
+    let instance = output.iter().map(|b| bn256::Fr::from(*b as u64)).collect();
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![instance]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+#[test]
+fn test_eip152_final_block() {
+    assert!(run(input(12, 1), &OUTPUT_F1));
+}
+
+#[test]
+fn test_eip152_not_final_block() {
+    assert!(run(input(12, 0), &OUTPUT_F0));
+}
+
+// test vector 3 is rejected by the precompile, f = 2
+#[test]
+fn test_eip152_invalid_f() {
+    assert!(!run(input(12, 2), &OUTPUT_F1));
+}
+
+// a valid input with more rounds than the circuit lays out, like vector 8, is an error and not a
+// panic of the trace
+#[test]
+fn test_eip152_too_many_rounds() {
+    let circuit = MyCircuit { input: input(MAX_ROUND as u32 + 1, 1) };
+    let instance = OUTPUT_F1.iter().map(|b| bn256::Fr::from(*b as u64)).collect();
+
+    assert!(matches!(MockProver::<bn256::Fr>::run(17, &circuit, vec![instance]), Err(Error::Synthesis)));
+}
+
+#[test]
+fn test_eip152_wrong_output() {
+    let mut output = OUTPUT_F1;
+    output[63] ^= 1;
+
+    assert!(!run(input(12, 1), &output));
+}
+
+// native RLC with the first byte at the highest power
+fn rlc(bytes: &[u8], r: bn256::Fr) -> bn256::Fr {
+    bytes.iter().fold(bn256::Fr::zero(), |acc, b| acc * r + bn256::Fr::from(*b as u64))
+}
+
+// An EVM side lookup of (id, input RLC, output RLC) for the bytes it claims the call had
+#[derive(Clone, Debug)]
+struct EvmConfig {
+    q_lookup: Selector,
+    id: Column<Advice>,
+    input_rlc: Column<Advice>,
+    output_rlc: Column<Advice>,
+    challenge: Challenge,
+}
+
+struct TableCircuit {
+    input: Vec<u8>,
+    claimed_id: u64,
+    claimed_input: Vec<u8>,
+    claimed_output: Vec<u8>,
+}
+
+impl Circuit<bn256::Fr> for TableCircuit {
+    type Config = (Eip152TableConfig, EvmConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            input: vec![0; INPUT_BYTES],
+            claimed_id: 0,
+            claimed_input: vec![0; INPUT_BYTES],
+            claimed_output: vec![0; OUTPUT_BYTES],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        let table = PrecompileTable::construct(meta);
+        let challenge = meta.challenge_usable_after(FirstPhase);
+        let config = Eip152TableChip::configure(meta, table, challenge);
+
+        let q_lookup = meta.complex_selector();
+        let id = meta.advice_column();
+        let input_rlc = meta.advice_column_in(SecondPhase);
+        let output_rlc = meta.advice_column_in(SecondPhase);
+
+        meta.enable_equality(id);
+
+        meta.lookup_any("blake2f call", |meta| {
+            let q = meta.query_selector(q_lookup);
+            let evm = [id, input_rlc, output_rlc].map(|column| meta.query_advice(column, Rotation::cur()));
+
+            let table = table.table_exprs(meta);
+            [q.clone()]
+                .into_iter()
+                .chain(evm.into_iter().map(|e| q.clone() * e))
+                .zip(table)
+                .collect()
+        });
+
+        (config, EvmConfig { q_lookup, id, input_rlc, output_rlc, challenge })
+    }
+
+    fn synthesize(
+        &self,
+        (config, evm): Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Eip152TableChip::construct(config);
+        let mut eip152 = Eip152Table::new(chip.clone(), layouter.namespace(|| "load tables"))?;
+
+        // the EVM side labels the call with 1
+        let id = layouter.assign_region(|| "evm call", |mut region| {
+            region.assign_advice(|| "assign call id", evm.id, 0, || Value::known(bn256::Fr::one()))
+        })?;
+
+        let input = chip.assign_bytes(&mut layouter, &self.input.iter().map(|b| Value::known(*b)).collect::<Vec<_>>())?;
+        eip152.call(&mut layouter, &id, &input)?;
+        eip152.assign_table(&mut layouter)?;
+
+        let r = layouter.get_challenge(evm.challenge);
+        layouter.assign_region(|| "evm lookup", |mut region| {
+            evm.q_lookup.enable(&mut region, 0)?;
+            region.assign_advice(|| "assign id", evm.id, 0, || Value::known(bn256::Fr::from(self.claimed_id)))?;
+            region.assign_advice(|| "assign input rlc", evm.input_rlc, 0, || r.map(|r| rlc(&self.claimed_input, r)))?;
+            region.assign_advice(|| "assign output rlc", evm.output_rlc, 0, || r.map(|r| rlc(&self.claimed_output, r)))?;
+
+            Ok(())
+        })
+    }
+}
+
+fn run_table(claimed_input: Vec<u8>, claimed_output: Vec<u8>) -> bool {
+    run_table_call(1, claimed_input, claimed_output)
+}
+
+fn run_table_call(claimed_id: u64, claimed_input: Vec<u8>, claimed_output: Vec<u8>) -> bool {
+    let circuit = TableCircuit {
+        input: input(12, 1),
+        claimed_id,
+        claimed_input,
+        claimed_output,
+    };
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+// the first and last byte of rounds, of every word of h, m and t, and f
+fn input_boundaries() -> Vec<usize> {
+    let words = (4..INPUT_BYTES - 1).step_by(8).flat_map(|i| [i, i + 7]);
+    [0, 3].into_iter().chain(words).chain([INPUT_BYTES - 1]).collect()
+}
+
+// the first and last byte of every output word
+fn output_boundaries() -> Vec<usize> {
+    (0..OUTPUT_BYTES).step_by(8).flat_map(|i| [i, i + 7]).collect()
+}
+
+fn assert_tampered_rejected(input_bytes: impl IntoIterator<Item = usize>, output_bytes: impl IntoIterator<Item = usize>) {
+    for i in input_bytes {
+        let mut claimed = input(12, 1);
+        claimed[i] ^= 1;
+
+        assert!(!run_table(claimed, OUTPUT_F1.to_vec()), "input byte {} tampered", i);
+    }
+
+    for i in output_bytes {
+        let mut claimed = OUTPUT_F1.to_vec();
+        claimed[i] ^= 1;
+
+        assert!(!run_table(input(12, 1), claimed), "output byte {} tampered", i);
+    }
+}
+
+#[test]
+fn test_precompile_table_lookup() {
+    assert!(run_table(input(12, 1), OUTPUT_F1.to_vec()));
+}
+
+#[test]
+fn test_precompile_table_tampered_input() {
+    assert_tampered_rejected(input_boundaries(), output_boundaries());
+}
+
+// every byte, one MockProver run each
+#[test]
+#[ignore]
+fn test_precompile_table_tampered_every_byte() {
+    assert_tampered_rejected(0..INPUT_BYTES, 0..OUTPUT_BYTES);
+}
+
+// the table id is copied from the call's id cell, a lookup of another call id fails
+#[test]
+fn test_precompile_table_wrong_id() {
+    assert!(run_table_call(1, input(12, 1), OUTPUT_F1.to_vec()));
+    assert!(!run_table_call(2, input(12, 1), OUTPUT_F1.to_vec()));
+}
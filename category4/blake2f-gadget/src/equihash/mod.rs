@@ -0,0 +1,589 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Cell, Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Instance, Selector},
+    poly::Rotation,
+};
+use halo2curves::{group::ff::PrimeField, FieldExt, bn256::{self, Fr}};
+
+use crate::blake2b::{initial_h_personal, BLOCK_BYTES, BLOCK_WORDS};
+use crate::{Blake2fChip, Blake2fConfig, Blake2fInstructions, Blake2fIv, Blake2fState, MAX_ROUND};
+
+#[cfg(test)]
+mod test;
+
+// block header without the solution, version .. nonce
+pub const HEADER_BYTES: usize = 140;
+// 17 words and the last 4 bytes, the public input
+pub const HEADER_WORDS: usize = (HEADER_BYTES + 7) / 8;
+
+// "ZcashPoW" || le32(n) || le32(k)
+pub fn personal(n: usize, k: usize) -> [u8; 16] {
+    let mut personal = [0; 16];
+    personal[..8].copy_from_slice(b"ZcashPoW");
+    personal[8..12].copy_from_slice(&(n as u32).to_le_bytes());
+    personal[12..].copy_from_slice(&(k as u32).to_le_bytes());
+    personal
+}
+
+pub struct Equihash<F: FieldExt, CS: EquihashInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, EquihashChip: EquihashInstructions<F>> Equihash<F, EquihashChip> {
+    pub fn new(chip: EquihashChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            _marker: PhantomData,
+        })
+    }
+
+    // `indices` in solution order, the header comes from the instance column
+    pub fn verify(&self, layouter: &mut impl Layouter<F>, indices: &[Value<u32>]) -> Result<(), Error> {
+        assert!(indices.len().is_power_of_two());
+
+        let header = self.chip.load_header(layouter, &self.iv)?;
+
+        let mut nodes = indices
+            .iter()
+            .map(|index| self.chip.leaf(layouter, &header, *index))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.chip.distinct(layouter, &nodes)?;
+
+        for level in 1..=indices.len().trailing_zeros() as usize {
+            nodes = nodes
+                .chunks(2)
+                .map(|pair| self.chip.merge(layouter, level, &pair[0], &pair[1]))
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        Ok(())
+    }
+}
+
+pub trait EquihashInstructions<F: FieldExt>: Chip<F> {
+    type Header: Clone + Debug;
+    type Node: Clone + Debug;
+    // the cells every compression copies, kept in the header
+    type Iv: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
+
+    // the header words from the instance column, hashed up to the last block
+    fn load_header(&self, layouter: &mut impl Layouter<F>, iv: &Self::Iv) -> Result<Self::Header, Error>;
+
+    // the n-bit hash output of one index
+    fn leaf(&self, layouter: &mut impl Layouter<F>, header: &Self::Header, index: Value<u32>) -> Result<Self::Node, Error>;
+
+    // all indices are distinct
+    fn distinct(&self, layouter: &mut impl Layouter<F>, leaves: &[Self::Node]) -> Result<(), Error>;
+
+    // collision on the `level`-th chunk, ordered first indices, the xor of the remaining bits
+    fn merge(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        level: usize,
+        left: &Self::Node,
+        right: &Self::Node,
+    ) -> Result<Self::Node, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct EquihashHeader {
+    iv: Blake2fIv,
+    // state after the first 128 header bytes
+    h: Blake2fState,
+    // header bytes 128..136
+    word: AssignedCell<Fr, Fr>,
+    // header bytes 136..140
+    tail: AssignedCell<Fr, Fr>,
+    zero: AssignedCell<Fr, Fr>,
+}
+
+// A subtree: its first index and the bits of the xor of its hash outputs that are not yet
+// known to be zero, from the most significant bit of the first byte.
+#[derive(Clone, Debug)]
+pub struct EquihashNode {
+    first: AssignedCell<Fr, Fr>,
+    bits: Vec<AssignedCell<Fr, Fr>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EquihashConfig<const N: usize, const K: usize> {
+    blake2f: Blake2fConfig,
+    bit: Column<Advice>,
+    acc: Column<Advice>,
+    triple: Column<Advice>,
+    index: Column<Advice>,
+    sorted: Column<Advice>,
+    instance: Column<Instance>,
+    s_decompose: Selector,
+    s_slot: Selector,
+    s_select: Selector,
+    s_index: Selector,
+    s_g_word: Selector,
+    s_xor: Selector,
+    s_less_than: Selector,
+    s_indices: Selector,
+    s_sorted: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct EquihashChip<const N: usize, const K: usize> {
+    config: EquihashConfig<N, K>,
+    blake2f: Blake2fChip,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl<const N: usize, const K: usize> Chip<bn256::Fr> for EquihashChip<N, K> {
+    type Config = EquihashConfig<N, K>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<const N: usize, const K: usize> EquihashChip<N, K> {
+    pub const COLLISION_BITS: usize = N / (K + 1);
+    pub const INDEX_BITS: usize = Self::COLLISION_BITS + 1;
+    pub const INDICES: usize = 1 << K;
+    // hash outputs per BLAKE2b call
+    pub const OUTPUTS: usize = 512 / N;
+    pub const DIGEST_BYTES: usize = Self::OUTPUTS * N / 8;
+
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        assert_eq!(N % 8, 0);
+        assert_eq!(N % (K + 1), 0);
+
+        Self {
+            blake2f: Blake2fChip::construct(config.blake2f.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let blake2f = Blake2fChip::configure(meta);
+
+        let bit = meta.advice_column();
+        let acc = meta.advice_column();
+        let triple = meta.advice_column();
+        let index = meta.advice_column();
+        let sorted = meta.advice_column();
+        let instance = meta.instance_column();
+
+        for column in [bit, acc, triple, index, sorted] {
+            meta.enable_equality(column);
+        }
+        meta.enable_equality(instance);
+
+        let s_decompose = meta.selector();
+        let s_slot = meta.selector();
+        let s_select = meta.selector();
+        let s_index = meta.selector();
+        let s_g_word = meta.selector();
+        let s_xor = meta.selector();
+        let s_less_than = meta.selector();
+        let s_indices = meta.complex_selector();
+        let s_sorted = meta.complex_selector();
+
+        let one = || Expression::Constant(Fr::one());
+        let constant = |c: u64| Expression::Constant(Fr::from(c));
+
+        // running sum from the most significant bit, row 0 holds acc = 0
+        meta.create_gate("equihash decompose", |meta| {
+            let s = meta.query_selector(s_decompose);
+
+            let bit = meta.query_advice(bit, Rotation::cur());
+            let acc_prev = meta.query_advice(acc, Rotation::prev());
+            let acc = meta.query_advice(acc, Rotation::cur());
+
+            Constraints::with_selector(s, vec![
+                bit.clone() * (one() - bit.clone()),
+                acc - acc_prev * constant(2) - bit,
+            ])
+        });
+
+        // acc rows 0..T: the slot one hot, row T: the slot
+        meta.create_gate("equihash slot", |meta| {
+            let s = meta.query_selector(s_slot);
+
+            let e: Vec<_> = (0..Self::OUTPUTS).map(|t| meta.query_advice(acc, Rotation(t as i32))).collect();
+            let slot = meta.query_advice(acc, Rotation(Self::OUTPUTS as i32));
+
+            let mut constraints: Vec<_> = e.iter().map(|e| e.clone() * (one() - e.clone())).collect();
+            constraints.push(e.iter().fold(-one(), |sum, e| sum + e.clone()));
+            constraints.push(e.iter().enumerate().fold(-slot, |sum, (t, e)| sum + e.clone() * constant(t as u64)));
+
+            Constraints::with_selector(s, constraints)
+        });
+
+        // bit rows 0..T: the bit of each hash output, acc rows 0..T: the slot one hot,
+        // bit row T: the bit of the selected output
+        meta.create_gate("equihash select", |meta| {
+            let s = meta.query_selector(s_select);
+
+            let selected = meta.query_advice(bit, Rotation(Self::OUTPUTS as i32));
+            let sum = (0..Self::OUTPUTS).fold(Expression::Constant(Fr::zero()), |sum, t| {
+                sum + meta.query_advice(bit, Rotation(t as i32)) * meta.query_advice(acc, Rotation(t as i32))
+            });
+
+            Constraints::with_selector(s, vec![selected - sum])
+        });
+
+        let query_triple = |meta: &mut halo2_proofs::plonk::VirtualCells<'_, bn256::Fr>| {
+            (
+                meta.query_advice(triple, Rotation::cur()),
+                meta.query_advice(triple, Rotation::next()),
+                meta.query_advice(triple, Rotation(2)),
+            )
+        };
+
+        // index = g * T + slot
+        meta.create_gate("equihash index", |meta| {
+            let s = meta.query_selector(s_index);
+            let (index, g, slot) = query_triple(meta);
+
+            Constraints::with_selector(s, vec![index - g * constant(Self::OUTPUTS as u64) - slot])
+        });
+
+        // the second word of the last block: header bytes 136..140 || le32(g)
+        meta.create_gate("equihash g word", |meta| {
+            let s = meta.query_selector(s_g_word);
+            let (tail, g, word) = query_triple(meta);
+
+            Constraints::with_selector(s, vec![word - tail - g * constant(1 << (8 * (HEADER_BYTES % 8)))])
+        });
+
+        meta.create_gate("equihash xor", |meta| {
+            let s = meta.query_selector(s_xor);
+            let (a, b, c) = query_triple(meta);
+
+            Constraints::with_selector(s, vec![c - a.clone() - b.clone() + a * b * constant(2)])
+        });
+
+        // a < b if diff is decomposed in INDEX_BITS bits
+        meta.create_gate("equihash less than", |meta| {
+            let s = meta.query_selector(s_less_than);
+            let (a, b, diff) = query_triple(meta);
+
+            Constraints::with_selector(s, vec![diff - b + a + one()])
+        });
+
+        // Same sets, and `sorted` is strictly increasing, so the indices are distinct. Shifted
+        // by one to keep the zero of unselected rows out of both tables.
+        meta.lookup_any("equihash index in sorted", |meta| {
+            let s_indices = meta.query_selector(s_indices);
+            let s_sorted = meta.query_selector(s_sorted);
+            let index = meta.query_advice(index, Rotation::cur());
+            let sorted = meta.query_advice(sorted, Rotation::cur());
+
+            vec![(s_indices * (index + one()), s_sorted * (sorted + one()))]
+        });
+
+        meta.lookup_any("equihash sorted in index", |meta| {
+            let s_indices = meta.query_selector(s_indices);
+            let s_sorted = meta.query_selector(s_sorted);
+            let index = meta.query_advice(index, Rotation::cur());
+            let sorted = meta.query_advice(sorted, Rotation::cur());
+
+            vec![(s_sorted * (sorted + one()), s_indices * (index + one()))]
+        });
+
+        EquihashConfig {
+            blake2f,
+            bit,
+            acc,
+            triple,
+            index,
+            sorted,
+            instance,
+            s_decompose,
+            s_slot,
+            s_select,
+            s_index,
+            s_g_word,
+            s_xor,
+            s_less_than,
+            s_indices,
+            s_sorted,
+        }
+    }
+
+    // bits from the most significant, `value` must be below 2^bits
+    fn decompose(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        value: Value<Fr>,
+        cell: Cell,
+        bits: usize,
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        let config = &self.config;
+
+        let values: Value<Vec<bool>> = value.map(|value| {
+            let repr = value.to_repr();
+            (0..bits).rev().map(|i| (repr[i / 8] >> (i % 8)) & 1 == 1).collect()
+        });
+
+        layouter.assign_region(|| "equihash decompose", |mut region| {
+            let mut acc = region.assign_advice_from_constant(|| "assign acc 0", config.acc, 0, Fr::zero())?;
+            let mut out = Vec::with_capacity(bits);
+
+            for i in 0..bits {
+                let row = i + 1;
+                config.s_decompose.enable(&mut region, row)?;
+
+                let bit = values.as_ref().map(|values| values[i]);
+                let next = acc.value().zip(bit).map(|(acc, bit)| *acc * Fr::from(2) + Fr::from(bit as u64));
+
+                out.push(region.assign_advice(|| "assign bit", config.bit, row, || bit.map(|bit| Fr::from(bit as u64)))?);
+                acc = region.assign_advice(|| "assign acc", config.acc, row, || next)?;
+            }
+
+            region.constrain_equal(acc.cell(), cell)?;
+
+            Ok(out)
+        })
+    }
+
+    fn less_than(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        a: &AssignedCell<Fr, Fr>,
+        b: &AssignedCell<Fr, Fr>,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+
+        let diff = layouter.assign_region(|| "equihash less than", |mut region| {
+            config.s_less_than.enable(&mut region, 0)?;
+
+            a.copy_advice(|| "copy a", &mut region, config.triple, 0)?;
+            b.copy_advice(|| "copy b", &mut region, config.triple, 1)?;
+
+            let diff = a.value().zip(b.value()).map(|(a, b)| *b - a - Fr::one());
+            region.assign_advice(|| "assign b - a - 1", config.triple, 2, || diff)
+        })?;
+
+        self.decompose(layouter, diff.value().copied(), diff.cell(), Self::INDEX_BITS)?;
+
+        Ok(())
+    }
+}
+
+impl<const N: usize, const K: usize> EquihashInstructions<bn256::Fr> for EquihashChip<N, K> {
+    type Header = EquihashHeader;
+    type Node = EquihashNode;
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        self.blake2f.initialize(layouter)
+    }
+
+    fn load_header(&self, layouter: &mut impl Layouter<bn256::Fr>, iv: &Blake2fIv) -> Result<Self::Header, Error> {
+        let config = &self.config;
+
+        let words = layouter.assign_region(|| "equihash header", |mut region| {
+            (0..HEADER_WORDS)
+                .map(|i| region.assign_advice_from_instance(|| "assign header word", config.instance, i, config.index, i))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        let blake2f = &self.blake2f;
+        let h = blake2f.initial_state(layouter, initial_h_personal(Self::DIGEST_BYTES, &personal(N, K)))?;
+
+        // the first block is the same for every index
+        let m = words[..BLOCK_WORDS].to_vec().try_into().unwrap();
+        let h = blake2f.compress_assigned(layouter, iv, &h, m, [BLOCK_BYTES as u64, 0], false, MAX_ROUND as u64)?;
+
+        Ok(EquihashHeader {
+            iv: iv.clone(),
+            h,
+            word: words[BLOCK_WORDS].clone(),
+            tail: words[BLOCK_WORDS + 1].clone(),
+            zero: blake2f.assign_constant(layouter, 0)?,
+        })
+    }
+
+    fn leaf(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        header: &Self::Header,
+        index: Value<u32>,
+    ) -> Result<Self::Node, Error> {
+        let config = &self.config;
+        let outputs = Self::OUTPUTS;
+
+        let (index, g, slot) = layouter.assign_region(|| "equihash index", |mut region| {
+            config.s_index.enable(&mut region, 0)?;
+
+            let index_cell = region.assign_advice(|| "assign index", config.triple, 0, || index.map(|i| Fr::from(i as u64)))?;
+            let g = region.assign_advice(|| "assign g", config.triple, 1, || index.map(|i| Fr::from((i as usize / outputs) as u64)))?;
+            let slot = region.assign_advice(|| "assign slot", config.triple, 2, || index.map(|i| Fr::from((i as usize % outputs) as u64)))?;
+
+            Ok((index_cell, g, slot))
+        })?;
+
+        // index < 2^INDEX_BITS, so is g and index = g * T + slot holds over the integers
+        self.decompose(layouter, index.value().copied(), index.cell(), Self::INDEX_BITS)?;
+        self.decompose(layouter, g.value().copied(), g.cell(), Self::INDEX_BITS)?;
+
+        let one_hot = layouter.assign_region(|| "equihash slot", |mut region| {
+            config.s_slot.enable(&mut region, 0)?;
+
+            let one_hot = (0..outputs)
+                .map(|t| {
+                    let e = slot.value().map(|slot| if *slot == Fr::from(t as u64) { Fr::one() } else { Fr::zero() });
+                    region.assign_advice(|| "assign one hot", config.acc, t, || e)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            slot.copy_advice(|| "copy slot", &mut region, config.acc, outputs)?;
+
+            Ok(one_hot)
+        })?;
+
+        let word = layouter.assign_region(|| "equihash g word", |mut region| {
+            config.s_g_word.enable(&mut region, 0)?;
+
+            let tail = header.tail.copy_advice(|| "copy tail", &mut region, config.triple, 0)?;
+            let g = g.copy_advice(|| "copy g", &mut region, config.triple, 1)?;
+
+            let shift = Fr::from(1 << (8 * (HEADER_BYTES % 8)));
+            let word = tail.value().zip(g.value()).map(|(tail, g)| *tail + *g * shift);
+            region.assign_advice(|| "assign g word", config.triple, 2, || word)
+        })?;
+
+        let mut m = vec![header.word.clone(), word];
+        m.resize(BLOCK_WORDS, header.zero.clone());
+
+        let t = [(HEADER_BYTES + 4) as u64, 0];
+        let digest = self.blake2f.compress_chained(layouter, &header.iv, &header.h, m.try_into().unwrap(), t, true, false, MAX_ROUND as u64)?;
+
+        // the digest as a bit string, every byte from its most significant bit
+        let mut stream = Vec::with_capacity(Self::DIGEST_BYTES * 8);
+        for (value, cell) in digest.value().into_iter().zip(digest.cells()).take((Self::DIGEST_BYTES + 7) / 8) {
+            let bits = self.decompose(layouter, value.map(Fr::from), cell, 64)?;
+
+            for byte in 0..8 {
+                for bit in (0..8).rev() {
+                    stream.push(bits[63 - (8 * byte + bit)].clone());
+                }
+            }
+        }
+
+        let bits = layouter.assign_region(|| "equihash select", |mut region| {
+            let mut bits = Vec::with_capacity(N);
+
+            for p in 0..N {
+                let row = p * (outputs + 1);
+                config.s_select.enable(&mut region, row)?;
+
+                let mut selected = Value::known(Fr::zero());
+                for (t, e) in one_hot.iter().enumerate() {
+                    let d = stream[t * N + p].copy_advice(|| "copy output bit", &mut region, config.bit, row + t)?;
+                    let e = e.copy_advice(|| "copy one hot", &mut region, config.acc, row + t)?;
+
+                    selected = selected.zip(d.value().zip(e.value())).map(|(sum, (d, e))| sum + *d * e);
+                }
+
+                bits.push(region.assign_advice(|| "assign selected bit", config.bit, row + outputs, || selected)?);
+            }
+
+            Ok(bits)
+        })?;
+
+        Ok(EquihashNode { first: index, bits })
+    }
+
+    fn distinct(&self, layouter: &mut impl Layouter<bn256::Fr>, leaves: &[Self::Node]) -> Result<(), Error> {
+        assert_eq!(leaves.len(), Self::INDICES);
+
+        let config = &self.config;
+
+        let sorted: Value<Vec<Fr>> = leaves
+            .iter()
+            .map(|leaf| leaf.first.value().copied())
+            .collect::<Value<Vec<_>>>()
+            .map(|mut indices| {
+                indices.sort_by_key(|index| index.get_lower_128());
+                indices
+            });
+
+        layouter.assign_region(|| "equihash indices", |mut region| {
+            for (i, leaf) in leaves.iter().enumerate() {
+                config.s_indices.enable(&mut region, i)?;
+                leaf.first.copy_advice(|| "copy index", &mut region, config.index, i)?;
+            }
+
+            Ok(())
+        })?;
+
+        let sorted = layouter.assign_region(|| "equihash sorted", |mut region| {
+            (0..leaves.len())
+                .map(|i| {
+                    config.s_sorted.enable(&mut region, i)?;
+                    region.assign_advice(|| "assign sorted", config.sorted, i, || sorted.as_ref().map(|sorted| sorted[i]))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        for pair in sorted.windows(2) {
+            self.less_than(layouter, &pair[0], &pair[1])?;
+        }
+
+        Ok(())
+    }
+
+    fn merge(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        level: usize,
+        left: &Self::Node,
+        right: &Self::Node,
+    ) -> Result<Self::Node, Error> {
+        assert!(level >= 1 && level <= K);
+        assert_eq!(left.bits.len(), N - (level - 1) * Self::COLLISION_BITS);
+
+        let config = &self.config;
+
+        // the last merge also zeroes the final chunk
+        let collision = if level == K { 2 * Self::COLLISION_BITS } else { Self::COLLISION_BITS };
+
+        self.less_than(layouter, &left.first, &right.first)?;
+
+        let bits = layouter.assign_region(|| "equihash merge", |mut region| {
+            for (a, b) in left.bits[..collision].iter().zip(right.bits[..collision].iter()) {
+                region.constrain_equal(a.cell(), b.cell())?;
+            }
+
+            let mut bits = Vec::with_capacity(left.bits.len() - collision);
+            for (i, (a, b)) in left.bits[collision..].iter().zip(right.bits[collision..].iter()).enumerate() {
+                let row = i * 3;
+                config.s_xor.enable(&mut region, row)?;
+
+                a.copy_advice(|| "copy a", &mut region, config.triple, row)?;
+                b.copy_advice(|| "copy b", &mut region, config.triple, row + 1)?;
+
+                let c = a.value().zip(b.value()).map(|(a, b)| *a + b - *a * b * Fr::from(2));
+                bits.push(region.assign_advice(|| "assign a ^ b", config.triple, row + 2, || c)?);
+            }
+
+            Ok(bits)
+        })?;
+
+        Ok(EquihashNode { first: left.first.clone(), bits })
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::bn256;
+
+use super::{personal, Equihash, EquihashChip, EquihashConfig, HEADER_BYTES};
+use crate::blake2b::initial_h_personal;
+use crate::native::{hash, words};
+
+const N: usize = 48;
+const K: usize = 5;
+
+type Chip = EquihashChip<N, K>;
+
+// 108 zero bytes and the nonce 1
+fn header() -> [u8; HEADER_BYTES] {
+    let mut header = [0; HEADER_BYTES];
+    header[108] = 1;
+    header
+}
+
+// solved with Wagner's algorithm for the header above
+const SOLUTION: [u32; 32] = [
+    7, 50, 61, 93, 20, 427, 108, 142, 107, 511, 229, 241, 174, 440, 230, 488, 44, 183, 127, 141, 164, 344, 225,
+    438, 45, 326, 287, 444, 231, 263, 341, 492,
+];
+
+fn leaf(header: &[u8], index: u32) -> Vec<bool> {
+    let g = index as usize / Chip::OUTPUTS;
+    let slot = index as usize % Chip::OUTPUTS;
+
+    let mut message = header.to_vec();
+    message.extend((g as u32).to_le_bytes());
+
+    let digest = hash(initial_h_personal(Chip::DIGEST_BYTES, &personal(N, K)), &message, Chip::DIGEST_BYTES);
+
+    digest[slot * N / 8..(slot + 1) * N / 8]
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+        .collect()
+}
+
+// native verifier, merges as in zcash's IsValidSolution
+fn verify(header: &[u8], indices: &[u32]) -> bool {
+    let cb = Chip::COLLISION_BITS;
+
+    if indices.len() != Chip::INDICES || indices.iter().collect::<HashSet<_>>().len() != indices.len() {
+        return false;
+    }
+    if indices.iter().any(|index| *index >= 1 << Chip::INDEX_BITS) {
+        return false;
+    }
+
+    let mut nodes: Vec<(Vec<bool>, u32)> = indices.iter().map(|index| (leaf(header, *index), *index)).collect();
+
+    for level in 1..=K {
+        let mut merged = Vec::with_capacity(nodes.len() / 2);
+
+        for pair in nodes.chunks(2) {
+            let ((a, first_a), (b, first_b)) = (&pair[0], &pair[1]);
+            let x: Vec<bool> = a.iter().zip(b.iter()).map(|(a, b)| a ^ b).collect();
+
+            if x[(level - 1) * cb..level * cb].iter().any(|bit| *bit) || first_a >= first_b {
+                return false;
+            }
+
+            merged.push((x, *first_a));
+        }
+
+        nodes = merged;
+    }
+
+    nodes[0].0.iter().all(|bit| !bit)
+}
+
+#[derive(Default)]
+struct MyCircuit {
+    indices: Vec<Value<u32>>,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = EquihashConfig<N, K>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            indices: vec![Value::unknown(); self.indices.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        Chip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Chip::construct(config);
+        let equihash = Equihash::new(chip, layouter.namespace(|| "load spread table"))?;
+
+        equihash.verify(&mut layouter, &self.indices)
+    }
+}
+
+fn run(header: &[u8], indices: &[u32]) -> bool {
+    let circuit = MyCircuit {
+        indices: indices.iter().map(|index| Value::known(*index)).collect(),
+    };
+
+    // the last 4 bytes are the last word
+    let instance = words(header).into_iter().map(bn256::Fr::from).collect();
+
+    let prover = match MockProver::<bn256::Fr>::run(18, &circuit, vec![instance]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+#[test]
+fn test_native_equihash() {
+    assert!(verify(&header(), &SOLUTION));
+
+    let mut swapped = SOLUTION;
+    swapped.swap(0, 1);
+    assert!(!verify(&header(), &swapped));
+
+    let mut other = header();
+    other[108] = 2;
+    assert!(!verify(&other, &SOLUTION));
+}
+
+#[test]
+fn test_equihash_48_5() {
+    assert!(run(&header(), &SOLUTION));
+}
+
+#[test]
+fn test_equihash_wrong_order() {
+    // the collisions still hold, the first indices are out of order
+    let mut swapped = SOLUTION;
+    swapped.swap(0, 1);
+
+    assert!(!run(&header(), &swapped));
+}
+
+#[test]
+fn test_equihash_wrong_header() {
+    let mut other = header();
+    other[108] = 2;
+
+    assert!(!run(&other, &SOLUTION));
+}
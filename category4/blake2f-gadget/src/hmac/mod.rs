@@ -0,0 +1,454 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Cell, Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use crate::{blake2b, blake2s, Blake2fChip, Blake2fInstructions, Blake2fIv, Blake2sChip};
+
+#[cfg(test)]
+mod test;
+
+// RFC 2104 §2
+pub const IPAD: u8 = 0x36;
+pub const OPAD: u8 = 0x5c;
+
+// HMAC and HKDF over message words fixed by the circuit length, see RFC 2104 and RFC 5869
+pub struct Hmac<F: FieldExt, CS: HmacInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, HmacChip: HmacInstructions<F>> Hmac<F, HmacChip> {
+    pub fn new(chip: HmacChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            _marker: PhantomData,
+        })
+    }
+
+    // H((K ^ opad) || H((K ^ ipad) || text)). `key` and `text` hold `key_len` and `text_len` bytes
+    // as little endian words of the hash, the bytes past the length in the last word must be zero.
+    // For the key, the circuit enforces it.
+    pub fn mac(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        key: &[AssignedCell<F, F>],
+        key_len: usize,
+        text: &[AssignedCell<F, F>],
+        text_len: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let (ipad, opad) = self.chip.pad_key(layouter, key, key_len)?;
+
+        let inner = [ipad, text.to_vec()].concat();
+        let inner = self.chip.hash(layouter, &self.iv, &inner, HmacChip::BLOCK_BYTES + text_len)?;
+
+        let outer = [opad, inner].concat();
+        self.chip.hash(layouter, &self.iv, &outer, HmacChip::BLOCK_BYTES + HmacChip::DIGEST_BYTES)
+    }
+
+    // PRK = HMAC(salt, IKM)
+    pub fn extract(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        salt: &[AssignedCell<F, F>],
+        salt_len: usize,
+        ikm: &[AssignedCell<F, F>],
+        ikm_len: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.mac(layouter, salt, salt_len, ikm, ikm_len)
+    }
+
+    // T(i) = HMAC(PRK, T(i - 1) || info || i), the first `len` bytes of T(1) || T(2) || ...
+    // `info` is fixed by the circuit and `len` must be a whole number of words.
+    pub fn expand(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        prk: &[AssignedCell<F, F>],
+        info: &[u8],
+        len: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        assert!(len % HmacChip::WORD_BYTES == 0 && len <= 255 * HmacChip::DIGEST_BYTES);
+
+        let mut okm = Vec::with_capacity(len / HmacChip::WORD_BYTES);
+        let mut t = vec![];
+
+        for i in 1..=(len + HmacChip::DIGEST_BYTES - 1) / HmacChip::DIGEST_BYTES {
+            // T(i - 1) is a whole number of words, the constant tail starts at a word boundary
+            let tail = [info, &[i as u8][..]].concat();
+            let text = [t.clone(), self.chip.assign_constant(layouter, &tail)?].concat();
+            let text_len = t.len() * HmacChip::WORD_BYTES + tail.len();
+
+            t = self.mac(layouter, prk, HmacChip::DIGEST_BYTES, &text, text_len)?;
+            okm.extend(t.iter().cloned());
+        }
+
+        okm.truncate(len / HmacChip::WORD_BYTES);
+        Ok(okm)
+    }
+}
+
+pub trait HmacInstructions<F: FieldExt>: Chip<F> {
+    const WORD_BYTES: usize;
+    const BLOCK_BYTES: usize;
+    const DIGEST_BYTES: usize;
+
+    // the cells every hash copies, the IV of the hash if it has one
+    type Iv: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
+
+    // witness words, e.g. a key or the input keying material
+    fn assign_words(&self, layouter: &mut impl Layouter<F>, words: &[Value<u64>]) -> Result<Vec<AssignedCell<F, F>>, Error>;
+
+    // constant bytes packed in little endian words, the last word padded with zeros
+    fn assign_constant(&self, layouter: &mut impl Layouter<F>, bytes: &[u8]) -> Result<Vec<AssignedCell<F, F>>, Error>;
+
+    // the blocks K ^ ipad and K ^ opad of a key of at most one block, the bytes of the last key
+    // word past `key_len` are constrained to zero
+    fn pad_key(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        key: &[AssignedCell<F, F>],
+        key_len: usize,
+    ) -> Result<(Vec<AssignedCell<F, F>>, Vec<AssignedCell<F, F>>), Error>;
+
+    // the unkeyed digest of `len` message bytes
+    fn hash(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        message: &[AssignedCell<F, F>],
+        len: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error>;
+}
+
+// A BLAKE2 chip HMAC can run over. HMAC takes the full digest of the hash, 32 bytes for
+// BLAKE2s as in WireGuard and 64 bytes for BLAKE2b.
+pub trait HmacHash: Chip<bn256::Fr> + Clone + Debug {
+    const WORD_BYTES: usize;
+    const BLOCK_BYTES: usize;
+    const DIGEST_BYTES: usize;
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config;
+
+    fn construct(config: Self::Config) -> Self;
+
+    // `()` for BLAKE2s, the IV cells for BLAKE2b
+    type Iv: Clone + Debug;
+
+    // loads the tables of the hash, and for BLAKE2b its IV
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Self::Iv, Error>;
+
+    // the digest words and the cells holding them
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Self::Iv,
+        message: &[AssignedCell<bn256::Fr, bn256::Fr>],
+        len: usize,
+    ) -> Result<Vec<(Value<u64>, Cell)>, Error>;
+}
+
+impl HmacHash for Blake2sChip {
+    const WORD_BYTES: usize = 4;
+    const BLOCK_BYTES: usize = blake2s::BLOCK_BYTES;
+    const DIGEST_BYTES: usize = 32;
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        Blake2sChip::configure(meta)
+    }
+
+    fn construct(config: Self::Config) -> Self {
+        Blake2sChip::construct(config)
+    }
+
+    type Iv = ();
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<(), Error> {
+        Blake2sChip::load(self.config(), layouter)
+    }
+
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        _iv: &(),
+        message: &[AssignedCell<bn256::Fr, bn256::Fr>],
+        len: usize,
+    ) -> Result<Vec<(Value<u64>, Cell)>, Error> {
+        let state = blake2s::hash_blocks(self, layouter, blake2s::initial_h(Self::DIGEST_BYTES), message, len)?;
+
+        Ok(state.value().into_iter().map(|word| word.map(|w| w as u64)).zip(state.cells()).collect())
+    }
+}
+
+impl HmacHash for Blake2fChip {
+    const WORD_BYTES: usize = 8;
+    const BLOCK_BYTES: usize = blake2b::BLOCK_BYTES;
+    const DIGEST_BYTES: usize = 64;
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        Blake2fChip::configure(meta)
+    }
+
+    fn construct(config: Self::Config) -> Self {
+        Blake2fChip::construct(config)
+    }
+
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        <Self as Blake2fInstructions<bn256::Fr>>::initialize(self, layouter)
+    }
+
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Blake2fIv,
+        message: &[AssignedCell<bn256::Fr, bn256::Fr>],
+        len: usize,
+    ) -> Result<Vec<(Value<u64>, Cell)>, Error> {
+        let state = blake2b::hash_blocks(self, layouter, iv, blake2b::initial_h(Self::DIGEST_BYTES), message, len)?;
+
+        Ok(state.value().into_iter().zip(state.cells()).collect())
+    }
+}
+
+pub type HmacBlake2sChip = HmacChip<Blake2sChip>;
+pub type HmacBlake2bChip = HmacChip<Blake2fChip>;
+
+#[derive(Clone, Debug)]
+pub struct HmacConfig<H: HmacHash> {
+    hash: H::Config,
+    word: Column<Advice>,
+    bit: Column<Advice>,
+    s_pad: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct HmacChip<H: HmacHash> {
+    config: HmacConfig<H>,
+    hash: H,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl<H: HmacHash> Chip<bn256::Fr> for HmacChip<H> {
+    type Config = HmacConfig<H>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+// the pad byte repeated over a word of the hash
+fn pad_word(pad: u8, word_bytes: usize) -> u64 {
+    (0..word_bytes).fold(0, |acc, i| acc | (pad as u64) << (8 * i))
+}
+
+impl<H: HmacHash> HmacChip<H> {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            hash: H::construct(config.hash.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let hash = H::configure(meta);
+
+        let word = meta.advice_column();
+        let bit = meta.advice_column();
+
+        meta.enable_equality(word);
+        // the key bits past the key length are fixed to zero
+        meta.enable_equality(bit);
+
+        let s_pad = meta.selector();
+        let word_bits = 8 * H::WORD_BYTES;
+
+        // row 0..word_bits: bits of k, low to high
+        // row 0: k
+        // row 1: k ^ ipad
+        // row 2: k ^ opad
+        //
+        // xor with a constant bit is either the bit or its negation, so both pads are linear
+        meta.create_gate("hmac pad", |meta| {
+            let s = meta.query_selector(s_pad);
+
+            let k = meta.query_advice(word, Rotation::cur());
+            let k_ipad = meta.query_advice(word, Rotation::next());
+            let k_opad = meta.query_advice(word, Rotation(2));
+
+            let one = Expression::Constant(Fr::one());
+            let ipad = pad_word(IPAD, H::WORD_BYTES);
+            let opad = pad_word(OPAD, H::WORD_BYTES);
+
+            let mut constraints = Vec::with_capacity(word_bits + 3);
+            let mut sum = Expression::Constant(Fr::zero());
+            let mut sum_ipad = Expression::Constant(Fr::zero());
+            let mut sum_opad = Expression::Constant(Fr::zero());
+
+            for i in 0..word_bits {
+                let b = meta.query_advice(bit, Rotation(i as i32));
+                let pow = Expression::Constant(Fr::from_u128(1 << i));
+                let xor = |pad: u64| if (pad >> i) & 1 == 1 { one.clone() - b.clone() } else { b.clone() };
+
+                constraints.push(b.clone() * (one.clone() - b.clone()));
+
+                sum = sum + b.clone() * pow.clone();
+                sum_ipad = sum_ipad + xor(ipad) * pow.clone();
+                sum_opad = sum_opad + xor(opad) * pow;
+            }
+
+            constraints.push(k - sum);
+            constraints.push(k_ipad - sum_ipad);
+            constraints.push(k_opad - sum_opad);
+
+            Constraints::with_selector(s, constraints)
+        });
+
+        HmacConfig { hash, word, bit, s_pad }
+    }
+}
+
+impl<H: HmacHash> HmacInstructions<bn256::Fr> for HmacChip<H> {
+    const WORD_BYTES: usize = H::WORD_BYTES;
+    const BLOCK_BYTES: usize = H::BLOCK_BYTES;
+    const DIGEST_BYTES: usize = H::DIGEST_BYTES;
+
+    type Iv = H::Iv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<H::Iv, Error> {
+        self.hash.initialize(layouter)
+    }
+
+    fn assign_words(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        words: &[Value<u64>],
+    ) -> Result<Vec<AssignedCell<bn256::Fr, bn256::Fr>>, Error> {
+        layouter.assign_region(|| "hmac words", |mut region| {
+            words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| region.assign_advice(|| format!("assign word {i}"), self.config.word, i, || word.map(Fr::from)))
+                .collect()
+        })
+    }
+
+    fn assign_constant(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        bytes: &[u8],
+    ) -> Result<Vec<AssignedCell<bn256::Fr, bn256::Fr>>, Error> {
+        layouter.assign_region(|| "hmac constant", |mut region| {
+            bytes
+                .chunks(H::WORD_BYTES)
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let word = chunk.iter().rev().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+                    region.assign_advice_from_constant(|| format!("assign constant {i}"), self.config.word, i, Fr::from(word))
+                })
+                .collect()
+        })
+    }
+
+    fn pad_key(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        key: &[AssignedCell<bn256::Fr, bn256::Fr>],
+        key_len: usize,
+    ) -> Result<(Vec<AssignedCell<bn256::Fr, bn256::Fr>>, Vec<AssignedCell<bn256::Fr, bn256::Fr>>), Error> {
+        // longer keys would be hashed first (RFC 2104 §2), not supported
+        assert!(key_len <= H::BLOCK_BYTES);
+        assert_eq!(key.len(), (key_len + H::WORD_BYTES - 1) / H::WORD_BYTES);
+
+        let word_bits = 8 * H::WORD_BYTES;
+        let block_words = H::BLOCK_BYTES / H::WORD_BYTES;
+        let ipad = pad_word(IPAD, H::WORD_BYTES);
+        let opad = pad_word(OPAD, H::WORD_BYTES);
+
+        // the key bits of the last word, all of them if it is full
+        let last_bits = match key_len % H::WORD_BYTES {
+            0 => word_bits,
+            bytes => 8 * bytes,
+        };
+
+        layouter.assign_region(|| "hmac pad key", |mut region| {
+            let word = self.config.word;
+            let mut k_ipad = Vec::with_capacity(block_words);
+            let mut k_opad = Vec::with_capacity(block_words);
+
+            for (i, k) in key.iter().enumerate() {
+                let row = i * word_bits;
+                let val = k.value().map(|v| v.get_lower_128() as u64);
+
+                self.config.s_pad.enable(&mut region, row)?;
+                k.copy_advice(|| "copy key word", &mut region, word, row)?;
+
+                for j in 0..word_bits {
+                    if i + 1 == key.len() && j >= last_bits {
+                        region.assign_advice_from_constant(|| "assign zero key bit", self.config.bit, row + j, Fr::zero())?;
+                    } else {
+                        region.assign_advice(|| format!("assign key bit {j}"), self.config.bit, row + j, || {
+                            val.map(|v| Fr::from((v >> j) & 1))
+                        })?;
+                    }
+                }
+
+                k_ipad.push(region.assign_advice(|| "assign k ^ ipad", word, row + 1, || val.map(|v| Fr::from(v ^ ipad)))?);
+                k_opad.push(region.assign_advice(|| "assign k ^ opad", word, row + 2, || val.map(|v| Fr::from(v ^ opad)))?);
+            }
+
+            // the zero padding of the key xored with the pads
+            let offset = key.len() * word_bits;
+            for i in key.len()..block_words {
+                let row = offset + 2 * (i - key.len());
+
+                k_ipad.push(region.assign_advice_from_constant(|| "assign ipad", word, row, Fr::from(ipad))?);
+                k_opad.push(region.assign_advice_from_constant(|| "assign opad", word, row + 1, Fr::from(opad))?);
+            }
+
+            Ok((k_ipad, k_opad))
+        })
+    }
+
+    fn hash(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &H::Iv,
+        message: &[AssignedCell<bn256::Fr, bn256::Fr>],
+        len: usize,
+    ) -> Result<Vec<AssignedCell<bn256::Fr, bn256::Fr>>, Error> {
+        let digest = self.hash.digest(layouter, iv, message, len)?;
+        let words = H::DIGEST_BYTES / H::WORD_BYTES;
+
+        // the digest is copied out of the hash region so it can feed the next message
+        layouter.assign_region(|| "hmac digest", |mut region| {
+            digest
+                .iter()
+                .take(words)
+                .enumerate()
+                .map(|(i, (val, cell))| {
+                    let copied = region.assign_advice(|| format!("copy digest {i}"), self.config.word, i, || val.map(Fr::from))?;
+                    region.constrain_equal(copied.cell(), *cell)?;
+                    Ok(copied)
+                })
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,206 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2curves::bn256;
+
+use super::{Hmac, HmacChip, HmacConfig, HmacHash, HmacInstructions, IPAD, OPAD};
+use crate::native::blake2b;
+use crate::blake2s::test::blake2s;
+use crate::{Blake2fChip, Blake2sChip};
+
+// native HMAC of RFC 2104 over a hash with `block` bytes per block
+fn hmac(hash: fn(&[u8]) -> Vec<u8>, block: usize, key: &[u8], text: &[u8]) -> Vec<u8> {
+    assert!(key.len() <= block);
+
+    let mut k = key.to_vec();
+    k.resize(block, 0);
+
+    let inner = hash(&[k.iter().map(|b| b ^ IPAD).collect(), text.to_vec()].concat());
+    hash(&[k.iter().map(|b| b ^ OPAD).collect(), inner].concat())
+}
+
+// native HKDF of RFC 5869
+fn hkdf(hash: fn(&[u8]) -> Vec<u8>, block: usize, salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    let prk = hmac(hash, block, salt, ikm);
+
+    let mut okm = vec![];
+    let mut t = vec![];
+    for i in 1u8.. {
+        if okm.len() >= len {
+            break;
+        }
+        t = hmac(hash, block, &prk, &[t, info.to_vec(), vec![i]].concat());
+        okm.extend(t.iter());
+    }
+
+    okm.truncate(len);
+    okm
+}
+
+fn blake2s_256(message: &[u8]) -> Vec<u8> {
+    blake2s(message, 32)
+}
+
+fn blake2b_512(message: &[u8]) -> Vec<u8> {
+    blake2b(message, 64)
+}
+
+// little endian words of the hash, the last one padded with zeros
+fn words<H: HmacHash>(bytes: &[u8]) -> Vec<u64> {
+    bytes
+        .chunks(H::WORD_BYTES)
+        .map(|chunk| chunk.iter().rev().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+        .collect()
+}
+
+struct MyCircuit<H: HmacHash> {
+    salt: Vec<u8>,
+    // the bytes of `salt` past it are in its last word, but not in the key
+    salt_len: usize,
+    ikm: Vec<u8>,
+    info: Vec<u8>,
+    len: usize,
+    _marker: PhantomData<H>,
+}
+
+impl<H: HmacHash> Circuit<bn256::Fr> for MyCircuit<H> {
+    type Config = (HmacConfig<H>, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // the lengths and the info are part of the circuit
+        Self {
+            salt: vec![0; self.salt.len()],
+            salt_len: self.salt_len,
+            ikm: vec![0; self.ikm.len()],
+            info: self.info.clone(),
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (HmacChip::<H>::configure(meta), instance)
+    }
+
+    fn synthesize(
+        &self,
+        (config, instance): Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = HmacChip::<H>::construct(config);
+        let hkdf = Hmac::new(chip.clone(), layouter.namespace(|| "load spread table"))?;
+
+        let salt = words::<H>(&self.salt).into_iter().map(Value::known).collect::<Vec<_>>();
+        let ikm = words::<H>(&self.ikm).into_iter().map(Value::known).collect::<Vec<_>>();
+
+        let salt = chip.assign_words(&mut layouter, &salt)?;
+        let ikm = chip.assign_words(&mut layouter, &ikm)?;
+
+        let prk = hkdf.extract(&mut layouter, &salt, self.salt_len, &ikm, self.ikm.len())?;
+        let okm = hkdf.expand(&mut layouter, &prk, &self.info, self.len)?;
+
+        for (i, word) in okm.iter().enumerate() {
+            layouter.constrain_instance(word.cell(), instance, i)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn run<H: HmacHash>(salt: &[u8], ikm: &[u8], info: &[u8], okm: &[u8]) -> bool {
+    run_with_salt_len::<H>(salt, salt.len(), ikm, info, okm)
+}
+
+fn run_with_salt_len<H: HmacHash>(salt: &[u8], salt_len: usize, ikm: &[u8], info: &[u8], okm: &[u8]) -> bool {
+    let circuit = MyCircuit::<H> {
+        salt: salt.to_vec(),
+        salt_len,
+        ikm: ikm.to_vec(),
+        info: info.to_vec(),
+        len: okm.len(),
+        _marker: PhantomData,
+    };
+
+    let instance = words::<H>(okm).into_iter().map(bn256::Fr::from).collect();
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![instance]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+// python3 -c "import hmac, hashlib; print(hmac.new(b'key', m, hashlib.blake2s).hexdigest())"
+#[test]
+fn test_native_hmac() {
+    let text = b"The quick brown fox jumps over the lazy dog";
+
+    assert_eq!(
+        hmac(blake2s_256, 64, b"key", text),
+        [
+            0xf9, 0x32, 0x15, 0xbb, 0x90, 0xd4, 0xaf, 0x4c, 0x30, 0x61, 0xcd, 0x93, 0x2f, 0xb1, 0x69, 0xfb,
+            0x8b, 0xb8, 0xa9, 0x1d, 0x0b, 0x40, 0x22, 0xba, 0xea, 0x12, 0x71, 0xe1, 0x32, 0x3c, 0xd9, 0xa0,
+        ]
+    );
+    assert_eq!(
+        hmac(blake2b_512, 128, b"key", text),
+        [
+            0x92, 0x29, 0x4f, 0x92, 0xc0, 0xdf, 0xb9, 0xb0, 0x0e, 0xc9, 0xae, 0x8b, 0xd9, 0x4d, 0x7e, 0x7d,
+            0x8a, 0x03, 0x6b, 0x88, 0x5a, 0x49, 0x9f, 0x14, 0x9d, 0xfe, 0x2f, 0xd2, 0x19, 0x93, 0x94, 0xaa,
+            0xaf, 0x6b, 0x88, 0x94, 0xa1, 0x73, 0x0c, 0xcc, 0xb2, 0xcd, 0x05, 0x0f, 0x9b, 0xcf, 0x50, 0x62,
+            0xa3, 0x8b, 0x51, 0xb0, 0xda, 0xb3, 0x32, 0x07, 0xf8, 0xef, 0x35, 0xae, 0x2c, 0x9d, 0xf5, 0x1b,
+        ]
+    );
+}
+
+// WireGuard's KDF2: the chaining key salts a DH output, two 32-byte outputs with empty info
+#[test]
+fn test_hkdf_blake2s() {
+    let ck: Vec<u8> = (0..32).collect();
+    let dh: Vec<u8> = (0..32).map(|i| 0xa0 ^ i).collect();
+    let okm = hkdf(blake2s_256, 64, &ck, &dh, &[], 64);
+
+    assert!(run::<Blake2sChip>(&ck, &dh, &[], &okm));
+}
+
+// a salt and an IKM ending mid word, one output block
+#[test]
+fn test_hkdf_blake2b() {
+    let salt: Vec<u8> = (0..13).collect();
+    let ikm = [0x0b; 22];
+    let info: Vec<u8> = (0xf0..=0xf9).collect();
+    let okm = hkdf(blake2b_512, 128, &salt, &ikm, &info, 48);
+
+    assert!(run::<Blake2fChip>(&salt, &ikm, &info, &okm));
+}
+
+#[test]
+fn test_hkdf_wrong_okm() {
+    let salt: Vec<u8> = (0..13).collect();
+    let ikm = [0x0b; 22];
+    let mut okm = hkdf(blake2b_512, 128, &salt, &ikm, &[], 48);
+    okm[0] ^= 1;
+
+    assert!(!run::<Blake2fChip>(&salt, &ikm, &[], &okm));
+}
+
+// a 12-byte key with a nonzero byte 12 in its last word, hashed as if it were part of the key
+#[test]
+fn test_hkdf_key_past_len() {
+    let salt: Vec<u8> = (1..=13).collect();
+    let ikm = [0x0b; 22];
+
+    let okm = hkdf(blake2b_512, 128, &salt[..12], &ikm, &[], 48);
+    assert!(run_with_salt_len::<Blake2fChip>(&salt[..12], 12, &ikm, &[], &okm));
+
+    let okm = hkdf(blake2b_512, 128, &salt, &ikm, &[], 48);
+    assert!(!run_with_salt_len::<Blake2fChip>(&salt, 12, &ikm, &[], &okm));
+}
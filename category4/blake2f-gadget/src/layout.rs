@@ -0,0 +1,321 @@
+// The rows a circuit actually uses, recorded while its floor planner lays it out.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use halo2_proofs::{
+    circuit::{FloorPlanner, Value},
+    plonk::{
+        Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error, Expression, Fixed,
+        Gate, Instance, Selector,
+    },
+};
+use halo2curves::bn256::Fr;
+
+/// The rows of one region, as the floor planner placed it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionRows {
+    pub name: String,
+    pub start: usize,
+    pub rows: usize,
+}
+
+/// The regions of a synthesized circuit, in assignment order.
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    pub regions: Vec<RegionRows>,
+    /// every row assigned, in a region or a lookup table
+    pub rows: usize,
+    /// the rows each lookup reads an assigned cell at, summed over the lookups. A row whose
+    /// input is multiplied by a zero cell reads none.
+    pub lookups: usize,
+}
+
+impl Layout {
+    /// The regions named `name`.
+    pub fn regions<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a RegionRows> + 'a {
+        self.regions.iter().filter(move |region| region.name == name)
+    }
+
+    /// The smallest `k` the layout fits in, with `reserved` rows left for the blinding factors.
+    pub fn min_k(&self, reserved: usize) -> u32 {
+        (self.rows + reserved).next_power_of_two().trailing_zeros()
+    }
+}
+
+/// An advice cell as the circuit assigned it.
+#[derive(Clone, Debug)]
+pub struct RecordedAdvice {
+    pub value: Fr,
+    pub annotation: String,
+    /// the index of its region in `Recorder::regions`
+    pub region: Option<usize>,
+}
+
+/// Everything the floor planner assigns, in absolute rows. Advice and fixed cells are keyed by
+/// (column index, row).
+#[derive(Debug)]
+pub struct Recorder {
+    region: Option<usize>,
+    /// the rows of the circuit, a rotation wraps around them
+    n: usize,
+    /// the name, the first and past the last row of each region
+    pub regions: Vec<(String, usize, usize)>,
+    /// past the last row assigned
+    pub rows: usize,
+    pub advice: BTreeMap<(usize, usize), RecordedAdvice>,
+    pub fixed: HashMap<(usize, usize), Fr>,
+    /// the default value of a table column from a row on, up to the end of the column
+    pub filled: HashMap<usize, (usize, Fr)>,
+    pub selectors: HashSet<(Selector, usize)>,
+    pub copies: Vec<((Column<Any>, usize), (Column<Any>, usize))>,
+}
+
+fn evaluate<VR: Into<Assigned<Fr>>>(value: Value<VR>) -> Fr {
+    let mut evaluated = Fr::zero();
+    value.map(|value| evaluated = Into::<Assigned<Fr>>::into(value).evaluate());
+    evaluated
+}
+
+/// The move of a cell in `Recorder::sensitive`, a value no assigned cell is expected to be a root
+/// of a constraint at.
+pub const DELTA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl Recorder {
+    fn new(n: usize) -> Self {
+        Self {
+            region: None,
+            n,
+            regions: vec![],
+            rows: 0,
+            advice: BTreeMap::new(),
+            fixed: HashMap::new(),
+            filled: HashMap::new(),
+            selectors: HashSet::new(),
+            copies: vec![],
+        }
+    }
+
+    fn touch(&mut self, row: usize) {
+        self.rows = self.rows.max(row + 1);
+
+        if let Some(region) = self.region {
+            let (_, start, end) = &mut self.regions[region];
+            *start = (*start).min(row);
+            *end = (*end).max(row + 1);
+        }
+    }
+
+    fn at(&self, row: usize, rotation: i32) -> usize {
+        (row as i64 + rotation as i64).rem_euclid(self.n as i64) as usize
+    }
+
+    /// The advice value at (column, row), zero if it is not assigned.
+    pub fn value(&self, cell: (usize, usize)) -> Fr {
+        self.advice.get(&cell).map_or(Fr::zero(), |advice| advice.value)
+    }
+
+    /// `expression` at `row`, with each advice cell of `moved` off its value by its delta.
+    pub fn evaluate(&self, expression: &Expression<Fr>, row: usize, moved: &[((usize, usize), Fr)]) -> Fr {
+        expression.evaluate(
+            &|constant| constant,
+            &|selector| if self.selectors.contains(&(selector, row)) { Fr::one() } else { Fr::zero() },
+            &|query| {
+                let (column, row) = (query.column_index(), self.at(row, query.rotation().0));
+                match (self.fixed.get(&(column, row)), self.filled.get(&column)) {
+                    (Some(value), _) => *value,
+                    (None, Some((from, value))) if row >= *from => *value,
+                    _ => Fr::zero(),
+                }
+            },
+            &|query| {
+                let cell = (query.column_index(), self.at(row, query.rotation().0));
+                moved
+                    .iter()
+                    .filter(|(moved, _)| *moved == cell)
+                    .fold(self.value(cell), |value, (_, delta)| value + delta)
+            },
+            &|_| Fr::zero(),
+            &|_| Fr::zero(),
+            &|a| -a,
+            &|a, b| a + b,
+            &|a, b| a * b,
+            &|a, scalar| a * scalar,
+        )
+    }
+
+    /// The advice cells `expression` queries at `row`.
+    pub fn queried(&self, expression: &Expression<Fr>, row: usize) -> Vec<(usize, usize)> {
+        expression.evaluate(
+            &|_| vec![],
+            &|_| vec![],
+            &|_| vec![],
+            &|query| vec![(query.column_index(), self.at(row, query.rotation().0))],
+            &|_| vec![],
+            &|_| vec![],
+            &|a| a,
+            &|a, b| [a, b].concat(),
+            &|a, b| [a, b].concat(),
+            &|a, _| a,
+        )
+    }
+
+    /// The queried advice cells the expressions change with at `row`, at the assigned values.
+    pub fn sensitive(&self, expressions: &[Expression<Fr>], row: usize) -> Vec<(usize, usize)> {
+        let mut cells = expressions
+            .iter()
+            .flat_map(|expression| {
+                let value = self.evaluate(expression, row, &[]);
+                self.queried(expression, row)
+                    .into_iter()
+                    .filter(move |cell| self.evaluate(expression, row, &[(*cell, Fr::from(DELTA))]) != value)
+            })
+            .collect::<Vec<_>>();
+        cells.sort();
+        cells.dedup();
+        cells
+    }
+
+    /// The rows a selector of `gate` is enabled at, every assigned row if it has none.
+    pub fn gate_rows(&self, gate: &Gate<Fr>) -> Vec<usize> {
+        let selectors = gate.polynomials().iter().flat_map(selectors).collect::<HashSet<_>>();
+
+        (0..self.rows)
+            .filter(|&row| selectors.is_empty() || selectors.iter().any(|selector| self.selectors.contains(&(*selector, row))))
+            .collect()
+    }
+}
+
+/// The selectors `expression` queries.
+pub fn selectors(expression: &Expression<Fr>) -> Vec<Selector> {
+    expression.evaluate(
+        &|_| vec![],
+        &|selector| vec![selector],
+        &|_| vec![],
+        &|_| vec![],
+        &|_| vec![],
+        &|_| vec![],
+        &|a| a,
+        &|a, b| [a, b].concat(),
+        &|a, b| [a, b].concat(),
+        &|a, _| a,
+    )
+}
+
+impl Assignment<Fr> for Recorder {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.region = Some(self.regions.len());
+        self.regions.push((name_fn().into(), usize::MAX, 0));
+    }
+
+    fn annotate_column<A, AR>(&mut self, _: A, _: Column<Any>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+    }
+
+    fn exit_region(&mut self) {
+        self.region = None;
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        self.selectors.insert((*selector, row));
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<Fr>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(&mut self, annotation: A, column: Column<Advice>, row: usize, to: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<Fr>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        let advice = RecordedAdvice { value: evaluate(to()), annotation: annotation().into(), region: self.region };
+        self.advice.insert((column.index(), row), advice);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(&mut self, _: A, column: Column<Fixed>, row: usize, to: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<Fr>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        self.fixed.insert((column.index(), row), evaluate(to()));
+        Ok(())
+    }
+
+    fn copy(&mut self, left_column: Column<Any>, left_row: usize, right_column: Column<Any>, right_row: usize) -> Result<(), Error> {
+        self.copies.push(((left_column, left_row), (right_column, right_row)));
+        Ok(())
+    }
+
+    // takes no rows of its own, the table is as long as the circuit
+    fn fill_from_row(&mut self, column: Column<Fixed>, row: usize, to: Value<Assigned<Fr>>) -> Result<(), Error> {
+        self.filled.insert(column.index(), (row, evaluate(to)));
+        Ok(())
+    }
+
+    fn get_challenge(&self, _: Challenge) -> Value<Fr> {
+        Value::unknown()
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+/// Lays `circuit` out with its floor planner for a circuit of 2^`k` rows, and returns its
+/// constraint system and everything it assigned.
+pub fn record<C: Circuit<Fr>>(circuit: &C, k: u32) -> Result<(ConstraintSystem<Fr>, Recorder), Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+
+    let mut recorder = Recorder::new(1 << k);
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, cs.constants().clone())?;
+
+    Ok((cs, recorder))
+}
+
+/// Lays `circuit` out with its floor planner and records the rows of every region and the rows
+/// its lookups read.
+pub fn measure<C: Circuit<Fr>>(circuit: &C) -> Result<Layout, Error> {
+    // no k is chosen yet, a rotation before row 0 reads past the layout
+    let (cs, recorder) = record(circuit, 32)?;
+
+    let lookups = cs
+        .lookups()
+        .iter()
+        .map(|lookup| (0..recorder.rows).filter(|&row| !recorder.sensitive(lookup.input_expressions(), row).is_empty()).count())
+        .sum();
+
+    let regions = recorder
+        .regions
+        .into_iter()
+        // a region that assigns nothing takes no rows
+        .map(|(name, start, end)| RegionRows { name, start: start.min(end), rows: end.saturating_sub(start) })
+        .collect();
+
+    Ok(Layout { regions, rows: recorder.rows, lookups })
+}
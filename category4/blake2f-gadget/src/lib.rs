@@ -1,36 +1,73 @@
 use std::{fmt::Debug, marker::PhantomData};
 
 use halo2_proofs::{
-    circuit::{Chip, Layouter, AssignedCell, Value},
+    circuit::{Cell, Chip, Layouter, AssignedCell, Value},
     plonk::Error,
 };
 use halo2curves::{FieldExt, bn256::Fr};
 
 mod spread_table;
+mod byte_table;
 mod bits;
 mod util;
 mod chip;
 mod compression;
 mod scheduler;
 mod state;
+mod words;
+mod sha256;
+mod sha512;
+mod argon2;
+mod blake2b;
+mod blake2b_var;
+mod blake2s;
+mod blake2tree;
+mod hmac;
+mod merkle;
+mod transcript;
+mod substrate;
+mod equihash;
+mod eip152;
+mod trace;
+pub mod native;
+pub mod layout;
+#[cfg(test)]
+mod differential;
 
 pub use chip::{Blake2fChip, Blake2fConfig};
+pub use scheduler::assignment::Blake2fIv;
+pub use trace::{Blake2fTrace, Blake2fTraceRow};
+pub use blake2b::{initial_h as blake2b_initial_h, Blake2b, ParamBlock};
+pub use blake2b_var::{Blake2bVar, Blake2bVarChip, Blake2bVarConfig, Blake2bVarInstructions, VarMessage};
+pub use blake2tree::{Blake2TreeChip, Blake2TreeConfig, Blake2TreeInstructions, Blake2bp, Blake2xb};
+pub use blake2s::{initial_h as blake2s_initial_h, Blake2s, Blake2sChip, Blake2sConfig, Blake2sInstructions, Blake2sState};
+pub use hmac::{Hmac, HmacBlake2bChip, HmacBlake2sChip, HmacChip, HmacConfig, HmacHash, HmacInstructions};
+pub use transcript::{Blake2bTranscript, TranscriptChip, TranscriptConfig, TranscriptInstructions, TranscriptState, REPR_BYTES};
+pub use substrate::{SubstrateChip, SubstrateConfig, SubstrateHasher, SubstrateInstructions, BLAKE2_128_BYTES, BLAKE2_256_BYTES};
+pub use merkle::{MerkleChip, MerkleConfig, MerkleDigest, MerkleInstructions, MerklePath};
+pub use eip152::{Eip152, Eip152Chip, Eip152Config, Eip152Input, Eip152Instructions, Eip152Table, Eip152TableChip, Eip152TableConfig, Eip152TableInstructions, PrecompileTable, INPUT_BYTES as EIP152_INPUT_BYTES, OUTPUT_BYTES as EIP152_OUTPUT_BYTES};
+pub use equihash::{personal as equihash_personal, Equihash, EquihashChip, EquihashConfig, EquihashHeader, EquihashInstructions, EquihashNode, HEADER_BYTES as EQUIHASH_HEADER_BYTES};
+pub use argon2::{compress_block as argon2_compress_block, fblamka, Argon2, Argon2Block, Argon2Chip, Argon2Config, Argon2Instructions};
+pub use sha256::{padding as sha256_padding, Sha256, Sha256Chip, Sha256Config, Sha256Instructions, Sha256State};
+pub use sha512::{padding as sha512_padding, Sha512, Sha512Chip, Sha512Config, Sha512Instructions, Sha512State, IV_512, IV_512_256};
 
-use state::InnerState;
+use state::{InnerState, RoundWord};
 
 
-pub(crate) const MAX_ROUND: usize = 12;
+pub const MAX_ROUND: usize = 12;
 
 pub struct Blake2f<F: FieldExt, CS: Blake2fInstructions<F>> {
     chip: CS,
+    iv: CS::Iv,
     _marker: PhantomData<F>,
 }
 
 impl<F: FieldExt, Blake2fChip: Blake2fInstructions<F>> Blake2f<F, Blake2fChip> {
     pub fn new(chip: Blake2fChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
-        chip.initialize(&mut layouter)?;
+        let iv = chip.initialize(&mut layouter)?;
         Ok(Self {
             chip,
+            iv,
             _marker: PhantomData,
         })
     }
@@ -45,7 +82,82 @@ impl<F: FieldExt, Blake2fChip: Blake2fInstructions<F>> Blake2f<F, Blake2fChip> {
         flag: Blake2fChip::CSU64,
         rounds: Blake2fChip::CSU64,
     ) -> Result<[Blake2fChip::CSU64; 8], Error>{
-        self.chip.compress(layouter, h, m, c0, c1, flag, rounds)
+        self.chip.compress(layouter, &self.iv, h, m, c0, c1, flag, rounds)
+    }
+
+    pub fn initial_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        h: [u64; 8],
+    ) -> Result<Blake2fChip::State, Error> {
+        self.chip.initial_state(layouter, h)
+    }
+
+    pub fn compress_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        h: &Blake2fChip::State,
+        m: [AssignedCell<F, F>; 16],
+        t: [u64; 2],
+        f: bool,
+        rounds: u64,
+    ) -> Result<Blake2fChip::State, Error> {
+        self.chip.compress_assigned(layouter, &self.iv, h, m, t, f, rounds)
+    }
+
+    pub fn compress_cells(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        h: [AssignedCell<F, F>; 8],
+        m: [AssignedCell<F, F>; 16],
+        t: [AssignedCell<F, F>; 2],
+        f: AssignedCell<F, F>,
+        rounds: AssignedCell<F, F>,
+    ) -> Result<Blake2fChip::State, Error> {
+        self.chip.compress_cells(layouter, &self.iv, h, m, t, f, rounds)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn compress_node(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        h: &Blake2fChip::State,
+        m: [AssignedCell<F, F>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+    ) -> Result<Blake2fChip::State, Error> {
+        self.chip.compress_node(layouter, &self.iv, h, m, t, f, last_node, rounds)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn compress_chained(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        h: &Blake2fChip::State,
+        m: [AssignedCell<F, F>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+    ) -> Result<Blake2fChip::State, Error> {
+        self.chip.compress_chained(layouter, &self.iv, h, m, t, f, last_node, rounds)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn compress_with_trace(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        h: &Blake2fChip::State,
+        m: [AssignedCell<F, F>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+        trace: Value<Blake2fTrace>,
+    ) -> Result<Blake2fChip::State, Error> {
+        self.chip.compress_with_trace(layouter, &self.iv, h, m, t, f, last_node, rounds, trace)
     }
 }
 
@@ -54,6 +166,24 @@ pub struct InitializedState {
     pub state: InnerState,
     pub m: [AssignedCell<Fr, Fr>; 16],
     pub round: AssignedCell<Fr, Fr>,
+    pub t: [RoundWord; 2],
+    pub f: RoundWord,
+    // rows taken by the scheduler at the top of the compress region
+    pub rows: usize,
+}
+
+// h as assigned words, what `compress_assigned` consumes and returns
+#[derive(Debug, Clone)]
+pub struct Blake2fState(pub [RoundWord; 8]);
+
+impl Blake2fState {
+    pub fn cells(&self) -> [Cell; 8] {
+        self.0.clone().map(|word| word.val.cell())
+    }
+
+    pub fn value(&self) -> [Value<u64>; 8] {
+        self.0.clone().map(|word| word.val.value_u64())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -64,11 +194,19 @@ pub struct Blake2fDigest {
 pub trait Blake2fInstructions<F: FieldExt>: Chip<F> {
     type CSU64: Clone + Debug + Default;
 
-    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error>;
+    // the cells every compression copies, e.g. the IV
+    type Iv: Clone + Debug;
+
+    // loads the tables and assigns the cells every compression of the circuit copies, once
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
 
+    // At most MAX_ROUND rounds are laid out: every compression fails with `Error::Synthesis` when
+    // its rounds are known to be more, though the precompile accepts up to 2^32 - 1.
+    #[allow(clippy::too_many_arguments)]
     fn compress(
         &self,
         layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
         h: [Self::CSU64; 8],
         m: [Self::CSU64; 16],
         c0: Self::CSU64,
@@ -76,5 +214,83 @@ pub trait Blake2fInstructions<F: FieldExt>: Chip<F> {
         flag: Self::CSU64,
         rounds: Self::CSU64,
     ) -> Result<[Self::CSU64; 8], Error>;
+
+    type State: Clone + Debug;
+
+    // h fixed by the circuit, e.g. the IV xored with a parameter block
+    fn initial_state(&self, layouter: &mut impl Layouter<F>, h: [u64; 8]) -> Result<Self::State, Error>;
+
+    fn assign_constant(&self, layouter: &mut impl Layouter<F>, word: u64) -> Result<AssignedCell<F, F>, Error>;
+
+    // `t`, `f` and `rounds` are constants of the circuit
+    #[allow(clippy::too_many_arguments)]
+    fn compress_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        h: &Self::State,
+        m: [AssignedCell<F, F>; 16],
+        t: [u64; 2],
+        f: bool,
+        rounds: u64,
+    ) -> Result<Self::State, Error>;
+
+    // every input copied from cells, `f` being the flag word 0 or 2^64 - 1
+    #[allow(clippy::too_many_arguments)]
+    fn compress_cells(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        h: [AssignedCell<F, F>; 8],
+        m: [AssignedCell<F, F>; 16],
+        t: [AssignedCell<F, F>; 2],
+        f: AssignedCell<F, F>,
+        rounds: AssignedCell<F, F>,
+    ) -> Result<Self::State, Error>;
+
+    // `compress_assigned` with the last node flag of a tree hash, f1 of RFC 7693 §3.2
+    #[allow(clippy::too_many_arguments)]
+    fn compress_node(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        h: &Self::State,
+        m: [AssignedCell<F, F>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+    ) -> Result<Self::State, Error>;
+
+    // `compress_node` for a state returned by a previous compression: its words and their limbs
+    // are copied into the next one instead of being assigned and decomposed again
+    #[allow(clippy::too_many_arguments)]
+    fn compress_chained(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        h: &Self::State,
+        m: [AssignedCell<F, F>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+    ) -> Result<Self::State, Error>;
+
+    // `compress_chained` copying `trace` into the region instead of computing it, e.g. traces of
+    // many compressions computed in parallel beforehand. `trace` is the trace of the same inputs.
+    #[allow(clippy::too_many_arguments)]
+    fn compress_with_trace(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        h: &Self::State,
+        m: [AssignedCell<F, F>; 16],
+        t: [u64; 2],
+        f: bool,
+        last_node: bool,
+        rounds: u64,
+        trace: Value<Blake2fTrace>,
+    ) -> Result<Self::State, Error>;
 }
 
@@ -0,0 +1,324 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Cell, Chip, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Instance, Selector},
+    poly::Rotation,
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use crate::blake2b::{initial_h, BLOCK_WORDS};
+use crate::byte_table::{ByteTableChip, ByteTableConfig};
+use crate::transcript::{compose_gate, ROWS_PER_COMPOSE};
+use crate::{Blake2fChip, Blake2fConfig, Blake2fInstructions, Blake2fIv, MAX_ROUND};
+
+#[cfg(test)]
+mod test;
+
+// BLAKE2b-256 digest as 4 little endian u64
+pub const DIGEST_WORDS: usize = 4;
+
+// one swap per digest word
+const ROWS_PER_SWAP: usize = 5;
+
+pub struct MerklePath<F: FieldExt, CS: MerkleInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, MerkleChip: MerkleInstructions<F>> MerklePath<F, MerkleChip> {
+    pub fn new(chip: MerkleChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            _marker: PhantomData,
+        })
+    }
+
+    // The depth is `path.len()`, fixed by the circuit. A direction bit is set when the current
+    // node is the right child.
+    pub fn root(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        leaf: [Value<u64>; DIGEST_WORDS],
+        path: &[([Value<u64>; DIGEST_WORDS], Value<bool>)],
+    ) -> Result<MerkleChip::Digest, Error> {
+        let mut node = self.chip.assign_leaf(layouter, leaf)?;
+
+        for (sibling, is_right) in path.iter() {
+            node = self.chip.hash_node(layouter, &self.iv, &node, *sibling, *is_right)?;
+        }
+
+        Ok(node)
+    }
+
+    pub fn expose_root(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        root: &MerkleChip::Digest,
+    ) -> Result<(), Error> {
+        self.chip.expose_root(layouter, root)
+    }
+}
+
+pub trait MerkleInstructions<F: FieldExt>: Chip<F> {
+    type Digest: Clone + Debug;
+    // the cells every compression copies
+    type Iv: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
+
+    fn assign_leaf(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        leaf: [Value<u64>; DIGEST_WORDS],
+    ) -> Result<Self::Digest, Error>;
+
+    // BLAKE2b-256(left || right), one compression
+    fn hash_node(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        node: &Self::Digest,
+        sibling: [Value<u64>; DIGEST_WORDS],
+        is_right: Value<bool>,
+    ) -> Result<Self::Digest, Error>;
+
+    // the root words go to instance rows 0..4
+    fn expose_root(&self, layouter: &mut impl Layouter<F>, root: &Self::Digest) -> Result<(), Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleDigest {
+    pub words: [Value<u64>; DIGEST_WORDS],
+    pub cells: [Cell; DIGEST_WORDS],
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleConfig {
+    blake2f: Blake2fConfig,
+    path: Column<Advice>,
+    instance: Column<Instance>,
+    byte_table: ByteTableConfig,
+    s_swap: Selector,
+    s_word: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleChip {
+    config: MerkleConfig,
+    blake2f: Blake2fChip,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for MerkleChip {
+    type Config = MerkleConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl MerkleChip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            blake2f: Blake2fChip::construct(config.blake2f.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let blake2f = Blake2fChip::configure(meta);
+
+        let path = meta.advice_column();
+        let instance = meta.instance_column();
+
+        meta.enable_equality(path);
+        meta.enable_equality(instance);
+
+        // the leaf and sibling words are composed from range checked bytes, the scheduler does
+        // not decompose `m` and the `a += b + m` carry would let `m + 2^64` alias `m`
+        let byte_table = ByteTableChip::configure(meta, path);
+        let s_word = meta.selector();
+        compose_gate(meta, "compose word", s_word, path, Fr::from(1 << 8));
+
+        let s_swap = meta.selector();
+
+        // row 0: is_right
+        // row 1: cur
+        // row 2: sibling
+        // row 3: left = is_right ? sibling : cur
+        // row 4: right = is_right ? cur : sibling
+        meta.create_gate("merkle swap", |meta| {
+            let s = meta.query_selector(s_swap);
+
+            let is_right = meta.query_advice(path, Rotation::cur());
+            let cur = meta.query_advice(path, Rotation::next());
+            let sibling = meta.query_advice(path, Rotation(2));
+            let left = meta.query_advice(path, Rotation(3));
+            let right = meta.query_advice(path, Rotation(4));
+
+            let one = Expression::Constant(Fr::one());
+
+            Constraints::with_selector(s, vec![
+                is_right.clone() * (one - is_right.clone()),
+                left - cur.clone() - is_right.clone() * (sibling.clone() - cur.clone()),
+                right - sibling.clone() - is_right * (cur - sibling),
+            ])
+        });
+
+        MerkleConfig { blake2f, path, instance, byte_table, s_swap, s_word }
+    }
+
+    pub fn load(
+        config: &MerkleConfig,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        ByteTableChip::load(&config.byte_table, layouter)
+    }
+
+    // The words from `offset`, each as 8 bytes looked up in the byte table and their
+    // composition on the row below them.
+    fn assign_words(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: usize,
+        annotation: &str,
+        words: [Value<Fr>; DIGEST_WORDS],
+    ) -> Result<[AssignedCell<Fr, Fr>; DIGEST_WORDS], Error> {
+        let mut cells = Vec::with_capacity(DIGEST_WORDS);
+
+        for (i, word) in words.iter().enumerate() {
+            let offset = offset + i * ROWS_PER_COMPOSE;
+            self.config.s_word.enable(region, offset)?;
+
+            for j in 0..8 {
+                self.config.byte_table.enable(region, offset + j)?;
+                let byte = word.map(|word| Fr::from(((word.get_lower_128() >> (8 * j)) & 0xff) as u64));
+                region.assign_advice(|| format!("{annotation} byte"), self.config.path, offset + j, || byte)?;
+            }
+
+            cells.push(region.assign_advice(|| format!("{annotation} {i}"), self.config.path, offset + 8, || *word)?);
+        }
+
+        Ok(cells.try_into().unwrap())
+    }
+
+    fn assign_leaf_words(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        leaf: [Value<Fr>; DIGEST_WORDS],
+    ) -> Result<[AssignedCell<Fr, Fr>; DIGEST_WORDS], Error> {
+        layouter.assign_region(|| "merkle leaf", |mut region| self.assign_words(&mut region, 0, "assign leaf", leaf))
+    }
+}
+
+impl MerkleInstructions<bn256::Fr> for MerkleChip {
+    type Digest = MerkleDigest;
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        let iv = self.blake2f.initialize(layouter)?;
+        Self::load(&self.config, layouter)?;
+        Ok(iv)
+    }
+
+    fn assign_leaf(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        leaf: [Value<u64>; DIGEST_WORDS],
+    ) -> Result<Self::Digest, Error> {
+        let cells = self.assign_leaf_words(layouter, leaf.map(|word| word.map(Fr::from)))?;
+
+        Ok(MerkleDigest { words: leaf, cells: cells.map(|cell| cell.cell()) })
+    }
+
+    fn hash_node(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Blake2fIv,
+        node: &Self::Digest,
+        sibling: [Value<u64>; DIGEST_WORDS],
+        is_right: Value<bool>,
+    ) -> Result<Self::Digest, Error> {
+        let path = self.config.path;
+
+        let m = layouter.assign_region(|| "merkle swap", |mut region| {
+            let mut left = Vec::with_capacity(DIGEST_WORDS);
+            let mut right = Vec::with_capacity(DIGEST_WORDS);
+            let mut bit: Option<Cell> = None;
+
+            // below the swaps and the zero words of the block
+            let siblings = self.assign_words(&mut region, DIGEST_WORDS * ROWS_PER_SWAP + BLOCK_WORDS, "assign sibling", sibling.map(|word| word.map(Fr::from)))?;
+
+            for i in 0..DIGEST_WORDS {
+                let row = i * ROWS_PER_SWAP;
+
+                self.config.s_swap.enable(&mut region, row)?;
+
+                let is_right_cell = region.assign_advice(|| "assign is_right", path, row, || is_right.map(|b| Fr::from(b as u64)))?;
+                match bit {
+                    Some(bit) => region.constrain_equal(bit, is_right_cell.cell())?,
+                    None => bit = Some(is_right_cell.cell()),
+                }
+
+                let cur = region.assign_advice(|| "assign cur", path, row + 1, || node.words[i].map(Fr::from))?;
+                region.constrain_equal(cur.cell(), node.cells[i])?;
+
+                siblings[i].copy_advice(|| "copy sibling", &mut region, path, row + 2)?;
+
+                let (l, r) = node.words[i]
+                    .zip(sibling[i])
+                    .zip(is_right)
+                    .map(|((cur, sibling), is_right)| if is_right { (sibling, cur) } else { (cur, sibling) })
+                    .unzip();
+
+                left.push(region.assign_advice(|| "assign left", path, row + 3, || l.map(Fr::from))?);
+                right.push(region.assign_advice(|| "assign right", path, row + 4, || r.map(Fr::from))?);
+            }
+
+            let mut m = left;
+            m.extend(right);
+
+            let offset = DIGEST_WORDS * ROWS_PER_SWAP;
+            for i in m.len()..BLOCK_WORDS {
+                m.push(region.assign_advice_from_constant(|| "assign zero", path, offset + i, Fr::zero())?);
+            }
+
+            Ok(m)
+        })?;
+
+        let blake2f = &self.blake2f;
+        let h = blake2f.initial_state(layouter, initial_h(DIGEST_WORDS * 8))?;
+
+        // left || right is a single final block of 64 bytes
+        let t = (2 * DIGEST_WORDS * 8) as u64;
+        let state = blake2f.compress_assigned(layouter, iv, &h, m.try_into().unwrap(), [t, 0], true, MAX_ROUND as u64)?;
+
+        let words = state.value();
+        let cells = state.cells();
+
+        Ok(MerkleDigest {
+            words: words[..DIGEST_WORDS].try_into().unwrap(),
+            cells: cells[..DIGEST_WORDS].try_into().unwrap(),
+        })
+    }
+
+    fn expose_root(&self, layouter: &mut impl Layouter<bn256::Fr>, root: &Self::Digest) -> Result<(), Error> {
+        for (i, cell) in root.cells.iter().enumerate() {
+            layouter.constrain_instance(*cell, self.config.instance, i)?;
+        }
+
+        Ok(())
+    }
+}
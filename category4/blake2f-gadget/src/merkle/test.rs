@@ -0,0 +1,151 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::{bn256, FieldExt};
+
+use super::{MerkleChip, MerkleConfig, MerkleInstructions, MerklePath, DIGEST_WORDS};
+use crate::native::{blake2b, words};
+
+fn digest(bytes: &[u8]) -> [u64; DIGEST_WORDS] {
+    words(&blake2b(bytes, DIGEST_WORDS * 8)).try_into().unwrap()
+}
+
+fn hash_node(left: &[u64; DIGEST_WORDS], right: &[u64; DIGEST_WORDS]) -> [u64; DIGEST_WORDS] {
+    let bytes: Vec<u8> = left.iter().chain(right.iter()).flat_map(|w| w.to_le_bytes()).collect();
+    digest(&bytes)
+}
+
+// native tree over 2^depth leaves, returns the root and the path of `index`
+fn merkle_path(depth: usize, index: usize) -> ([u64; DIGEST_WORDS], [u64; DIGEST_WORDS], Vec<([u64; DIGEST_WORDS], bool)>) {
+    let mut level: Vec<[u64; DIGEST_WORDS]> = (0..1u64 << depth).map(|i| digest(&i.to_le_bytes())).collect();
+    let leaf = level[index];
+
+    let mut path = vec![];
+    let mut index = index;
+    while level.len() > 1 {
+        path.push((level[index ^ 1], index & 1 == 1));
+
+        level = level.chunks(2).map(|pair| hash_node(&pair[0], &pair[1])).collect();
+        index >>= 1;
+    }
+
+    (leaf, level[0], path)
+}
+
+#[derive(Default)]
+struct MyCircuit {
+    leaf: [Value<u64>; DIGEST_WORDS],
+    path: Vec<([Value<u64>; DIGEST_WORDS], Value<bool>)>,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = MerkleConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            leaf: [Value::unknown(); DIGEST_WORDS],
+            path: vec![([Value::unknown(); DIGEST_WORDS], Value::unknown()); self.path.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        MerkleChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = MerkleChip::construct(config);
+        let merkle = MerklePath::new(chip, layouter.namespace(|| "load spread table"))?;
+
+        let root = merkle.root(&mut layouter, self.leaf, &self.path)?;
+        merkle.expose_root(&mut layouter, &root)
+    }
+}
+
+fn run(depth: usize, index: usize, tamper_root: bool) -> bool {
+    let (leaf, mut root, path) = merkle_path(depth, index);
+    if tamper_root {
+        root[0] ^= 1;
+    }
+
+    let circuit = MyCircuit {
+        leaf: leaf.map(Value::known),
+        path: path.into_iter().map(|(sibling, is_right)| (sibling.map(Value::known), Value::known(is_right))).collect(),
+    };
+
+    let instance = root.iter().map(|w| bn256::Fr::from(*w)).collect();
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![instance]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+#[test]
+fn test_merkle_depth_1() {
+    assert!(run(1, 0, false));
+    assert!(run(1, 1, false));
+}
+
+#[test]
+fn test_merkle_depth_4() {
+    assert!(run(4, 11, false));
+}
+
+#[test]
+fn test_merkle_wrong_root() {
+    assert!(!run(4, 11, true));
+}
+
+// the leaf words as field elements, assigned and range checked on their own
+struct LeafCircuit {
+    leaf: [bn256::Fr; DIGEST_WORDS],
+}
+
+impl Circuit<bn256::Fr> for LeafCircuit {
+    type Config = MerkleConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self { leaf: self.leaf }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        MerkleChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = MerkleChip::construct(config);
+        chip.initialize(&mut layouter)?;
+
+        chip.assign_leaf_words(&mut layouter, self.leaf.map(Value::known))?;
+        Ok(())
+    }
+}
+
+// `word + 2^64` would alias `word` in the `a += b + m` carry, it has no 8 byte decomposition
+#[test]
+fn test_merkle_word_above_u64() {
+    let (leaf, _, _) = merkle_path(1, 0);
+    let leaf = leaf.map(bn256::Fr::from);
+
+    let prover = MockProver::<bn256::Fr>::run(17, &LeafCircuit { leaf }, vec![vec![]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let mut aliased = leaf;
+    aliased[0] += bn256::Fr::from_u128(1 << 64);
+
+    let prover = MockProver::<bn256::Fr>::run(17, &LeafCircuit { leaf: aliased }, vec![vec![]]).unwrap();
+    assert!(prover.verify().is_err());
+}
@@ -0,0 +1,156 @@
+use crate::blake2b::{initial_h, BLOCK_BYTES, BLOCK_WORDS};
+use crate::MAX_ROUND;
+
+#[cfg(test)]
+mod test;
+
+/// The BLAKE2b IV, the same as SHA-512 (RFC 7693 §2.6).
+pub const IV: [u64; 8] = crate::sha512::IV_512;
+
+/// The message permutations of RFC 7693 §2.7, round r uses `SIGMA[r % 10]`.
+pub const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// `SIGMA` in the order the compression region adds the message words: the subrounds are
+/// the first and the second half of the four column Gs, then of the four diagonal Gs.
+pub const PRE_COMPUTED: [[usize; 16]; 10] = pre_computed();
+
+const fn pre_computed() -> [[usize; 16]; 10] {
+    let mut pre_computed = [[0; 16]; 10];
+    let mut r = 0;
+    while r < 10 {
+        let mut i = 0;
+        while i < 16 {
+            let sub_round = i / 4;
+            let g = (sub_round / 2) * 4 + i % 4;
+            pre_computed[r][i] = SIGMA[r][2 * g + sub_round % 2];
+            i += 1;
+        }
+        r += 1;
+    }
+    pre_computed
+}
+
+/// v0-v15 before the first round: h, the IV, t xored into v12 and v13 and the flag words f and
+/// `last_node`, 0 or 2^64 - 1, into v14 and v15.
+pub fn init_state(h: [u64; 8], t: [u64; 2], f: u64, last_node: u64) -> [u64; 16] {
+    let mut v = [0; 16];
+    v[..8].copy_from_slice(&h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    v[14] ^= f;
+    v[15] ^= last_node;
+    v
+}
+
+/// The mixing function G of RFC 7693 §3.1 on va, vb, vc and vd.
+pub fn g(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// Round r (from 0) on v0-v15.
+pub fn round(v: &mut [u64; 16], m: &[u64; BLOCK_WORDS], r: usize) {
+    let s = SIGMA[r % 10];
+    g(v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+    g(v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+    g(v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+    g(v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+    g(v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+    g(v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+    g(v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+    g(v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+}
+
+/// `init_state` followed by v0-v15 after each of the `rounds` rounds.
+pub fn round_states(init_state: [u64; 16], m: &[u64; BLOCK_WORDS], rounds: usize) -> Vec<[u64; 16]> {
+    let mut v = init_state;
+    let mut states = vec![v];
+    for r in 0..rounds {
+        round(&mut v, m, r);
+        states.push(v);
+    }
+    states
+}
+
+/// h ^ v0-v7 ^ v8-v15, the output of the compression.
+pub fn finalize(mut h: [u64; 8], v: &[u64; 16]) -> [u64; 8] {
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+    h
+}
+
+/// The compression function F of EIP-152, with any number of rounds.
+pub fn blake2f(rounds: u32, h: [u64; 8], m: [u64; BLOCK_WORDS], t: [u64; 2], f: bool) -> [u64; 8] {
+    let mut v = init_state(h, t, if f { u64::MAX } else { 0 }, 0);
+    for r in 0..rounds as usize {
+        round(&mut v, &m, r);
+    }
+    finalize(h, &v)
+}
+
+/// The compression function F of RFC 7693 §3.2, with the last node flag f1 of a tree hash.
+pub fn compress(h: &mut [u64; 8], m: &[u64; BLOCK_WORDS], t: u128, f: bool, last_node: bool) {
+    let flag = |set: bool| if set { u64::MAX } else { 0 };
+    let states = round_states(
+        init_state(*h, [t as u64, (t >> 64) as u64], flag(f), flag(last_node)),
+        m,
+        MAX_ROUND,
+    );
+    *h = finalize(*h, &states[MAX_ROUND]);
+}
+
+/// Little endian words of `bytes`, the last one zero padded.
+pub fn words(bytes: &[u8]) -> Vec<u64> {
+    bytes
+        .chunks(8)
+        .map(|chunk| chunk.iter().rev().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+        .collect()
+}
+
+/// BLAKE2b in sequential mode without key, salt or personalization.
+pub fn blake2b(message: &[u8], digest_len: usize) -> Vec<u8> {
+    hash(initial_h(digest_len), message, digest_len)
+}
+
+/// BLAKE2b of `message` from the initial h, e.g. of a personalized parameter block.
+pub fn hash(h: [u64; 8], message: &[u8], digest_len: usize) -> Vec<u8> {
+    hash_node(h, message, digest_len, false)
+}
+
+/// `hash` with the last node flag set on the last block if `last_node`.
+pub fn hash_node(mut h: [u64; 8], message: &[u8], digest_len: usize, last_node: bool) -> Vec<u8> {
+    let blocks = std::cmp::max(1, (message.len() + BLOCK_BYTES - 1) / BLOCK_BYTES);
+    for i in 0..blocks {
+        let last = i == blocks - 1;
+        let chunk = &message[i * BLOCK_BYTES..std::cmp::min(message.len(), (i + 1) * BLOCK_BYTES)];
+
+        let mut m = [0u64; BLOCK_WORDS];
+        for (m, w) in m.iter_mut().zip(words(chunk)) {
+            *m = w;
+        }
+
+        let t = if last { message.len() } else { (i + 1) * BLOCK_BYTES };
+        compress(&mut h, &m, t as u128, last, last && last_node);
+    }
+
+    h.iter().flat_map(|h| h.to_le_bytes()).take(digest_len).collect()
+}
@@ -0,0 +1,138 @@
+use crate::blake2b::initial_h;
+use crate::{Blake2fTrace, MAX_ROUND};
+
+use super::{blake2b, blake2f, compress, init_state, round_states, words, PRE_COMPUTED, SIGMA};
+
+fn decode(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+// rounds (big endian) || h || m || t || f, as the precompile reads them
+fn parse_eip152(input: &[u8]) -> (u32, [u64; 8], [u64; 16], [u64; 2], bool) {
+    assert_eq!(input.len(), 213);
+
+    let rounds = u32::from_be_bytes(input[..4].try_into().unwrap());
+    let h = words(&input[4..68]).try_into().unwrap();
+    let m = words(&input[68..196]).try_into().unwrap();
+    let t = words(&input[196..212]).try_into().unwrap();
+    (rounds, h, m, t, input[212] == 1)
+}
+
+// https://eips.ethereum.org/EIPS/eip-152#test-cases, vectors 4 to 7 only differ by rounds and f
+const EIP152_H: &str = "48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5\
+                        d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b";
+
+const EIP152_VECTORS: [(u32, u8, &str); 4] = [
+    (
+        0,
+        1,
+        "08c9bcf367e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5\
+         d282e6ad7f520e511f6c3e2b8c68059b9442be0454267ce079217e1319cde05b",
+    ),
+    (
+        12,
+        1,
+        "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+         7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+    ),
+    (
+        12,
+        0,
+        "75ab69d3190a562c51aef8d88f1c2775876944407270c42c9844252c26d28752\
+         98743e7f6d5ea2f2d3e8d226039cd31b4e426ac4f2d3d666a610c2116fde4735",
+    ),
+    (
+        1,
+        1,
+        "b63a380cb2897d521994a85234ee2c181b5f844d2c624c002677e9703449d2fb\
+         a551b3a8333bcdf5f2f7e08993d53923de3d64fcc68c034e717b9293fed7a421",
+    ),
+];
+
+fn eip152_input(rounds: u32, f: u8) -> Vec<u8> {
+    let mut m = b"abc".to_vec();
+    m.resize(128, 0);
+
+    let t = [3u64.to_le_bytes(), 0u64.to_le_bytes()].concat();
+
+    [&rounds.to_be_bytes()[..], &decode(EIP152_H), &m, &t, &[f]].concat()
+}
+
+// RFC 7693 Appendix A
+#[test]
+fn test_native_blake2b() {
+    assert_eq!(
+        blake2b(b"abc", 64),
+        [
+            0xBA, 0x80, 0xA5, 0x3F, 0x98, 0x1C, 0x4D, 0x0D, 0x6A, 0x27, 0x97, 0xB6, 0x9F, 0x12, 0xF6, 0xE9,
+            0x4C, 0x21, 0x2F, 0x14, 0x68, 0x5A, 0xC4, 0xB7, 0x4B, 0x12, 0xBB, 0x6F, 0xDB, 0xFF, 0xA2, 0xD1,
+            0x7D, 0x87, 0xC5, 0x39, 0x2A, 0xAB, 0x79, 0x2D, 0xC2, 0x52, 0xD5, 0xDE, 0x45, 0x33, 0xCC, 0x95,
+            0x18, 0xD3, 0x8A, 0xA8, 0xDB, 0xF1, 0x92, 0x5A, 0xB9, 0x23, 0x86, 0xED, 0xD4, 0x00, 0x99, 0x23,
+        ]
+    );
+}
+
+// the empty message is still one zero block, and a message over two blocks
+#[test]
+fn test_native_blake2b_lengths() {
+    assert_eq!(
+        blake2b(b"", 64),
+        decode(
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419\
+             d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+        )
+    );
+    assert_eq!(
+        blake2b(&(0..200).map(|i| i as u8).collect::<Vec<_>>(), 32),
+        decode("63c3d97a9f8894d5e043a707b0fee7f7ec4c049a23bbf1079df20b4165f9e22d")
+    );
+}
+
+#[test]
+fn test_eip152_vectors() {
+    for (rounds, f, output) in EIP152_VECTORS {
+        let (rounds, h, m, t, f) = parse_eip152(&eip152_input(rounds, f));
+        let h = blake2f(rounds, h, m, t, f);
+
+        assert_eq!(h.iter().flat_map(|h| h.to_le_bytes()).collect::<Vec<_>>(), decode(output));
+    }
+}
+
+// F with 12 rounds is the compression of RFC 7693
+#[test]
+fn test_blake2f_is_compress() {
+    let mut h = initial_h(64);
+    let m: [u64; 16] = core::array::from_fn(|i| (i as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15));
+
+    let expected = blake2f(MAX_ROUND as u32, h, m, [200, 1], true);
+    compress(&mut h, &m, 200 | (1 << 64), true, false);
+    assert_eq!(h, expected);
+}
+
+#[test]
+fn test_pre_computed() {
+    assert_eq!(PRE_COMPUTED[0], [0, 2, 4, 6, 1, 3, 5, 7, 8, 10, 12, 14, 9, 11, 13, 15]);
+    for (pre_computed, sigma) in PRE_COMPUTED.iter().zip(SIGMA) {
+        let mut sorted = *pre_computed;
+        sorted.sort();
+        assert_eq!(sorted, core::array::from_fn(|i| i));
+        assert_eq!(pre_computed[4], sigma[1]);
+        assert_eq!(pre_computed[8], sigma[8]);
+    }
+}
+
+// the states after each round are the ones the trace ends its rounds with
+#[test]
+fn test_round_states_match_trace() {
+    let h = initial_h(32);
+    let m: [u64; 16] = core::array::from_fn(|i| (i as u64).wrapping_mul(0xd1b54a32d192ed03) ^ 0x5555);
+    let v = init_state(h, [128, 0], u64::MAX, 0);
+
+    let states = round_states(v, &m, MAX_ROUND);
+    for rounds in 0..=MAX_ROUND {
+        assert_eq!(Blake2fTrace::from_state(v, m, rounds).final_state(), states[rounds]);
+    }
+}
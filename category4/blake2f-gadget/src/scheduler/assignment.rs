@@ -2,200 +2,314 @@ use std::borrow::BorrowMut;
 
 use halo2_proofs::{
     plonk::Error,
-    circuit::{Value, Layouter},
+    circuit::{AssignedCell, Value, Layouter, Region},
 };
-use halo2curves::{FieldExt, bn256::{self, Fr}};
+use halo2curves::bn256::{self, Fr};
 
 use super::SchedulerConfig;
-use crate::{state::{InnerState, RoundWord}, util::spread_odd_u128_from_xor};
-use crate::InitializedState;
-
-const IV: [u64; 8] = [
-    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
-    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
-];
+use crate::compression::assignment::assign_xor_slots;
+use crate::state::{InnerState, RoundWord};
+use crate::native::IV;
+use crate::{Blake2fTrace, InitializedState, MAX_ROUND};
+
+// v0-v11 and v15 on a row each, v12-v14 before and after the xor, c0, c1 and flag decomposed,
+// with one lookup pair
+pub const SCHEDULER_ROWS: usize = 13 + 9 * 4;
+// v0-v7 are not assigned when chained. These 8 rows are all chaining saves: round 0 still holds
+// the copied words and their limbs, which the gates of round 1 and the final xor read by rotation.
+pub const CHAINED_SCHEDULER_ROWS: usize = SCHEDULER_ROWS - 8;
+// rounds and m, in the `num` column when there is no `round` column
+pub const FIXED_ROUNDS_INPUT_ROWS: usize = 1 + 16;
+
+// The IV in the fixed column, loaded once per circuit and copied into every compression.
+// `last_node` is IV[7] ^ (2^64 - 1), v15 of the last node of a tree level.
+#[derive(Clone, Debug)]
+pub struct Blake2fIv {
+    pub iv: [AssignedCell<Fr, Fr>; 8],
+    pub last_node: AssignedCell<Fr, Fr>,
+}
 
 impl SchedulerConfig {
+    pub fn load_iv(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        layouter.assign_region(|| "blake2f iv", |mut region| {
+            let fixed_column = self.columns.constants;
+
+            let mut ivs = Vec::new();
+            for (i, iv) in IV.iter().enumerate() {
+                ivs.push(region.assign_fixed(|| format!("assign iv {i}"), fixed_column, i, || Value::known(Fr::from(*iv)))?);
+            }
+
+            let last_node = region.assign_fixed(
+                || "assign last node iv 7",
+                fixed_column,
+                IV.len(),
+                || Value::known(Fr::from(IV[7] ^ u64::MAX)),
+            )?;
+
+            Ok(Blake2fIv { iv: ivs.try_into().unwrap(), last_node })
+        })
+    }
+
+    // rows of the scheduler at the top of the compress region, its 9 words with lookup take
+    // 4 rows each with one lookup pair
+    pub fn rows(&self, chained: bool) -> usize {
+        let rows = if chained { CHAINED_SCHEDULER_ROWS } else { SCHEDULER_ROWS };
+        let rows = rows - 9 * (4 - self.columns.word_rows());
+
+        if self.columns.fixed_rounds() {
+            rows + FIXED_ROUNDS_INPUT_ROWS
+        } else {
+            rows
+        }
+    }
+
+    // The scheduler takes the first rows of the compress region, the rounds start below. The
+    // xors setting v12-v14 are copied from `trace`, the trace of the same inputs.
+    #[allow(clippy::too_many_arguments)]
     pub fn process(
         &self,
-        layouter: &mut impl Layouter<bn256::Fr>,
+        region: &mut Region<'_, bn256::Fr>,
+        iv: &Blake2fIv,
+        h: [Value<u64>; 8],
+        m: [Value<u64>; 16],
+        c0: Value<u64>,
+        c1: Value<u64>,
+        flag: Value<u64>,
+        rounds: Value<u64>,
+        trace: Value<&Blake2fTrace>,
+    )  -> Result<InitializedState, Error> {
+        self.process_node(region, iv, h, m, c0, c1, flag, 0, rounds, trace)
+    }
+
+    // `process` for the last node of a BLAKE2 tree level, v15 ^= last_node (RFC 7693 §3.2 f1).
+    // The last node flag is a circuit constant like the IVs.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_node(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        iv: &Blake2fIv,
         h: [Value<u64>; 8],
         m: [Value<u64>; 16],
         c0: Value<u64>,
         c1: Value<u64>,
         flag: Value<u64>,
+        last_node: u64,
         rounds: Value<u64>,
+        trace: Value<&Blake2fTrace>,
+    )  -> Result<InitializedState, Error> {
+        self.assign_state(region, iv, h, None, m, c0, c1, flag, last_node, rounds, trace)
+    }
+
+    // `process_node` for a block after the first: v0-v7 are the words of the previous
+    // compression, decomposed in its final xor, and are neither assigned nor decomposed again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_chained(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        iv: &Blake2fIv,
+        h: &[RoundWord; 8],
+        m: [Value<u64>; 16],
+        c0: Value<u64>,
+        c1: Value<u64>,
+        flag: Value<u64>,
+        last_node: u64,
+        rounds: Value<u64>,
+        trace: Value<&Blake2fTrace>,
+    )  -> Result<InitializedState, Error> {
+        let values = h.clone().map(|h| h.val.value_u64());
+        self.assign_state(region, iv, values, Some(h), m, c0, c1, flag, last_node, rounds, trace)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn assign_state(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        assigned_iv: &Blake2fIv,
+        h: [Value<u64>; 8],
+        chained: Option<&[RoundWord; 8]>,
+        m: [Value<u64>; 16],
+        c0: Value<u64>,
+        c1: Value<u64>,
+        flag: Value<u64>,
+        last_node: u64,
+        rounds: Value<u64>,
+        trace: Value<&Blake2fTrace>,
     )  -> Result<InitializedState, Error> {
         let lookup = self.lookup.clone();
+        let mut iv = IV;
+        iv[7] ^= last_node;
 
         // v0, v1, v2, v3, v4, v5, v6, v7 := h[0], h[1], h[2], h[3], h[4], h[5], h[6], h[7]
         // v8, v9, v10, v11, v12, v13, v14, v15 := iv[0], iv[1], iv[2], iv[3], iv[4], iv[5], iv[6], iv[7]
         // v12 ^= c0
         // v13 ^= c1
         // v14 ^= flag
-        layouter.assign_region(|| "scheduler process", |mut region| {
-            let num_column = self.columns.num;
-            let fixed_column = self.columns.constants;
-            let mut ivs = Vec::new();
-            let mut assigned_m = Vec::new();
-
-            // Assign IV to fixed column
-            for i in 0..IV.len() {
-                ivs.push(
-                    region.assign_fixed(
-                        || {format!("assign iv {i}")} ,
-                        fixed_column,
-                        i,
-                        || Value::known(Fr::from(IV[i]))
-                    )?
-                );
-            }
+        // v15 ^= last_node
+        let num_column = self.columns.num;
+        let mut assigned_m = Vec::new();
+
+        // the IV loaded once, v15 copies the last node one
+        let mut ivs = assigned_iv.iv.clone();
+        if last_node != 0 {
+            ivs[7] = assigned_iv.last_node.clone();
+        }
 
-            // Assign round
-            let assigned_rounds = region.assign_advice(
-                || "assign rounds",
-                self.columns.round,
-                0,
-                || rounds.map(Fr::from)
-            )?;
+        // rounds and `m` go into the ROUND COLUMN, or below the state when the rounds are fixed
+        let rows = self.rows(chained.is_some());
+        let (input_column, input_offset) = match self.columns.rounds {
+            Some(round_columns) => (round_columns.round, 0),
+            None => (num_column, rows - FIXED_ROUNDS_INPUT_ROWS),
+        };
 
+        // Assign round
+        let assigned_rounds = region.assign_advice(
+            || "assign rounds",
+            input_column,
+            input_offset,
+            || rounds.map(Fr::from)
+        )?;
+        if self.columns.fixed_rounds() {
+            region.constrain_constant(assigned_rounds.cell(), Fr::from(MAX_ROUND as u64))?;
+        }
 
-            // Assign `m` into the ROUND COLUMN
-            for (i, v) in m.into_iter().enumerate() {
-                assigned_m.push(region.assign_advice(
-                    || format!("assign m{i}"),
-                    self.columns.round,
-                    i+1, // 0 already assigned to `round`
-                    || v.map(Fr::from),
-                )?);
-            }
 
-            // Assign v0-v11, v15 without lookup
-            // let mut offset = 0;
-
-            use std::rc::Rc;
-            use std::cell::RefCell;
-            
-            // let mut offset = Rc::new();
-            let region: Rc<RefCell<_>> = Rc::new(RefCell::new(region));
-            let offset = Rc::new(RefCell::new(0usize));
-
-            // we borrow mutable ref in two closure
-            let assign_round = |annotation: &'static str, val: Value<u64>, with_lookup: bool| {
-                let region_cell = region.clone();
-                let mut region = region_cell.try_borrow_mut().unwrap();
-
-                let offset_cell = offset.clone();
-                let mut offset = offset_cell.try_borrow_mut().unwrap();
-
-                let var = if with_lookup {
-                    self.s_decompose.enable(&mut region, *offset).unwrap();
-                    RoundWord::assign_with_lookup(|| annotation, &mut region, val, *offset, num_column, &lookup)
-                } else {
-                    RoundWord::assign_without_lookup(|| annotation, &mut region, val, *offset, num_column)
-                };
-
-                if with_lookup {
-                    *offset += 4;
-                } else {
-                    *offset += 1;
-                }
+        // Assign `m` into the ROUND COLUMN
+        for (i, v) in m.into_iter().enumerate() {
+            assigned_m.push(region.assign_advice(
+                || format!("assign m{i}"),
+                input_column,
+                input_offset + i + 1, // 0 already assigned to `round`
+                || v.map(Fr::from),
+            )?);
+        }
 
-                var
-            };
+        // Assign v0-v11, v15 without lookup
+        // let mut offset = 0;
 
-            let assign_updated_v12_v13_v14 = |annotation: &'static str, val: Value<u64>, spread_odd: Value<u128>| {
-                let region_cell = region.clone();
-                let mut region = region_cell.try_borrow_mut().unwrap();
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        
+        // let mut offset = Rc::new();
+        let region: Rc<RefCell<_>> = Rc::new(RefCell::new(region));
+        let offset = Rc::new(RefCell::new(0usize));
 
-                let offset_cell = offset.clone();
-                let mut offset = offset_cell.try_borrow_mut().unwrap();
+        // we borrow mutable ref in two closure
+        let assign_round = |annotation: &'static str, val: Value<u64>, with_lookup: bool| {
+            let region_cell = region.clone();
+            let mut region = region_cell.try_borrow_mut().unwrap();
 
+            let offset_cell = offset.clone();
+            let mut offset = offset_cell.try_borrow_mut().unwrap();
+
+            let var = if with_lookup {
                 self.s_decompose.enable(&mut region, *offset).unwrap();
-                let var = RoundWord::assign_with_lookup(|| annotation, &mut region, val, *offset, num_column, &lookup);
+                RoundWord::assign_with_lookup(|| annotation, &mut region, val, *offset, num_column, &lookup)
+            } else {
+                RoundWord::assign_without_lookup(|| annotation, &mut region, val, *offset, num_column)
+            };
 
-                self.s_xor_v12_v13_v14.enable(&mut region, *offset).unwrap();
+            if with_lookup {
+                *offset += self.columns.word_rows();
+            } else {
+                *offset += 1;
+            }
 
-                region.assign_advice(
-                    || "assign spread_odd",
-                    num_column,
-                    *offset + 3,
-                    || spread_odd.map(Fr::from_u128),
-                )?;
+            var
+        };
 
-                *offset += 4;
+        let assign_updated_v12_v13_v14 = |annotation: &'static str, val: Value<u64>, spread_odd: Value<u128>| {
+            let region_cell = region.clone();
+            let mut region = region_cell.try_borrow_mut().unwrap();
 
-                var
-            };
+            let offset_cell = offset.clone();
+            let mut offset = offset_cell.try_borrow_mut().unwrap();
 
-            // assign v0-v7 without lookup
-            let v0 = assign_round("assign v0", h[0], false)?;
-            let v1 = assign_round("assign v1", h[1], false)?;
-            let v2 = assign_round("assign v2", h[2], false)?;
-            let v3 = assign_round("assign v3", h[3], false)?;
-            let v4 = assign_round("assign v4", h[4], false)?;
-            let v5 = assign_round("assign v5", h[5], false)?;
-            let v6 = assign_round("assign v6", h[6], false)?;
-            let v7 = assign_round("assign v7", h[7], false)?;
-
-            // assign v8-v11 without lookup
-            let v8 = assign_round("assign v8", Value::known(IV[0]), false)?;
-            let v9 = assign_round("assign v9", Value::known(IV[1]), false)?;
-            let v10 = assign_round("assign v10", Value::known(IV[2]), false)?;
-            let v11 = assign_round("assign v11", Value::known(IV[3]), false)?;
-
-            // assign v15 without lookup
-            let v15 = assign_round("assign v15", Value::known(IV[7]), false)?;
-
-            // assign old v12, v13, v14 with lookup
-            let old_v12 = assign_round("assign old v12", Value::known(IV[4]), true)?;
-            let old_v13 = assign_round("assign old v13", Value::known(IV[5]), true)?;
-            let old_v14 = assign_round("assign old v14", Value::known(IV[6]), true)?;
-
-
-            // assign c0, c1, flag with lookup
-            let _var_c0 = assign_round("assign c0", c0, true)?;
-            let _var_c1 = assign_round("assign c1", c1, true)?;
-            let _var_flag = assign_round("assign flag", flag, true)?;
-
-
-            // assign updated v12,v13,v14
-            let v12 = assign_updated_v12_v13_v14(
-                "assign v12 = IV[4]^c0",
-                c0.map(|c0| IV[4] ^ c0),
-                c0.map(|c0| spread_odd_u128_from_xor(IV[4], c0)),
-            )?;
-            let v13 = assign_updated_v12_v13_v14(
-                "assign v13 = IV[5]^c1",
-                c1.map(|c| IV[5] ^ c),
-                c1.map(|c| spread_odd_u128_from_xor(IV[5], c)),
-            )?;
-            let v14 = assign_updated_v12_v13_v14(
-                "assign v14 = IV[6]^flag",
-                flag.map(|flag| IV[6] ^ flag),
-                flag.map(|f| spread_odd_u128_from_xor(IV[6], f)),
-            )?;
+            self.s_decompose.enable(&mut region, *offset).unwrap();
+            let var = RoundWord::assign_with_lookup(|| annotation, &mut region, val, *offset, num_column, &lookup);
 
-            // enforce copy from IVs
-            let mut region = region.try_borrow_mut().unwrap();
-            region.constrain_equal(old_v12.val.cell(), ivs[4].cell())?;
-            region.constrain_equal(old_v13.val.cell(), ivs[5].cell())?;
-            region.constrain_equal(old_v14.val.cell(), ivs[6].cell())?;
-
-            region.constrain_equal(v8.val.cell(), ivs[0].cell())?;
-            region.constrain_equal(v9.val.cell(), ivs[1].cell())?;
-            region.constrain_equal(v10.val.cell(), ivs[2].cell())?;
-            region.constrain_equal(v11.val.cell(), ivs[3].cell())?;
-            region.constrain_equal(v15.val.cell(), ivs[7].cell())?;
-
-            let state = InitializedState {
-                state: InnerState::new(v0,v1,v2,v3,v4,v5,v6,v7,v8,v9,v10,v11,v12,v13,v14,v15),
-                round: assigned_rounds,
-                m: assigned_m.try_into().unwrap(),
-            };
+            self.s_xor_v12_v13_v14.enable(&mut region, *offset).unwrap();
+            assign_xor_slots(&mut region, &lookup, *offset, val, spread_odd, 0)?;
 
-            Ok(state)
-        })
+            *offset += self.columns.word_rows();
+
+            var
+        };
+
+        // assign v0-v7 without lookup, unless chained from the previous compression
+        let [v0, v1, v2, v3, v4, v5, v6, v7] = match chained {
+            Some(words) => words.clone(),
+            None => [
+                assign_round("assign v0", h[0], false)?,
+                assign_round("assign v1", h[1], false)?,
+                assign_round("assign v2", h[2], false)?,
+                assign_round("assign v3", h[3], false)?,
+                assign_round("assign v4", h[4], false)?,
+                assign_round("assign v5", h[5], false)?,
+                assign_round("assign v6", h[6], false)?,
+                assign_round("assign v7", h[7], false)?,
+            ],
+        };
+
+        // assign v8-v11 without lookup
+        let v8 = assign_round("assign v8", Value::known(IV[0]), false)?;
+        let v9 = assign_round("assign v9", Value::known(IV[1]), false)?;
+        let v10 = assign_round("assign v10", Value::known(IV[2]), false)?;
+        let v11 = assign_round("assign v11", Value::known(IV[3]), false)?;
+
+        // assign v15 without lookup
+        let v15 = assign_round("assign v15", Value::known(iv[7]), false)?;
+
+        // assign old v12, v13, v14 with lookup
+        let old_v12 = assign_round("assign old v12", Value::known(IV[4]), true)?;
+        let old_v13 = assign_round("assign old v13", Value::known(IV[5]), true)?;
+        let old_v14 = assign_round("assign old v14", Value::known(IV[6]), true)?;
+
+
+        // assign c0, c1, flag with lookup
+        let var_c0 = assign_round("assign c0", c0, true)?;
+        let var_c1 = assign_round("assign c1", c1, true)?;
+        let var_flag = assign_round("assign flag", flag, true)?;
+
+
+        // assign updated v12,v13,v14
+        let v12 = assign_updated_v12_v13_v14(
+            "assign v12 = IV[4]^c0",
+            trace.map(|trace| trace.init_state[12]),
+            trace.map(|trace| trace.init_odd[0]),
+        )?;
+        let v13 = assign_updated_v12_v13_v14(
+            "assign v13 = IV[5]^c1",
+            trace.map(|trace| trace.init_state[13]),
+            trace.map(|trace| trace.init_odd[1]),
+        )?;
+        let v14 = assign_updated_v12_v13_v14(
+            "assign v14 = IV[6]^flag",
+            trace.map(|trace| trace.init_state[14]),
+            trace.map(|trace| trace.init_odd[2]),
+        )?;
+
+        // enforce copy from IVs
+        let mut region = region.try_borrow_mut().unwrap();
+        region.constrain_equal(old_v12.val.cell(), ivs[4].cell())?;
+        region.constrain_equal(old_v13.val.cell(), ivs[5].cell())?;
+        region.constrain_equal(old_v14.val.cell(), ivs[6].cell())?;
+
+        region.constrain_equal(v8.val.cell(), ivs[0].cell())?;
+        region.constrain_equal(v9.val.cell(), ivs[1].cell())?;
+        region.constrain_equal(v10.val.cell(), ivs[2].cell())?;
+        region.constrain_equal(v11.val.cell(), ivs[3].cell())?;
+        region.constrain_equal(v15.val.cell(), ivs[7].cell())?;
+
+        let state = InitializedState {
+            state: InnerState::new(v0,v1,v2,v3,v4,v5,v6,v7,v8,v9,v10,v11,v12,v13,v14,v15),
+            round: assigned_rounds,
+            m: assigned_m.try_into().unwrap(),
+            t: [var_c0, var_c1],
+            f: var_flag,
+            rows,
+        };
+
+        Ok(state)
     }
 
 }
@@ -247,7 +361,8 @@ mod tests {
                 let columns = Columns::init(meta);
                 let input_dense = meta.advice_column();
                 let input_spread = meta.advice_column();
-                let lookup = SpreadTableChip::configure(meta, input_dense, input_spread);
+                let mut lookup = SpreadTableChip::configure(meta, input_dense, input_spread);
+                SpreadTableChip::configure_xor(meta, &mut lookup, &[]);
                 let extra_config = SchedulerConfig::configure(meta, lookup.input.clone(), columns);
                 for column in [input_dense, input_spread].iter() {
                     meta.enable_equality(*column);
@@ -265,15 +380,27 @@ mod tests {
                 mut layouter: impl Layouter<bn256::Fr>,
             ) -> Result<(), Error> {
                 SpreadTableChip::load(config.lookup_config, &mut layouter)?;
-                let init_state = config.scheduler_config.process(
-                    &mut layouter,
-                    self.inputs.h.iter().map(|v| Value::known(*v)).collect::<Vec<_>>().try_into().unwrap(),
-                    self.inputs.m.iter().map(|v| Value::known(*v)).collect::<Vec<_>>().try_into().unwrap(),
-                    Value::known(self.inputs.t[0]),
-                    Value::known(self.inputs.t[1]),
-                    Value::known(self.inputs.f as u64),
-                    Value::known(self.inputs.rounds as u64),
-                )?;
+                let iv = config.scheduler_config.load_iv(&mut layouter)?;
+                let trace = Blake2fTrace::new(
+                    self.inputs.h,
+                    self.inputs.m,
+                    self.inputs.t,
+                    self.inputs.f as u64,
+                    self.inputs.rounds as usize,
+                );
+                let init_state = layouter.assign_region(|| "scheduler process", |mut region| {
+                    config.scheduler_config.process(
+                        &mut region,
+                        &iv,
+                        self.inputs.h.iter().map(|v| Value::known(*v)).collect::<Vec<_>>().try_into().unwrap(),
+                        self.inputs.m.iter().map(|v| Value::known(*v)).collect::<Vec<_>>().try_into().unwrap(),
+                        Value::known(self.inputs.t[0]),
+                        Value::known(self.inputs.t[1]),
+                        Value::known(self.inputs.f as u64),
+                        Value::known(self.inputs.rounds as u64),
+                        Value::known(&trace),
+                    )
+                })?;
 
                 let state = init_state.state;
                 state.v0.unwrap().val.value_u64().map(|v| assert_eq!(v, self.outputs[0]));
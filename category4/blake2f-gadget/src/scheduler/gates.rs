@@ -5,6 +5,7 @@ use halo2curves::bn256::{self, Fr};
 
 use super::SchedulerConfig;
 use crate::{spread_table::SpreadInputs, chip::{query_table, compose_dense_from_bits, compose_spread_from_bits, Columns}};
+use crate::compression::gates::query_odd;
 
 
 impl SchedulerConfig {
@@ -21,23 +22,23 @@ impl SchedulerConfig {
         meta.create_gate("decompose a u64 to 4 * u16", |meta| {
             let s = meta.query_selector(s_decompose);
 
-            let val = meta.query_advice(columns.num, Rotation::cur());
+            let val = columns.query_cell(meta, 0, 0);
             let (dense, _) = query_table(meta, &lookup, Rotation::cur());
 
             vec![s * (val - compose_dense_from_bits(&dense))]
         });
 
         meta.create_gate("xor v12 v13 v14", |meta| {
-            // old v12: Rotation(-24)
-            // c0: Rotation(-12)
+            // old v12: 6 words above
+            // c0: 3 words above
 
             let s = meta.query_selector(s_xor_v12_v13_v14);
 
-            let (_, s_old_v) = query_table(meta, &lookup, Rotation(-24));
-            let (_, s_rhs) = query_table(meta, &lookup, Rotation(-12));
+            let (_, s_old_v) = query_table(meta, &lookup, columns.word(-6));
+            let (_, s_rhs) = query_table(meta, &lookup, columns.word(-3));
 
             let (_, v) = query_table(meta, &lookup, Rotation::cur());
-            let v_spread_odd = meta.query_advice(columns.num, Rotation(3));
+            let v_spread_odd = query_odd(meta, &lookup);
 
             // v(spread_even) + v_spread_odd = s_old_v + s_rhs
 
@@ -0,0 +1,403 @@
+use halo2_proofs::{
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Expression, Selector, VirtualCells},
+    poly::Rotation,
+};
+use halo2curves::FieldExt;
+use halo2curves::bn256::{self, Fr};
+
+use crate::spread_table::SpreadInputs;
+
+use super::{LIMBS, WORD_BITS};
+
+// Σ0, Σ1, σ0, σ1 of FIPS 180-4 §4.1.2
+pub const BIG_SIGMA_0: [Shift; 3] = [Shift::Rotr(2), Shift::Rotr(13), Shift::Rotr(22)];
+pub const BIG_SIGMA_1: [Shift; 3] = [Shift::Rotr(6), Shift::Rotr(11), Shift::Rotr(25)];
+pub const SMALL_SIGMA_0: [Shift; 3] = [Shift::Rotr(7), Shift::Rotr(18), Shift::Shr(3)];
+pub const SMALL_SIGMA_1: [Shift; 3] = [Shift::Rotr(17), Shift::Rotr(19), Shift::Shr(10)];
+
+#[derive(Clone, Copy, Debug)]
+pub enum Shift {
+    Rotr(usize),
+    Shr(usize),
+}
+
+impl Shift {
+    fn amount(&self) -> usize {
+        match *self {
+            Shift::Rotr(r) | Shift::Shr(r) => r,
+        }
+    }
+
+    pub fn apply(&self, x: u64, word_bits: usize) -> u64 {
+        let mask = if word_bits == 64 { u64::MAX } else { (1 << word_bits) - 1 };
+        match *self {
+            Shift::Rotr(r) => ((x >> r) | (x << (word_bits - r))) & mask,
+            Shift::Shr(r) => x >> r,
+        }
+    }
+}
+
+// The input word is cut at every shift amount, and every piece longer than 16
+// bits is cut again, so each piece can be looked up in the 16-bit spread table.
+// A piece of `len < 16` bits takes a second lookup row holding
+// (dense << (16 - len), spread << 2 * (16 - len)) to bound it to `len` bits.
+//
+// row | num | dense             | spread
+//  0  |  x  | piece_0           | spread(piece_0)
+//  1  |     | piece_0 << (16-l) | spread(piece_0) << 2(16-l)
+// ... |     | piece_i           | ...
+//  e  | xor | even limb 0       | ...
+//  o  | maj | odd limb 0        | ...
+#[derive(Clone, Debug)]
+pub struct SigmaLayout {
+    pub word_bits: usize,
+    pub shifts: Vec<Shift>,
+    // (row, bit offset, bit length), low to high
+    pub pieces: Vec<(usize, usize, usize)>,
+    pub even: usize,
+    pub odd: usize,
+    pub rows: usize,
+}
+
+impl SigmaLayout {
+    pub fn new(word_bits: usize, shifts: &[Shift]) -> Self {
+        let mut bounds = vec![0, word_bits];
+        bounds.extend(shifts.iter().map(|s| s.amount()));
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut pieces = Vec::new();
+        let mut row = 0;
+        for bound in bounds.windows(2) {
+            let mut offset = bound[0];
+            while offset < bound[1] {
+                let len = (bound[1] - offset).min(16);
+                pieces.push((row, offset, len));
+                row += if len < 16 { 2 } else { 1 };
+                offset += len;
+            }
+        }
+
+        let limbs = word_bits / 16;
+        let even = row;
+        let odd = even + limbs;
+
+        Self {
+            word_bits,
+            shifts: shifts.to_vec(),
+            pieces,
+            even,
+            odd,
+            rows: odd + limbs,
+        }
+    }
+
+    // bit position of the piece starting at `offset` after `shift`, None if shifted out
+    pub fn position(&self, shift: Shift, offset: usize) -> Option<usize> {
+        match shift {
+            Shift::Rotr(r) => Some((offset + self.word_bits - r) % self.word_bits),
+            Shift::Shr(r) => {
+                if offset >= r {
+                    Some(offset - r)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SigmaGate {
+    pub selector: Selector,
+    pub layout: SigmaLayout,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha256Gates {
+    pub s_decompose: Selector,
+
+    pub big_sigma_0: SigmaGate,
+    pub big_sigma_1: SigmaGate,
+    pub small_sigma_0: SigmaGate,
+    pub small_sigma_1: SigmaGate,
+
+    pub s_ch: Selector,
+    pub s_maj: Selector,
+
+    pub s_add_2: Selector,
+    pub s_add_4: Selector,
+    pub s_add_6: Selector,
+    pub s_add_7: Selector,
+}
+
+impl Sha256Gates {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        num: Column<Advice>,
+        lookup: &SpreadInputs,
+    ) -> Self {
+        let s_decompose = meta.selector();
+        let s_ch = meta.selector();
+        let s_maj = meta.selector();
+        let s_add_2 = meta.selector();
+        let s_add_4 = meta.selector();
+        let s_add_6 = meta.selector();
+        let s_add_7 = meta.selector();
+
+        decompose_gate("decompose a u32 to 2 * u16", meta, s_decompose, num, lookup, LIMBS);
+
+        let big_sigma_0 = sigma_gate("Σ0", meta, num, lookup, SigmaLayout::new(WORD_BITS, &BIG_SIGMA_0));
+        let big_sigma_1 = sigma_gate("Σ1", meta, num, lookup, SigmaLayout::new(WORD_BITS, &BIG_SIGMA_1));
+        let small_sigma_0 = sigma_gate("σ0", meta, num, lookup, SigmaLayout::new(WORD_BITS, &SMALL_SIGMA_0));
+        let small_sigma_1 = sigma_gate("σ1", meta, num, lookup, SigmaLayout::new(WORD_BITS, &SMALL_SIGMA_1));
+
+        ch_gate("ch", meta, s_ch, num, lookup, WORD_BITS);
+        maj_gate("maj", meta, s_maj, lookup, WORD_BITS);
+
+        add_gate::<2>("add 2 words", meta, s_add_2, num, lookup, WORD_BITS);
+        add_gate::<4>("add 4 words", meta, s_add_4, num, lookup, WORD_BITS);
+        add_gate::<6>("add 6 words", meta, s_add_6, num, lookup, WORD_BITS);
+        add_gate::<7>("add 7 words", meta, s_add_7, num, lookup, WORD_BITS);
+
+        Self {
+            s_decompose,
+            big_sigma_0,
+            big_sigma_1,
+            small_sigma_0,
+            small_sigma_1,
+            s_ch,
+            s_maj,
+            s_add_2,
+            s_add_4,
+            s_add_6,
+            s_add_7,
+        }
+    }
+
+    // the add gates by their number of terms, see `Words::s_add`
+    pub(crate) fn s_add(&self) -> [Option<Selector>; 8] {
+        let mut s_add = [None; 8];
+        s_add[2] = Some(self.s_add_2);
+        s_add[4] = Some(self.s_add_4);
+        s_add[6] = Some(self.s_add_6);
+        s_add[7] = Some(self.s_add_7);
+        s_add
+    }
+}
+
+fn pow2(bits: usize) -> Expression<bn256::Fr> {
+    Expression::Constant(Fr::from_u128(1 << bits))
+}
+
+// spread of the all-ones word, i.e. spread(!x) = ONES - spread(x)
+pub(crate) fn spread_ones(word_bits: usize) -> u128 {
+    (0..word_bits).fold(0u128, |acc, i| acc + (1 << (2 * i)))
+}
+
+// little endian limbs of the word starting at `row`
+pub(crate) fn query_word(
+    meta: &mut VirtualCells<bn256::Fr>,
+    lookup: &SpreadInputs,
+    row: usize,
+    limbs: usize,
+) -> (Expression<bn256::Fr>, Expression<bn256::Fr>) {
+    let mut dense = Expression::Constant(Fr::zero());
+    let mut spread = Expression::Constant(Fr::zero());
+
+    for i in 0..limbs {
+        let rotation = Rotation((row + i) as i32);
+        dense = dense + meta.query_advice(lookup.dense, rotation) * pow2(16 * i);
+        spread = spread + meta.query_advice(lookup.spread, rotation) * pow2(32 * i);
+    }
+
+    (dense, spread)
+}
+
+pub(crate) fn decompose_gate(
+    name: &'static str,
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    selector: Selector,
+    num: Column<Advice>,
+    lookup: &SpreadInputs,
+    limbs: usize,
+) {
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+
+        let val = meta.query_advice(num, Rotation::cur());
+        let (dense, _) = query_word(meta, lookup, 0, limbs);
+
+        Constraints::with_selector(s, vec![val - dense])
+    });
+}
+
+// xor(shift_0(x), shift_1(x), shift_2(x)):
+//   sum of the shifted spreads = spread(even) + 2 * spread(odd)
+pub(crate) fn sigma_gate(
+    name: &'static str,
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    num: Column<Advice>,
+    lookup: &SpreadInputs,
+    layout: SigmaLayout,
+) -> SigmaGate {
+    let selector = meta.selector();
+    let limbs = layout.word_bits / 16;
+
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+        let x = meta.query_advice(num, Rotation::cur());
+
+        let mut constraints = Vec::new();
+        let mut dense_sum = Expression::Constant(Fr::zero());
+        let mut spreads = Vec::new();
+
+        for &(row, offset, len) in layout.pieces.iter() {
+            let dense = meta.query_advice(lookup.dense, Rotation(row as i32));
+            let spread = meta.query_advice(lookup.spread, Rotation(row as i32));
+
+            if len < 16 {
+                let scaled_dense = meta.query_advice(lookup.dense, Rotation(row as i32 + 1));
+                let scaled_spread = meta.query_advice(lookup.spread, Rotation(row as i32 + 1));
+
+                constraints.push(scaled_dense - dense.clone() * pow2(16 - len));
+                constraints.push(scaled_spread - spread.clone() * pow2(2 * (16 - len)));
+            }
+
+            dense_sum = dense_sum + dense * pow2(offset);
+            spreads.push((offset, spread));
+        }
+
+        // x = sum(piece_i * 2^offset_i)
+        constraints.push(x - dense_sum);
+
+        let mut shifted = Expression::Constant(Fr::zero());
+        for shift in layout.shifts.iter() {
+            for (offset, spread) in spreads.iter() {
+                if let Some(position) = layout.position(*shift, *offset) {
+                    shifted = shifted + spread.clone() * pow2(2 * position);
+                }
+            }
+        }
+
+        let (_, even) = query_word(meta, lookup, layout.even, limbs);
+        let (_, odd) = query_word(meta, lookup, layout.odd, limbs);
+
+        constraints.push(shifted - even - odd * Expression::Constant(Fr::from(2)));
+
+        Constraints::with_selector(s, constraints)
+    });
+
+    SigmaGate { selector, layout }
+}
+
+// row       | num           | dense/spread
+// 0         | e             | e
+// 1         | ch            |
+// limbs     | f             | f
+// 2 * limbs | g             | g
+// 3 * limbs | e ^ f         | e ^ f
+// 4 * limbs | e & f         | e & f
+// 5 * limbs | !e ^ g        | !e ^ g
+// 6 * limbs | !e & g        | !e & g
+pub(crate) fn ch_gate(
+    name: &'static str,
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    selector: Selector,
+    num: Column<Advice>,
+    lookup: &SpreadInputs,
+    word_bits: usize,
+) {
+    let limbs = word_bits / 16;
+
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+
+        let (_, e) = query_word(meta, lookup, 0, limbs);
+        let (_, f) = query_word(meta, lookup, limbs, limbs);
+        let (_, g) = query_word(meta, lookup, 2 * limbs, limbs);
+        let (_, ef_even) = query_word(meta, lookup, 3 * limbs, limbs);
+        let (_, ef_odd) = query_word(meta, lookup, 4 * limbs, limbs);
+        let (_, neg_even) = query_word(meta, lookup, 5 * limbs, limbs);
+        let (_, neg_odd) = query_word(meta, lookup, 6 * limbs, limbs);
+
+        let ch = meta.query_advice(num, Rotation::next());
+        let ef_and = meta.query_advice(num, Rotation((4 * limbs) as i32));
+        let neg_and = meta.query_advice(num, Rotation((6 * limbs) as i32));
+
+        let two = Expression::Constant(Fr::from(2));
+        let ones = Expression::Constant(Fr::from_u128(spread_ones(word_bits)));
+
+        Constraints::with_selector(
+            s,
+            vec![
+                e.clone() + f - ef_even - ef_odd * two.clone(),
+                ones - e + g - neg_even - neg_odd * two,
+                // (e & f) and (!e & g) never share a bit, so xor is a plain sum
+                ch - ef_and - neg_and,
+            ],
+        )
+    });
+}
+
+// row       | dense/spread
+// 0         | a
+// limbs     | b
+// 2 * limbs | c
+// 3 * limbs | a ^ b ^ c
+// 4 * limbs | maj(a, b, c)
+pub(crate) fn maj_gate(
+    name: &'static str,
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    selector: Selector,
+    lookup: &SpreadInputs,
+    word_bits: usize,
+) {
+    let limbs = word_bits / 16;
+
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+
+        let (_, a) = query_word(meta, lookup, 0, limbs);
+        let (_, b) = query_word(meta, lookup, limbs, limbs);
+        let (_, c) = query_word(meta, lookup, 2 * limbs, limbs);
+        let (_, even) = query_word(meta, lookup, 3 * limbs, limbs);
+        let (_, odd) = query_word(meta, lookup, 4 * limbs, limbs);
+
+        Constraints::with_selector(
+            s,
+            vec![a + b + c - even - odd * Expression::Constant(Fr::from(2))],
+        )
+    });
+}
+
+// row           | num    | dense/spread
+// 0..TERMS      | term_i |
+// TERMS         | result | result
+// TERMS + limbs |        | carry
+//
+// The carry is looked up, so it is < 2^16 and the sum can't wrap around the field.
+pub(crate) fn add_gate<const TERMS: usize>(
+    name: &'static str,
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    selector: Selector,
+    num: Column<Advice>,
+    lookup: &SpreadInputs,
+    word_bits: usize,
+) {
+    let limbs = word_bits / 16;
+
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+
+        let mut sum = Expression::Constant(Fr::zero());
+        for i in 0..TERMS {
+            sum = sum + meta.query_advice(num, Rotation(i as i32));
+        }
+
+        let result = meta.query_advice(num, Rotation(TERMS as i32));
+        let carry = meta.query_advice(lookup.dense, Rotation((TERMS + limbs) as i32));
+
+        Constraints::with_selector(s, vec![sum - result - carry * pow2(word_bits)])
+    });
+}
@@ -0,0 +1,219 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed},
+};
+use halo2curves::{FieldExt, bn256};
+
+use crate::bits::AssignedBits;
+use crate::spread_table::{SpreadTableChip, SpreadTableConfig};
+
+mod assignment;
+pub(crate) mod gates;
+#[cfg(test)]
+mod test;
+
+use gates::Sha256Gates;
+
+pub(crate) const WORD_BITS: usize = 32;
+pub(crate) const LIMBS: usize = WORD_BITS / 16;
+pub(crate) const ROUNDS: usize = 64;
+pub const BLOCK_SIZE: usize = 16;
+pub const DIGEST_SIZE: usize = 8;
+
+pub(crate) const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+pub(crate) const ROUND_CONSTANTS: [u32; ROUNDS] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+pub struct Sha256<F: FieldExt, CS: Sha256Instructions<F>> {
+    chip: CS,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Sha256Chip: Sha256Instructions<F>> Sha256<F, Sha256Chip> {
+    pub fn new(chip: Sha256Chip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            _marker: PhantomData,
+        })
+    }
+
+    // The message length is fixed by the circuit, like `padding(l)` in category1/sha256.circom.
+    pub fn digest(
+        &mut self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+    ) -> Result<[Sha256Chip::BlockWord; DIGEST_SIZE], Error> {
+        let mut state = self.chip.initialization_vector(layouter)?;
+
+        for block in padding(message) {
+            state = self.chip.compress(layouter, &state, block)?;
+        }
+
+        self.chip.digest(layouter, &state)
+    }
+}
+
+pub trait Sha256Instructions<F: FieldExt>: Chip<F> {
+    type State: Clone + Debug;
+    type BlockWord: Copy + Debug + Default;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error>;
+
+    fn initialization_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error>;
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+        block: [Self::BlockWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error>;
+
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+}
+
+// <message> 1 <zeros> <bit length as u64>, big endian words, see FIPS 180-4 §5.1.1
+pub fn padding(message: &[Value<u8>]) -> Vec<[Value<u32>; BLOCK_SIZE]> {
+    let bit_len = (message.len() as u64) * 8;
+
+    let mut bytes = message.to_vec();
+    bytes.push(Value::known(0x80));
+    while bytes.len() % 64 != 56 {
+        bytes.push(Value::known(0));
+    }
+    bytes.extend(bit_len.to_be_bytes().map(Value::known));
+
+    bytes
+        .chunks(64)
+        .map(|block| {
+            block
+                .chunks(4)
+                .map(|word| {
+                    word.iter().fold(Value::known(0u32), |acc, byte| {
+                        acc.zip(*byte).map(|(acc, byte)| (acc << 8) | byte as u32)
+                    })
+                })
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha256State(pub [AssignedBits<32>; 8]);
+
+#[derive(Clone, Debug)]
+pub struct Sha256Config {
+    lookup: SpreadTableConfig,
+    num: Column<Advice>,
+    constants: Column<Fixed>,
+    gates: Sha256Gates,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha256Chip {
+    config: Sha256Config,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for Sha256Chip {
+    type Config = Sha256Config;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Sha256Chip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let num = meta.advice_column();
+        let constants = meta.fixed_column();
+
+        let input_dense = meta.advice_column();
+        let input_spread = meta.advice_column();
+
+        meta.enable_equality(num);
+        meta.enable_constant(constants);
+
+        // XOR, Ch and Maj all go through the same spread table as BLAKE2f
+        let lookup = SpreadTableChip::configure(meta, input_dense, input_spread);
+        let gates = Sha256Gates::configure(meta, num, &lookup.input);
+
+        Sha256Config { lookup, num, constants, gates }
+    }
+
+    pub fn load(
+        config: &Sha256Config,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        SpreadTableChip::load(config.lookup.clone(), layouter)
+    }
+}
+
+impl Sha256Instructions<bn256::Fr> for Sha256Chip {
+    type State = Sha256State;
+    type BlockWord = Value<u32>;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<(), Error> {
+        Self::load(&self.config, layouter)
+    }
+
+    fn initialization_vector(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<Self::State, Error> {
+        layouter.assign_region(|| "sha256 iv", |mut region| self.config.assign_iv(&mut region))
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        state: &Self::State,
+        block: [Self::BlockWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error> {
+        layouter.assign_region(
+            || "sha256 compress",
+            |mut region| self.config.assign_compress(&mut region, state, block),
+        )
+    }
+
+    fn digest(
+        &self,
+        _layouter: &mut impl Layouter<bn256::Fr>,
+        state: &Self::State,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error> {
+        Ok(state.0.clone().map(|word| word.value_u32()))
+    }
+}
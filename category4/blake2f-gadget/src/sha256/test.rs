@@ -0,0 +1,98 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::bn256;
+
+use super::{Sha256, Sha256Chip, Sha256Config, DIGEST_SIZE};
+
+#[derive(Default)]
+struct MyCircuit {
+    message: Vec<u8>,
+    digest: [u32; DIGEST_SIZE],
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = Sha256Config;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // the message length is part of the circuit
+        Self {
+            message: vec![0; self.message.len()],
+            digest: [0; DIGEST_SIZE],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        Sha256Chip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Sha256Chip::construct(config);
+        let mut sha256 = Sha256::new(chip, layouter.namespace(|| "load spread table"))?;
+
+        let message = self.message.iter().map(|b| Value::known(*b)).collect::<Vec<_>>();
+        let digest = sha256.digest(&mut layouter, &message)?;
+
+        digest.iter().zip(self.digest).for_each(|(h, ex_h)| {
+            h.map(|v| assert_eq!(v, ex_h));
+        });
+
+        Ok(())
+    }
+}
+
+fn run(message: &[u8], digest: [u32; DIGEST_SIZE]) {
+    let circuit = MyCircuit {
+        message: message.to_vec(),
+        digest,
+    };
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+// category1/test/SHA256_1.js
+#[test]
+fn test_sha256_abc() {
+    run(
+        b"abc",
+        [3128432319, 2399260650, 1094795486, 1571693091, 2953011619, 2518121116, 3021012833, 4060091821],
+    );
+}
+
+// category1/test/SHA256_2.js, two blocks after padding
+#[test]
+fn test_sha256_abc_24() {
+    run(
+        &b"abc".repeat(24),
+        [125991561, 1949098848, 2422801197, 105223881, 3578045529, 1355105186, 3518866688, 2848027893],
+    );
+}
+
+#[test]
+fn test_padding() {
+    let blocks = super::padding(&[Value::known(b'a'), Value::known(b'b'), Value::known(b'c')]);
+    assert_eq!(blocks.len(), 1);
+
+    let words = blocks[0].map(|w| {
+        let mut v = 0;
+        w.map(|w| v = w);
+        v
+    });
+    assert_eq!(words[0], 0x61626380);
+    assert!(words[1..15].iter().all(|w| *w == 0));
+    assert_eq!(words[15], 24);
+
+    let blocks = super::padding(&vec![Value::known(0u8); 72]);
+    assert_eq!(blocks.len(), 2);
+}
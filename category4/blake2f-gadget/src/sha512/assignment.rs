@@ -0,0 +1,135 @@
+use halo2_proofs::{
+    circuit::{Region, Value},
+    plonk::Error,
+};
+use halo2curves::bn256;
+
+use crate::{
+    bits::{AssignedBits, Bits},
+    state::RoundWord,
+    words::{AddTerm, Words},
+};
+
+use super::{Sha512Config, Sha512State, LIMBS, ROUNDS, ROUND_CONSTANTS};
+
+impl Sha512Config {
+    fn words(&self) -> Words<'_, 64> {
+        Words {
+            num: self.num,
+            s_decompose: self.gates.s_decompose,
+            s_add: self.gates.s_add(),
+            lookup: &self.lookup.input,
+        }
+    }
+
+    pub(super) fn assign_iv(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        iv: &[u64; 8],
+    ) -> Result<Sha512State, Error> {
+        let mut state = Vec::with_capacity(8);
+
+        for (i, iv) in iv.iter().enumerate() {
+            let val = region.assign_advice_from_constant(
+                || format!("assign iv {i}"),
+                self.num,
+                i,
+                Bits::<64>::from(*iv),
+            )?;
+            state.push(RoundWord::new(AssignedBits(val), None));
+        }
+
+        Ok(Sha512State(state.try_into().unwrap()))
+    }
+
+    pub(super) fn assign_compress(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        state: &Sha512State,
+        block: [Value<u64>; 16],
+    ) -> Result<Sha512State, Error> {
+        let words = self.words();
+        let mut offset = 0;
+
+        // W[0..16] is the message block
+        let mut w = Vec::with_capacity(ROUNDS);
+        for (i, word) in block.into_iter().enumerate() {
+            w.push(words.assign_word(region, || format!("assign w{i}"), offset, word)?);
+            offset += LIMBS;
+        }
+
+        // W[t] = σ1(W[t-2]) + W[t-7] + σ0(W[t-15]) + W[t-16]
+        for t in 16..ROUNDS {
+            let s0 = words.assign_sigma(region, &mut offset, &self.gates.small_sigma_0, &w[t - 15])?;
+            let s1 = words.assign_sigma(region, &mut offset, &self.gates.small_sigma_1, &w[t - 2])?;
+            let wt = words.assign_add(
+                region,
+                &mut offset,
+                &[
+                    AddTerm::Word(&s1),
+                    AddTerm::Word(&w[t - 7]),
+                    AddTerm::Word(&s0),
+                    AddTerm::Word(&w[t - 16]),
+                ],
+            )?;
+            w.push(wt);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state.0.clone().map(|word| word.val);
+
+        for t in 0..ROUNDS {
+            let s1 = words.assign_sigma(region, &mut offset, &self.gates.big_sigma_1, &e)?;
+            let ch = words.assign_ch(region, &mut offset, self.gates.s_ch, [&e, &f, &g])?;
+            let s0 = words.assign_sigma(region, &mut offset, &self.gates.big_sigma_0, &a)?;
+            let (_, maj) = words.assign_xor_maj(region, &mut offset, self.gates.s_maj, [&a, &b, &c])?;
+
+            // e' = d + T1
+            // a' = T1 + T2
+            //   T1 = h + Σ1(e) + ch(e, f, g) + K[t] + W[t]
+            //   T2 = Σ0(a) + maj(a, b, c)
+            let new_e = words.assign_add(
+                region,
+                &mut offset,
+                &[
+                    AddTerm::Word(&d),
+                    AddTerm::Word(&h),
+                    AddTerm::Word(&s1),
+                    AddTerm::Word(&ch),
+                    AddTerm::Constant(ROUND_CONSTANTS[t]),
+                    AddTerm::Word(&w[t]),
+                ],
+            )?;
+            let new_a = words.assign_add(
+                region,
+                &mut offset,
+                &[
+                    AddTerm::Word(&h),
+                    AddTerm::Word(&s1),
+                    AddTerm::Word(&ch),
+                    AddTerm::Constant(ROUND_CONSTANTS[t]),
+                    AddTerm::Word(&w[t]),
+                    AddTerm::Word(&s0),
+                    AddTerm::Word(&maj),
+                ],
+            )?;
+
+            h = g;
+            g = f;
+            f = e;
+            e = new_e;
+            d = c;
+            c = b;
+            b = a;
+            a = new_a;
+        }
+
+        let vs = [a, b, c, d, e, f, g, h];
+        let mut hs = Vec::with_capacity(8);
+        for (h, v) in state.0.iter().zip(vs.iter()) {
+            let h = words.assign_add(region, &mut offset, &[AddTerm::Word(&h.val), AddTerm::Word(v)])?;
+            hs.push(RoundWord::new(h, None));
+        }
+
+        Ok(Sha512State(hs.try_into().unwrap()))
+    }
+}
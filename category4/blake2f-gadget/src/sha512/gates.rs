@@ -0,0 +1,91 @@
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Selector};
+use halo2curves::bn256;
+
+use crate::spread_table::SpreadInputs;
+use crate::sha256::gates::{
+    add_gate, ch_gate, decompose_gate, maj_gate, sigma_gate, Shift, SigmaGate, SigmaLayout,
+};
+
+use super::WORD_BITS;
+
+// Σ0, Σ1, σ0, σ1 of FIPS 180-4 §4.1.3
+pub const BIG_SIGMA_0: [Shift; 3] = [Shift::Rotr(28), Shift::Rotr(34), Shift::Rotr(39)];
+pub const BIG_SIGMA_1: [Shift; 3] = [Shift::Rotr(14), Shift::Rotr(18), Shift::Rotr(41)];
+pub const SMALL_SIGMA_0: [Shift; 3] = [Shift::Rotr(1), Shift::Rotr(8), Shift::Shr(7)];
+pub const SMALL_SIGMA_1: [Shift; 3] = [Shift::Rotr(19), Shift::Rotr(61), Shift::Shr(6)];
+
+// Same gates as SHA-256, on 64-bit words made of four 16-bit limbs (see `RoundWord`).
+#[derive(Clone, Debug)]
+pub struct Sha512Gates {
+    pub s_decompose: Selector,
+
+    pub big_sigma_0: SigmaGate,
+    pub big_sigma_1: SigmaGate,
+    pub small_sigma_0: SigmaGate,
+    pub small_sigma_1: SigmaGate,
+
+    pub s_ch: Selector,
+    pub s_maj: Selector,
+
+    pub s_add_2: Selector,
+    pub s_add_4: Selector,
+    pub s_add_6: Selector,
+    pub s_add_7: Selector,
+}
+
+impl Sha512Gates {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+        num: Column<Advice>,
+        lookup: &SpreadInputs,
+    ) -> Self {
+        let limbs = WORD_BITS / 16;
+
+        let s_decompose = meta.selector();
+        let s_ch = meta.selector();
+        let s_maj = meta.selector();
+        let s_add_2 = meta.selector();
+        let s_add_4 = meta.selector();
+        let s_add_6 = meta.selector();
+        let s_add_7 = meta.selector();
+
+        decompose_gate("decompose a u64 to 4 * u16", meta, s_decompose, num, lookup, limbs);
+
+        let big_sigma_0 = sigma_gate("Σ0 (512)", meta, num, lookup, SigmaLayout::new(WORD_BITS, &BIG_SIGMA_0));
+        let big_sigma_1 = sigma_gate("Σ1 (512)", meta, num, lookup, SigmaLayout::new(WORD_BITS, &BIG_SIGMA_1));
+        let small_sigma_0 = sigma_gate("σ0 (512)", meta, num, lookup, SigmaLayout::new(WORD_BITS, &SMALL_SIGMA_0));
+        let small_sigma_1 = sigma_gate("σ1 (512)", meta, num, lookup, SigmaLayout::new(WORD_BITS, &SMALL_SIGMA_1));
+
+        ch_gate("ch (512)", meta, s_ch, num, lookup, WORD_BITS);
+        maj_gate("maj (512)", meta, s_maj, lookup, WORD_BITS);
+
+        add_gate::<2>("add 2 u64", meta, s_add_2, num, lookup, WORD_BITS);
+        add_gate::<4>("add 4 u64", meta, s_add_4, num, lookup, WORD_BITS);
+        add_gate::<6>("add 6 u64", meta, s_add_6, num, lookup, WORD_BITS);
+        add_gate::<7>("add 7 u64", meta, s_add_7, num, lookup, WORD_BITS);
+
+        Self {
+            s_decompose,
+            big_sigma_0,
+            big_sigma_1,
+            small_sigma_0,
+            small_sigma_1,
+            s_ch,
+            s_maj,
+            s_add_2,
+            s_add_4,
+            s_add_6,
+            s_add_7,
+        }
+    }
+
+    // the add gates by their number of terms, see `Words::s_add`
+    pub(crate) fn s_add(&self) -> [Option<Selector>; 8] {
+        let mut s_add = [None; 8];
+        s_add[2] = Some(self.s_add_2);
+        s_add[4] = Some(self.s_add_4);
+        s_add[6] = Some(self.s_add_6);
+        s_add[7] = Some(self.s_add_7);
+        s_add
+    }
+}
@@ -0,0 +1,262 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed},
+};
+use halo2curves::{FieldExt, bn256};
+
+use crate::spread_table::{SpreadTableChip, SpreadTableConfig};
+use crate::state::RoundWord;
+
+mod assignment;
+mod gates;
+#[cfg(test)]
+mod test;
+
+use gates::Sha512Gates;
+
+pub(crate) const WORD_BITS: usize = 64;
+pub(crate) const LIMBS: usize = WORD_BITS / 16;
+pub(crate) const ROUNDS: usize = 80;
+pub const BLOCK_SIZE: usize = 16;
+pub const DIGEST_SIZE: usize = 8;
+
+// FIPS 180-4 §5.3.5
+pub const IV_512: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+// FIPS 180-4 §5.3.6.2
+pub const IV_512_256: [u64; 8] = [
+    0x22312194fc2bf72c, 0x9f555fa3c84c64c2, 0x2393b86b6f53b151, 0x963877195940eabd,
+    0x96283ee2a88effe3, 0xbe5e1e2553863992, 0x2b0199fc2c85b8aa, 0x0eb72ddc81c52ca2,
+];
+
+pub(crate) const ROUND_CONSTANTS: [u64; ROUNDS] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+pub struct Sha512<F: FieldExt, CS: Sha512Instructions<F>> {
+    chip: CS,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Sha512Chip: Sha512Instructions<F>> Sha512<F, Sha512Chip> {
+    pub fn new(chip: Sha512Chip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn digest(
+        &mut self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+    ) -> Result<[Sha512Chip::BlockWord; DIGEST_SIZE], Error> {
+        self.hash(layouter, &IV_512, message)
+    }
+
+    // SHA-512/256 only differs by its IV and the truncated output, see FIPS 180-4 §6.7
+    pub fn digest_512_256(
+        &mut self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+    ) -> Result<[Sha512Chip::BlockWord; 4], Error> {
+        let digest = self.hash(layouter, &IV_512_256, message)?;
+        Ok(digest[..4].try_into().unwrap())
+    }
+
+    fn hash(
+        &mut self,
+        layouter: &mut impl Layouter<F>,
+        iv: &[u64; 8],
+        message: &[Value<u8>],
+    ) -> Result<[Sha512Chip::BlockWord; DIGEST_SIZE], Error> {
+        let mut state = self.chip.initialization_vector(layouter, iv)?;
+
+        for block in padding(message) {
+            state = self.chip.compress(layouter, &state, block)?;
+        }
+
+        self.chip.digest(layouter, &state)
+    }
+}
+
+pub trait Sha512Instructions<F: FieldExt>: Chip<F> {
+    type State: Clone + Debug;
+    type BlockWord: Copy + Debug + Default;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error>;
+
+    fn initialization_vector(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &[u64; 8],
+    ) -> Result<Self::State, Error>;
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+        block: [Self::BlockWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error>;
+
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+}
+
+// <message> 1 <zeros> <bit length as u128>, big endian words, see FIPS 180-4 §5.1.2
+pub fn padding(message: &[Value<u8>]) -> Vec<[Value<u64>; BLOCK_SIZE]> {
+    let bit_len = (message.len() as u128) * 8;
+
+    let mut bytes = message.to_vec();
+    bytes.push(Value::known(0x80));
+    while bytes.len() % 128 != 112 {
+        bytes.push(Value::known(0));
+    }
+    bytes.extend(bit_len.to_be_bytes().map(Value::known));
+
+    bytes
+        .chunks(128)
+        .map(|block| {
+            block
+                .chunks(8)
+                .map(|word| {
+                    word.iter().fold(Value::known(0u64), |acc, byte| {
+                        acc.zip(*byte).map(|(acc, byte)| (acc << 8) | byte as u64)
+                    })
+                })
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap()
+        })
+        .collect()
+}
+
+// The working variables are the same `RoundWord`s as the BLAKE2f state.
+#[derive(Clone, Debug)]
+pub struct Sha512State(pub [RoundWord; 8]);
+
+#[derive(Clone, Debug)]
+pub struct Sha512Config {
+    lookup: SpreadTableConfig,
+    num: Column<Advice>,
+    constants: Column<Fixed>,
+    gates: Sha512Gates,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha512Chip {
+    config: Sha512Config,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for Sha512Chip {
+    type Config = Sha512Config;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Sha512Chip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let num = meta.advice_column();
+        let constants = meta.fixed_column();
+
+        let input_dense = meta.advice_column();
+        let input_spread = meta.advice_column();
+
+        meta.enable_equality(num);
+        meta.enable_constant(constants);
+
+        let lookup = SpreadTableChip::configure(meta, input_dense, input_spread);
+        let gates = Sha512Gates::configure(meta, num, &lookup.input);
+
+        Sha512Config { lookup, num, constants, gates }
+    }
+
+    pub fn load(
+        config: &Sha512Config,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        SpreadTableChip::load(config.lookup.clone(), layouter)
+    }
+}
+
+impl Sha512Instructions<bn256::Fr> for Sha512Chip {
+    type State = Sha512State;
+    type BlockWord = Value<u64>;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<(), Error> {
+        Self::load(&self.config, layouter)
+    }
+
+    fn initialization_vector(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &[u64; 8],
+    ) -> Result<Self::State, Error> {
+        layouter.assign_region(|| "sha512 iv", |mut region| self.config.assign_iv(&mut region, iv))
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        state: &Self::State,
+        block: [Self::BlockWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error> {
+        layouter.assign_region(
+            || "sha512 compress",
+            |mut region| self.config.assign_compress(&mut region, state, block),
+        )
+    }
+
+    fn digest(
+        &self,
+        _layouter: &mut impl Layouter<bn256::Fr>,
+        state: &Self::State,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error> {
+        Ok(state.0.clone().map(|word| word.val.value_u64()))
+    }
+}
@@ -0,0 +1,131 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::bn256;
+
+use super::{Sha512, Sha512Chip, Sha512Config};
+
+const ABC_112: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+#[derive(Default)]
+struct MyCircuit {
+    message: Vec<u8>,
+    // 8 words for SHA-512, 4 for SHA-512/256
+    digest: Vec<u64>,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = Sha512Config;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            message: vec![0; self.message.len()],
+            digest: vec![0; self.digest.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        Sha512Chip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = Sha512Chip::construct(config);
+        let mut sha512 = Sha512::new(chip, layouter.namespace(|| "load spread table"))?;
+
+        let message = self.message.iter().map(|b| Value::known(*b)).collect::<Vec<_>>();
+        let digest = match self.digest.len() {
+            4 => sha512.digest_512_256(&mut layouter, &message)?.to_vec(),
+            _ => sha512.digest(&mut layouter, &message)?.to_vec(),
+        };
+
+        digest.iter().zip(self.digest.iter()).for_each(|(h, ex_h)| {
+            h.map(|v| assert_eq!(v, *ex_h));
+        });
+
+        Ok(())
+    }
+}
+
+fn run(message: &[u8], digest: &[u64]) {
+    let circuit = MyCircuit {
+        message: message.to_vec(),
+        digest: digest.to_vec(),
+    };
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+// NIST SHA512.pdf, one block
+#[test]
+fn test_sha512_abc() {
+    run(
+        b"abc",
+        &[
+            0xddaf35a193617aba, 0xcc417349ae204131, 0x12e6fa4e89a97ea2, 0x0a9eeee64b55d39a,
+            0x2192992a274fc1a8, 0x36ba3c23a3feebbd, 0x454d4423643ce80e, 0x2a9ac94fa54ca49f,
+        ],
+    );
+}
+
+#[test]
+fn test_sha512_empty() {
+    run(
+        b"",
+        &[
+            0xcf83e1357eefb8bd, 0xf1542850d66d8007, 0xd620e4050b5715dc, 0x83f4a921d36ce9ce,
+            0x47d0d13c5d85f2b0, 0xff8318d2877eec2f, 0x63b931bd47417a81, 0xa538327af927da3e,
+        ],
+    );
+}
+
+// NIST SHA512.pdf, two blocks after padding
+#[test]
+fn test_sha512_abc_112() {
+    run(
+        ABC_112,
+        &[
+            0x8e959b75dae313da, 0x8cf4f72814fc143f, 0x8f7779c6eb9f7fa1, 0x7299aeadb6889018,
+            0x501d289e4900f7e4, 0x331b99dec4b5433a, 0xc7d329eeb6dd2654, 0x5e96e55b874be909,
+        ],
+    );
+}
+
+// NIST SHA512_256.pdf
+#[test]
+fn test_sha512_256_abc() {
+    run(b"abc", &[0x53048e2681941ef9, 0x9b2e29b76b4c7dab, 0xe4c2d0c634fc6d46, 0xe0e2f13107e7af23]);
+}
+
+#[test]
+fn test_sha512_256_abc_112() {
+    run(ABC_112, &[0x3928e184fb8690f8, 0x40da3988121d31be, 0x65cb9d3ef83ee614, 0x6feac861e19b563a]);
+}
+
+#[test]
+fn test_padding() {
+    let blocks = super::padding(&[Value::known(b'a'), Value::known(b'b'), Value::known(b'c')]);
+    assert_eq!(blocks.len(), 1);
+
+    let words = blocks[0].map(|w| {
+        let mut v = 0;
+        w.map(|w| v = w);
+        v
+    });
+    assert_eq!(words[0], 0x6162638000000000);
+    assert!(words[1..15].iter().all(|w| *w == 0));
+    assert_eq!(words[15], 24);
+
+    let blocks = super::padding(&vec![Value::known(0u8); 112]);
+    assert_eq!(blocks.len(), 2);
+}
@@ -2,11 +2,10 @@ use crate::{util::*, bits::AssignedBits};
 use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::{Chip, Layouter, Region, Value},
-    plonk::{Advice, Column, ConstraintSystem, Error, TableColumn},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector, TableColumn},
     poly::Rotation,
 };
 use halo2curves::{pasta::pallas, bn256};
-use std::convert::TryInto;
 use std::marker::PhantomData;
 
 
@@ -20,24 +19,13 @@ pub(super) struct SpreadWord<const DENSE: usize, const SPREAD: usize> {
 
 
 impl<const DENSE: usize, const SPREAD: usize> SpreadWord<DENSE, SPREAD> {
-    pub(super) fn new(dense: [bool; DENSE]) -> Self {
+    // the limb `dense` of at most DENSE bits, spread on the integer
+    pub(super) fn new(dense: u64) -> Self {
         assert!(DENSE <= 16);
+        assert_eq!(dense >> DENSE, 0);
         SpreadWord {
-            dense,
-            spread: spread_bits(dense),
-        }
-    }
-
-    #[allow(dead_code)]
-    pub(super) fn try_new<T: TryInto<[bool; DENSE]> + std::fmt::Debug>(dense: T) -> Self
-    where
-        <T as TryInto<[bool; DENSE]>>::Error: std::fmt::Debug,
-    {
-        assert!(DENSE <= 16);
-        let dense: [bool; DENSE] = dense.try_into().unwrap();
-        SpreadWord {
-            dense,
-            spread: spread_bits(dense),
+            dense: i2lebsp(dense),
+            spread: u1282lebsp_n(spread_u64(dense)),
         }
     }
 }
@@ -55,15 +43,26 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
         cols: &SpreadInputs,
         row: usize,
         word: Value<SpreadWord<DENSE, SPREAD>>,
+    ) -> Result<Self, Error> {
+        Self::with_lookup_in(region, cols.dense, cols.spread, row, word)
+    }
+
+    // `with_lookup` into one of the parallel input pairs
+    pub(super) fn with_lookup_in(
+        region: &mut Region<'_, bn256::Fr>,
+        dense_col: Column<Advice>,
+        spread_col: Column<Advice>,
+        row: usize,
+        word: Value<SpreadWord<DENSE, SPREAD>>,
     ) -> Result<Self, Error> {
         let dense_val = word.map(|word| word.dense);
         let spread_val = word.map(|word| word.spread);
 
         let dense =
-            AssignedBits::<DENSE>::assign_bits(region, || "dense", cols.dense, row, dense_val)?;
+            AssignedBits::<DENSE>::assign_bits(region, || "dense", dense_col, row, dense_val)?;
 
         let spread =
-            AssignedBits::<SPREAD>::assign_bits(region, || "spread", cols.spread, row, spread_val)?;
+            AssignedBits::<SPREAD>::assign_bits(region, || "spread", spread_col, row, spread_val)?;
 
         Ok(SpreadVar { dense, spread })
     }
@@ -104,6 +103,58 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
 pub struct SpreadInputs {
     pub(super) dense: Column<Advice>,
     pub(super) spread: Column<Advice>,
+    // more (dense, spread) pairs looked up in the same table, next to the first one
+    pub(super) parallel: Vec<(Column<Advice>, Column<Advice>)>,
+    // when set, every pair of a row is multiplied by this cell before the lookup: 1 looks the
+    // row up, 0 skips it. Each row that holds limbs must constrain it.
+    pub(super) enable: Option<Column<Advice>>,
+    // the pairs of the xor slots next to the limbs, twice as many as the limb pairs, empty
+    // unless `configure_xor` adds them
+    pub(super) xor: Vec<(Column<Advice>, Column<Advice>)>,
+    // the xor slot and the width of a narrow limb, and the selector that looks it up below 2^width
+    pub(super) narrow: Vec<(usize, usize, Selector)>,
+}
+
+// The slots of an xor word: the 4 limbs of the odd bits, then the low and the high part of the
+// limb a rotation splits. The parts are also in a narrow lookup of their width.
+pub(crate) const XOR_ODD: usize = 0;
+pub(crate) const XOR_LOW: usize = 4;
+pub(crate) const XOR_HIGH: usize = 5;
+
+impl SpreadInputs {
+    // number of input pairs, the 4 limbs of a word take 4 / lanes rows
+    pub(crate) fn lanes(&self) -> usize {
+        1 + self.parallel.len()
+    }
+
+    // the pair of limb `i` of a word and its row below the first limb
+    pub(crate) fn limb(&self, i: usize) -> (Column<Advice>, Column<Advice>, usize) {
+        let lane = i % self.lanes();
+        let (dense, spread) = if lane == 0 {
+            (self.dense, self.spread)
+        } else {
+            self.parallel[lane - 1]
+        };
+
+        (dense, spread, i / self.lanes())
+    }
+
+    // the pair of xor slot `i` of a word and its row below the first limb
+    pub(crate) fn xor_limb(&self, i: usize) -> (Column<Advice>, Column<Advice>, usize) {
+        let pairs = self.xor.len();
+        let (dense, spread) = self.xor[i % pairs];
+
+        (dense, spread, i / pairs)
+    }
+
+    // the selector of the narrow lookup of `width` bits in xor slot `slot`
+    pub(crate) fn narrow(&self, slot: usize, width: usize) -> Selector {
+        self.narrow
+            .iter()
+            .find(|narrow| (narrow.0, narrow.1) == (slot, width))
+            .expect("narrow lookup configured")
+            .2
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -142,24 +193,58 @@ impl<F: FieldExt> SpreadTableChip<F> {
         meta: &mut ConstraintSystem<F>,
         input_dense: Column<Advice>,
         input_spread: Column<Advice>,
+    ) -> <Self as Chip<F>>::Config {
+        Self::configure_parallel(meta, input_dense, input_spread, vec![])
+    }
+
+    // one lookup into the same table for every input pair
+    pub fn configure_parallel(
+        meta: &mut ConstraintSystem<F>,
+        input_dense: Column<Advice>,
+        input_spread: Column<Advice>,
+        parallel: Vec<(Column<Advice>, Column<Advice>)>,
+    ) -> <Self as Chip<F>>::Config {
+        Self::configure_with_enable(meta, input_dense, input_spread, parallel, None)
+    }
+
+    // `configure_parallel` with the lookup of a row multiplied by the `enable` cell of the row
+    pub fn configure_with_enable(
+        meta: &mut ConstraintSystem<F>,
+        input_dense: Column<Advice>,
+        input_spread: Column<Advice>,
+        parallel: Vec<(Column<Advice>, Column<Advice>)>,
+        enable: Option<Column<Advice>>,
     ) -> <Self as Chip<F>>::Config {
         let table_dense = meta.lookup_table_column();
         let table_spread = meta.lookup_table_column();
 
-        meta.lookup("lookup", |meta| {
-            let dense_cur = meta.query_advice(input_dense, Rotation::cur());
-            let spread_cur = meta.query_advice(input_spread, Rotation::cur());
+        for (input_dense, input_spread) in std::iter::once((input_dense, input_spread)).chain(parallel.iter().copied()) {
+            meta.lookup("lookup", |meta| {
+                let mut dense_cur = meta.query_advice(input_dense, Rotation::cur());
+                let mut spread_cur = meta.query_advice(input_spread, Rotation::cur());
 
-            vec![
-                (dense_cur, table_dense),
-                (spread_cur, table_spread),
-            ]
-        });
+                // (0, 0) is in the table
+                if let Some(enable) = enable {
+                    let enable = meta.query_advice(enable, Rotation::cur());
+                    dense_cur = enable.clone() * dense_cur;
+                    spread_cur = enable * spread_cur;
+                }
+
+                vec![
+                    (dense_cur, table_dense),
+                    (spread_cur, table_spread),
+                ]
+            });
+        }
 
         SpreadTableConfig {
             input: SpreadInputs {
                 dense: input_dense,
                 spread: input_spread,
+                parallel,
+                enable,
+                xor: vec![],
+                narrow: vec![],
             },
             table: SpreadTable {
                 dense: table_dense,
@@ -168,6 +253,58 @@ impl<F: FieldExt> SpreadTableChip<F> {
         }
     }
 
+    // The xor slots, 2 pairs per limb pair looked up like the limbs, with the `enable` cell of
+    // the row if there is one. A limb of `width` bits in one of the `narrow` (slot, width) is
+    // looked up again shifted left by 16 - width, so it is below 2^width.
+    pub fn configure_xor(
+        meta: &mut ConstraintSystem<F>,
+        config: &mut SpreadTableConfig,
+        narrow: &[(usize, usize)],
+    ) {
+        let input = &mut config.input;
+        let table = &config.table;
+
+        input.xor = (0..2 * input.lanes()).map(|_| (meta.advice_column(), meta.advice_column())).collect();
+
+        for &(input_dense, input_spread) in input.xor.iter() {
+            meta.lookup("xor lookup", |meta| {
+                let mut dense_cur = meta.query_advice(input_dense, Rotation::cur());
+                let mut spread_cur = meta.query_advice(input_spread, Rotation::cur());
+
+                if let Some(enable) = input.enable {
+                    let enable = meta.query_advice(enable, Rotation::cur());
+                    dense_cur = enable.clone() * dense_cur;
+                    spread_cur = enable * spread_cur;
+                }
+
+                vec![
+                    (dense_cur, table.dense),
+                    (spread_cur, table.spread),
+                ]
+            });
+        }
+
+        for &(slot, width) in narrow {
+            assert!(width < 16);
+            let (input_dense, input_spread, _) = input.xor_limb(slot);
+            let s_narrow = meta.complex_selector();
+
+            meta.lookup("narrow lookup", |meta| {
+                let s = meta.query_selector(s_narrow);
+                let dense_cur = meta.query_advice(input_dense, Rotation::cur());
+                let spread_cur = meta.query_advice(input_spread, Rotation::cur());
+
+                let shift = 16 - width;
+                vec![
+                    (s.clone() * dense_cur * Expression::Constant(F::from_u128(1 << shift)), table.dense),
+                    (s * spread_cur * Expression::Constant(F::from_u128(1 << (2 * shift))), table.spread),
+                ]
+            });
+
+            input.narrow.push((slot, width, s_narrow));
+        }
+    }
+
     pub fn load(
         config: SpreadTableConfig,
         layouter: &mut impl Layouter<F>,
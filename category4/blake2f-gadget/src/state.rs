@@ -188,6 +188,52 @@ impl RoundWord {
         Ok(new_val)
     }
 
+    // Copy a word that was decomposed where it was assigned, its limb cells are copied instead
+    // of witnessed again. The copied limbs are looked up again, like those of a new word.
+    pub fn copy_into_with_halves(&self, region: &mut Region<'_, bn256::Fr>, offset: usize, columns: &Columns, lookup: &SpreadInputs) -> Result<Self, Error> {
+        let halves = self.halves.as_ref().expect("word decomposed where it was assigned");
+
+        let val = self.val.copy_advice(|| "copy roundword-val", region, columns.num, offset).map(AssignedBits)?;
+
+        let mut copies = Vec::with_capacity(4);
+        for (i, half) in halves.iter().enumerate() {
+            let (dense_col, spread_col, row) = lookup.limb(i);
+
+            copies.push(SpreadVar {
+                dense: half.dense.copy_advice(|| "copy dense limb", region, dense_col, offset + row).map(AssignedBits)?,
+                spread: half.spread.copy_advice(|| "copy spread limb", region, spread_col, offset + row).map(AssignedBits)?,
+            });
+        }
+
+        Self::enable_lookup(region, offset, lookup, None)?;
+
+        Ok(Self::new(val, Some(copies.try_into().unwrap())))
+    }
+
+    // Copy a word into a padding round. The copy gate checks the value. The limbs are zeros
+    // that the gates multiply by s_round = 0, their lookup is disabled and the word has no halves.
+    pub fn copy_into_padding(&self, region: &mut Region<'_, bn256::Fr>, offset: usize, columns: &Columns, lookup: &SpreadInputs) -> Result<Self, Error> {
+        let val = AssignedBits::<64>::assign(
+            region,
+            || "padding roundword-val",
+            columns.num,
+            offset,
+            self.val.value_u64(),
+        )?;
+
+        for i in 0..4 {
+            let (dense_col, spread_col, row) = lookup.limb(i);
+            SpreadVar::<16, 32>::with_lookup_in(region, dense_col, spread_col, offset + row, Value::known(SpreadWord::new(0)))?;
+        }
+
+        Self::enable_lookup(region, offset, lookup, Some(Value::known(bn256::Fr::zero())))?;
+
+        Ok(Self {
+            val,
+            halves: None,
+        })
+    }
+
     pub fn assign_without_lookup<A: Fn()-> AR, AR: Into<String>>(
         annotation: A,
         region: &mut Region<'_, bn256::Fr>,
@@ -209,6 +255,7 @@ impl RoundWord {
         })
     }
 
+    // A word decomposed into limbs that are always looked up
     pub(super) fn assign_with_lookup<A: Fn()-> AR, AR: Into<String>>(
         annotation: A,
         region: &mut Region<'_, bn256::Fr>,
@@ -216,6 +263,58 @@ impl RoundWord {
         offset: usize,
         num_col: Column<Advice>,
         lookup: &SpreadInputs,
+    ) -> Result<Self, Error> {
+        let word = Self::assign_limbs(annotation, region, val, offset, num_col, lookup)?;
+        Self::enable_lookup(region, offset, lookup, None)?;
+
+        Ok(word)
+    }
+
+    // A word of a computed round, looked up since its `enable` cells are s_round = 1. A padding
+    // round assigns the same rows with zero limbs and the lookup disabled.
+    pub(super) fn assign_in_round<A: Fn()-> AR, AR: Into<String>>(
+        annotation: A,
+        region: &mut Region<'_, bn256::Fr>,
+        val: Value<u64>,
+        offset: usize,
+        num_col: Column<Advice>,
+        lookup: &SpreadInputs,
+    ) -> Result<Self, Error> {
+        let word = Self::assign_limbs(annotation, region, val, offset, num_col, lookup)?;
+        Self::enable_lookup(region, offset, lookup, Some(Value::known(bn256::Fr::one())))?;
+
+        Ok(word)
+    }
+
+    // The `enable` cells of the limb rows of a word, if the lookup has them. `None` copies them
+    // from the constant 1: the same copy constraints for every `rounds`, on the rows whose limbs
+    // are read whatever the rounds. Otherwise they are witnessed and `lookup in main round` checks
+    // they are s_round.
+    fn enable_lookup(
+        region: &mut Region<'_, bn256::Fr>,
+        offset: usize,
+        lookup: &SpreadInputs,
+        in_round: Option<Value<bn256::Fr>>,
+    ) -> Result<(), Error> {
+        if let Some(enable) = lookup.enable {
+            for row in 0..4 / lookup.lanes() {
+                match in_round {
+                    Some(value) => region.assign_advice(|| "enable lookup", enable, offset + row, || value)?,
+                    None => region.assign_advice_from_constant(|| "enable lookup", enable, offset + row, bn256::Fr::one())?,
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    fn assign_limbs<A: Fn()-> AR, AR: Into<String>>(
+        annotation: A,
+        region: &mut Region<'_, bn256::Fr>,
+        val: Value<u64>,
+        offset: usize,
+        num_col: Column<Advice>,
+        lookup: &SpreadInputs,
     ) -> Result<Self, Error> {
         let mut v = 0;
         val.map(|x| {
@@ -223,15 +322,7 @@ impl RoundWord {
             x
         });
 
-        let bits: [_; 64] = i2lebsp(v);
-
-        let d0: [bool; 16] = bits[..16].try_into().unwrap();
-        let d1: [bool; 16] = bits[16..32].try_into().unwrap();
-        let d2: [bool; 16] = bits[32..48].try_into().unwrap();
-        let d3: [bool; 16] = bits[48..].try_into().unwrap();
-
         let annotation = annotation().into();
-        let dences = [d0, d1, d2, d3];
 
         let val = AssignedBits::<64>::assign(
             region,
@@ -241,10 +332,12 @@ impl RoundWord {
             val,
         )?;
 
+        // limb i in the pair i % lanes, on row i / lanes
         let mut halves = Vec::new();
         for i in 0..4 {
-            let sw = SpreadWord::<16, 32>::new(dences[i]);
-            let s = SpreadVar::with_lookup(region, lookup, offset + i, Value::known(sw))?;
+            let (dense_col, spread_col, row) = lookup.limb(i);
+            let sw = SpreadWord::<16, 32>::new((v >> (16 * i)) & 0xffff);
+            let s = SpreadVar::with_lookup_in(region, dense_col, spread_col, offset + row, Value::known(sw))?;
             halves.push(s);
         }
 
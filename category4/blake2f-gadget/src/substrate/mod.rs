@@ -0,0 +1,253 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+};
+use halo2curves::{FieldExt, bn256::{self, Fr}};
+
+use crate::byte_table::{ByteTableChip, ByteTableConfig};
+use crate::blake2b::{hash_blocks, initial_h};
+use crate::transcript::{compose, compose_gate, ROWS_PER_COMPOSE};
+use crate::{Blake2fChip, Blake2fConfig, Blake2fInstructions, Blake2fIv};
+
+#[cfg(test)]
+mod test;
+
+// digest lengths of frame_support's Blake2_128 and Blake2_256 hashers
+pub const BLAKE2_128_BYTES: usize = 16;
+pub const BLAKE2_256_BYTES: usize = 32;
+
+// The storage hashers of a Substrate runtime over SCALE encoded bytes, which the caller encodes
+// natively. Inputs and outputs are range checked byte cells, so digests and raw keys can be
+// concatenated into storage keys or fed to the next hash.
+pub struct SubstrateHasher<F: FieldExt, CS: SubstrateInstructions<F>> {
+    chip: CS,
+    iv: CS::Iv,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, SubstrateChip: SubstrateInstructions<F>> SubstrateHasher<F, SubstrateChip> {
+    pub fn new(chip: SubstrateChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        Ok(Self {
+            chip,
+            iv,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn blake2_128(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        data: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.chip.blake2b(layouter, &self.iv, data, BLAKE2_128_BYTES)
+    }
+
+    pub fn blake2_256(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        data: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.chip.blake2b(layouter, &self.iv, data, BLAKE2_256_BYTES)
+    }
+
+    // blake2_128(data) || data, the raw key is reused cell by cell
+    pub fn blake2_128_concat(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        data: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let digest = self.blake2_128(layouter, data)?;
+        Ok([digest, data.to_vec()].concat())
+    }
+
+    // prefix || blake2_128_concat(key), the prefix being twox_128(pallet) || twox_128(item)
+    pub fn map_key(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        prefix: &[u8],
+        key: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let prefix = self.chip.assign_constant_bytes(layouter, prefix)?;
+        let hashed = self.blake2_128_concat(layouter, key)?;
+
+        Ok([prefix, hashed].concat())
+    }
+}
+
+pub trait SubstrateInstructions<F: FieldExt>: Chip<F> {
+    // the cells every compression copies
+    type Iv: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
+
+    // range checked bytes
+    fn assign_bytes(&self, layouter: &mut impl Layouter<F>, bytes: &[Value<u8>]) -> Result<Vec<AssignedCell<F, F>>, Error>;
+
+    fn assign_constant_bytes(&self, layouter: &mut impl Layouter<F>, bytes: &[u8]) -> Result<Vec<AssignedCell<F, F>>, Error>;
+
+    // unkeyed BLAKE2b with a `digest_len` output, as bytes
+    fn blake2b(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: &Self::Iv,
+        data: &[AssignedCell<F, F>],
+        digest_len: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct SubstrateConfig {
+    blake2f: Blake2fConfig,
+    bytes: Column<Advice>,
+    byte_table: ByteTableConfig,
+    s_word: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubstrateChip {
+    config: SubstrateConfig,
+    blake2f: Blake2fChip,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for SubstrateChip {
+    type Config = SubstrateConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl SubstrateChip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            blake2f: Blake2fChip::construct(config.blake2f.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let blake2f = Blake2fChip::configure(meta);
+
+        let bytes = meta.advice_column();
+        meta.enable_equality(bytes);
+
+        let byte_table = ByteTableChip::configure(meta, bytes);
+
+        // 8 little endian bytes to a message or digest word
+        let s_word = meta.selector();
+        compose_gate(meta, "compose word", s_word, bytes, Fr::from(1 << 8));
+
+        SubstrateConfig { blake2f, bytes, byte_table, s_word }
+    }
+
+    pub fn load(
+        config: &SubstrateConfig,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        ByteTableChip::load(&config.byte_table, layouter)
+    }
+}
+
+impl SubstrateInstructions<bn256::Fr> for SubstrateChip {
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        let iv = self.blake2f.initialize(layouter)?;
+        Self::load(&self.config, layouter)?;
+        Ok(iv)
+    }
+
+    fn assign_bytes(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        bytes: &[Value<u8>],
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        layouter.assign_region(|| "substrate bytes", |mut region| {
+            bytes
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| {
+                    self.config.byte_table.enable(&mut region, i)?;
+                    region.assign_advice(|| "assign byte", self.config.bytes, i, || byte.map(|b| Fr::from(b as u64)))
+                })
+                .collect()
+        })
+    }
+
+    fn assign_constant_bytes(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        bytes: &[u8],
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        layouter.assign_region(|| "substrate constant", |mut region| {
+            bytes
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| {
+                    region.assign_advice_from_constant(|| "assign constant byte", self.config.bytes, i, Fr::from(*byte as u64))
+                })
+                .collect()
+        })
+    }
+
+    fn blake2b(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        iv: &Blake2fIv,
+        data: &[AssignedCell<Fr, Fr>],
+        digest_len: usize,
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        let zero = self.assign_constant_bytes(layouter, &[0])?.remove(0);
+
+        // the last word is padded with zero bytes, as `Blake2b::hash` expects
+        let words = layouter.assign_region(|| "substrate message", |mut region| {
+            data.chunks(8)
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let limbs: Vec<_> = (0..8)
+                        .map(|j| chunk.get(j).unwrap_or(&zero))
+                        .map(|byte| (byte.value().copied(), byte.cell()))
+                        .collect();
+
+                    compose(&mut region, self.config.bytes, i * ROWS_PER_COMPOSE, self.config.s_word, Fr::from(1 << 8), &limbs)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        let state = hash_blocks(&self.blake2f, layouter, iv, initial_h(digest_len), &words, data.len())?;
+
+        // the digest words are recomposed from range checked bytes
+        layouter.assign_region(|| "substrate digest", |mut region| {
+            let mut digest = Vec::with_capacity(digest_len);
+
+            for (i, (word, cell)) in state.value().iter().zip(state.cells()).take((digest_len + 7) / 8).enumerate() {
+                let offset = i * ROWS_PER_COMPOSE;
+                self.config.s_word.enable(&mut region, offset)?;
+
+                for j in 0..8 {
+                    self.config.byte_table.enable(&mut region, offset + j)?;
+                    let byte = word.map(|w| Fr::from((w >> (8 * j)) & 0xff));
+                    digest.push(region.assign_advice(|| "assign digest byte", self.config.bytes, offset + j, || byte)?);
+                }
+
+                let composed = region.assign_advice(|| "copy digest word", self.config.bytes, offset + 8, || word.map(Fr::from))?;
+                region.constrain_equal(composed.cell(), cell)?;
+            }
+
+            digest.truncate(digest_len);
+            Ok(digest)
+        })
+    }
+}
@@ -0,0 +1,168 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2curves::bn256;
+
+use super::{SubstrateChip, SubstrateConfig, SubstrateHasher, SubstrateInstructions, BLAKE2_128_BYTES, BLAKE2_256_BYTES};
+use crate::native::blake2b;
+
+// twox_128(b"System") || twox_128(b"Account")
+const SYSTEM_ACCOUNT: [u8; 32] = [
+    0x26, 0xaa, 0x39, 0x4e, 0xea, 0x56, 0x30, 0xe0, 0x7c, 0x48, 0xae, 0x0c, 0x95, 0x58, 0xce, 0xf7, 0xb9, 0x9d, 0x88,
+    0x0e, 0xc6, 0x81, 0x79, 0x9c, 0x0c, 0xf3, 0x0e, 0x88, 0x86, 0x37, 0x1d, 0xa9,
+];
+
+// //Alice sr25519 public key
+const ALICE: [u8; 32] = [
+    0xd4, 0x35, 0x93, 0xc7, 0x15, 0xfd, 0xd3, 0x1c, 0x61, 0x14, 0x1a, 0xbd, 0x04, 0xa9, 0x9f, 0xd6, 0x82, 0x2c, 0x85,
+    0x58, 0x85, 0x4c, 0xcd, 0xe3, 0x9a, 0x56, 0x84, 0xe7, 0xa5, 0x6d, 0xa2, 0x7d,
+];
+
+// blake2_128(ALICE), the hashed part of System.Account(Alice) on every dev chain
+const ALICE_BLAKE2_128: [u8; BLAKE2_128_BYTES] = [
+    0xde, 0x1e, 0x86, 0xa9, 0xa8, 0xc7, 0x39, 0x86, 0x4c, 0xf3, 0xcc, 0x5e, 0xc2, 0xbe, 0xa5, 0x9f,
+];
+
+// SCALE compact encoding of an integer below 2^30
+fn compact(n: u32) -> Vec<u8> {
+    match n {
+        0..=0x3f => vec![(n << 2) as u8],
+        0x40..=0x3fff => ((n << 2) as u16 | 1).to_le_bytes().to_vec(),
+        _ => ((n << 2) | 2).to_le_bytes().to_vec(),
+    }
+}
+
+// SCALE encoded header with an empty digest
+fn header(parent: [u8; 32], number: u32, state_root: [u8; 32], extrinsics_root: [u8; 32]) -> Vec<u8> {
+    [&parent[..], &compact(number)[..], &state_root[..], &extrinsics_root[..], &compact(0)[..]].concat()
+}
+
+fn blake2_128_concat(data: &[u8]) -> Vec<u8> {
+    [blake2b(data, BLAKE2_128_BYTES), data.to_vec()].concat()
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Hasher {
+    Blake2_128Concat,
+    Blake2_256,
+    // System.Account map key
+    AccountKey,
+}
+
+struct MyCircuit {
+    hasher: Hasher,
+    data: Vec<u8>,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = (SubstrateConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            hasher: self.hasher,
+            data: vec![0; self.data.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (SubstrateChip::configure(meta), instance)
+    }
+
+    fn synthesize(
+        &self,
+        (config, instance): Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = SubstrateChip::construct(config);
+        let hasher = SubstrateHasher::new(chip.clone(), layouter.namespace(|| "load tables"))?;
+
+        let data = chip.assign_bytes(&mut layouter, &self.data.iter().map(|b| Value::known(*b)).collect::<Vec<_>>())?;
+
+        let out = match self.hasher {
+            Hasher::Blake2_128Concat => hasher.blake2_128_concat(&mut layouter, &data)?,
+            Hasher::Blake2_256 => hasher.blake2_256(&mut layouter, &data)?,
+            Hasher::AccountKey => hasher.map_key(&mut layouter, &SYSTEM_ACCOUNT, &data)?,
+        };
+
+        for (i, byte) in out.iter().enumerate() {
+            layouter.constrain_instance(byte.cell(), instance, i)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn run(hasher: Hasher, data: &[u8], expected: &[u8]) -> bool {
+    let circuit = MyCircuit {
+        hasher,
+        data: data.to_vec(),
+    };
+
+    let instance = expected.iter().map(|b| bn256::Fr::from(*b as u64)).collect();
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![instance]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+// python3 -c "import hashlib; print(hashlib.blake2b(data, digest_size=16).hexdigest())"
+#[test]
+fn test_native_hashers() {
+    assert_eq!(blake2b(&ALICE, BLAKE2_128_BYTES), ALICE_BLAKE2_128);
+    assert_eq!(
+        blake2b(&[], BLAKE2_256_BYTES),
+        [
+            0x0e, 0x57, 0x51, 0xc0, 0x26, 0xe5, 0x43, 0xb2, 0xe8, 0xab, 0x2e, 0xb0, 0x60, 0x99, 0xda, 0xa1, 0xd1,
+            0xe5, 0xdf, 0x47, 0x77, 0x8f, 0x77, 0x87, 0xfa, 0xab, 0x45, 0xcd, 0xf1, 0x2f, 0xe3, 0xa8,
+        ]
+    );
+
+    let parent: [u8; 32] = (0..32).collect::<Vec<u8>>().try_into().unwrap();
+    assert_eq!(
+        blake2b(&header(parent, 1000, [0x11; 32], [0x22; 32]), BLAKE2_256_BYTES),
+        [
+            0xef, 0xef, 0xaa, 0x74, 0x8d, 0x43, 0xbc, 0xd4, 0xe5, 0x74, 0x3c, 0xa5, 0x4a, 0x68, 0xf7, 0x37, 0x5b,
+            0x04, 0xf6, 0x40, 0xea, 0x6f, 0x9d, 0x6a, 0xd5, 0x43, 0x6b, 0xcd, 0x30, 0xd3, 0x8d, 0xd5,
+        ]
+    );
+
+    assert_eq!(compact(1), [0x04]);
+    assert_eq!(compact(1000), [0xa1, 0x0f]);
+}
+
+#[test]
+fn test_blake2_128_concat_u32_key() {
+    // a StorageMap<_, Blake2_128Concat, u32, _> key, SCALE encoded as 4 little endian bytes
+    let key = 1u32.to_le_bytes();
+    assert!(run(Hasher::Blake2_128Concat, &key, &blake2_128_concat(&key)));
+}
+
+#[test]
+fn test_system_account_key() {
+    let expected = [&SYSTEM_ACCOUNT[..], &ALICE_BLAKE2_128[..], &ALICE[..]].concat();
+    assert!(run(Hasher::AccountKey, &ALICE, &expected));
+}
+
+#[test]
+fn test_blake2_256_header() {
+    let parent: [u8; 32] = (0..32).collect::<Vec<u8>>().try_into().unwrap();
+    let header = header(parent, 1000, [0x11; 32], [0x22; 32]);
+
+    assert!(run(Hasher::Blake2_256, &header, &blake2b(&header, BLAKE2_256_BYTES)));
+}
+
+#[test]
+fn test_wrong_account_key() {
+    let mut expected = [&SYSTEM_ACCOUNT[..], &ALICE_BLAKE2_128[..], &ALICE[..]].concat();
+    expected[40] ^= 1;
+
+    assert!(!run(Hasher::AccountKey, &ALICE, &expected));
+}
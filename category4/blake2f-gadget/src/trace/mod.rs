@@ -0,0 +1,195 @@
+use crate::native::{self, IV, PRE_COMPUTED};
+use crate::util::{spread_even_u128_from_xor, spread_odd_u128_from_three_xor, spread_odd_u128_from_xor};
+use crate::MAX_ROUND;
+
+#[cfg(test)]
+mod test;
+
+// rotations of the first and second G half of a subround, for d and b
+const ROTATE_D: [u32; 2] = [32, 16];
+const ROTATE_B: [u32; 2] = [24, 63];
+
+/// One main round row: the new value of a word and the three cells next to it.
+///
+/// - v0-v3, `a += b + m`: the message word and the carry
+/// - v8-v11, `c += d`: 0 and the carry
+/// - v4-v7 and v12-v15, xor and rotate R: the spread even bits above and below
+///   2R and the spread odd bits of the xor
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Blake2fTraceRow {
+    pub val: u64,
+    pub cells: [u128; 3],
+}
+
+/// The witness of a BLAKE2f compression computed natively, row by row in the layout of
+/// the compression region. It only depends on the inputs, so traces of many compressions
+/// can be computed in parallel before any region is assigned.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Blake2fTrace {
+    /// v0-v15 after the scheduler
+    pub init_state: [u64; 16],
+    /// the spread odd bits of the xors setting v12-v14 in the scheduler
+    pub init_odd: [u128; 3],
+    /// the computed rounds, 4 subrounds of v0-v15 each
+    pub rounds: Vec<[[Blake2fTraceRow; 16]; 4]>,
+    /// h0-h7 and the spread odd bits of their triple xor
+    pub h: [u64; 8],
+    pub h_odd: [u128; 8],
+}
+
+impl Blake2fTrace {
+    /// The trace of F(h, m, t, f) with `rounds` rounds, `f` is the flag word xored into v14.
+    pub fn new(h: [u64; 8], m: [u64; 16], t: [u64; 2], f: u64, rounds: usize) -> Self {
+        Self::new_node(h, m, t, f, 0, rounds)
+    }
+
+    /// `new` with the last node word xored into v15.
+    pub fn new_node(h: [u64; 8], m: [u64; 16], t: [u64; 2], f: u64, last_node: u64, rounds: usize) -> Self {
+        Self::from_state(native::init_state(h, t, f, last_node), m, rounds)
+    }
+
+    /// The trace from v0-v15 as the scheduler leaves them.
+    pub fn from_state(init_state: [u64; 16], m: [u64; 16], rounds: usize) -> Self {
+        assert!(rounds <= MAX_ROUND);
+
+        let mut v = init_state;
+        let rounds = (0..rounds)
+            .map(|r| {
+                let mut sub_rounds = [[Blake2fTraceRow::default(); 16]; 4];
+                for (sub_round, rows) in sub_rounds.iter_mut().enumerate() {
+                    for j in 0..4 {
+                        Self::g(&mut v, rows, r, sub_round, j, &m);
+                    }
+                }
+                sub_rounds
+            })
+            .collect();
+
+        let mut h = [0; 8];
+        let mut h_odd = [0; 8];
+        for i in 0..8 {
+            (h[i], h_odd[i]) = Self::h_xor(init_state[i], v[i], v[i + 8]);
+        }
+
+        let init_odd = [0, 1, 2].map(|i| spread_odd_u128_from_xor(IV[4 + i], init_state[12 + i] ^ IV[4 + i]));
+
+        Self {
+            init_state,
+            init_odd,
+            rounds,
+            h,
+            h_odd,
+        }
+    }
+
+    /// The message word added to word i < 4 in a subround (from 0) of round r.
+    pub fn message_index(r: usize, sub_round: usize, i: usize) -> usize {
+        PRE_COMPUTED[r % 10][sub_round * 4 + i]
+    }
+
+    /// h ^ v_l ^ v_r and the spread odd bits of spread(h) + spread(v_l) + spread(v_r).
+    pub fn h_xor(h: u64, v_l: u64, v_r: u64) -> (u64, u128) {
+        (h ^ v_l ^ v_r, spread_odd_u128_from_three_xor(h, v_l, v_r))
+    }
+
+    /// v0-v15 after the computed rounds.
+    pub fn final_state(&self) -> [u64; 16] {
+        match self.rounds.last() {
+            Some(sub_rounds) => sub_rounds[3].map(|row| row.val),
+            None => self.init_state,
+        }
+    }
+
+    // the j-th G of a subround: the columns in the first two subrounds, the diagonals after
+    fn g(v: &mut [u64; 16], rows: &mut [Blake2fTraceRow; 16], r: usize, sub_round: usize, j: usize, m: &[u64; 16]) {
+        let shift = if sub_round < 2 { 0 } else { 1 };
+        let (a, b, c, d) = (j, 4 + (j + shift) % 4, 8 + (j + 2 * shift) % 4, 12 + (j + 3 * shift) % 4);
+
+        let m = m[Self::message_index(r, sub_round, j)];
+        let (sum, c0) = v[a].overflowing_add(m);
+        let (sum, c1) = sum.overflowing_add(v[b]);
+        v[a] = sum;
+        rows[a] = Blake2fTraceRow { val: sum, cells: [m as u128, c0 as u128 + c1 as u128, 0] };
+
+        rows[d] = Self::xor_and_rotate(v, d, a, ROTATE_D[sub_round % 2]);
+
+        let (sum, carry) = v[c].overflowing_add(v[d]);
+        v[c] = sum;
+        rows[c] = Blake2fTraceRow { val: sum, cells: [0, carry as u128, 0] };
+
+        rows[b] = Self::xor_and_rotate(v, b, c, ROTATE_B[sub_round % 2]);
+    }
+
+    fn xor_and_rotate(v: &mut [u64; 16], x: usize, y: usize, rotate: u32) -> Blake2fTraceRow {
+        let even = spread_even_u128_from_xor(v[x], v[y]);
+        let odd = spread_odd_u128_from_xor(v[x], v[y]);
+        v[x] = (v[x] ^ v[y]).rotate_right(rotate);
+
+        Blake2fTraceRow {
+            val: v[x],
+            cells: [even >> (2 * rotate), even & ((1 << (2 * rotate)) - 1), odd],
+        }
+    }
+
+    /// Little endian bytes: the initial state and its odd bits, the number of rounds, the rows
+    /// of every round and the final h.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.init_state.iter().for_each(|v| bytes.extend(v.to_le_bytes()));
+        self.init_odd.iter().for_each(|v| bytes.extend(v.to_le_bytes()));
+        bytes.push(self.rounds.len() as u8);
+        for row in self.rounds.iter().flatten().flatten() {
+            bytes.extend(row.val.to_le_bytes());
+            row.cells.iter().for_each(|cell| bytes.extend(cell.to_le_bytes()));
+        }
+        self.h.iter().for_each(|v| bytes.extend(v.to_le_bytes()));
+        self.h_odd.iter().for_each(|v| bytes.extend(v.to_le_bytes()));
+
+        bytes
+    }
+
+    /// The inverse of `to_bytes`, `None` if the bytes are not a trace of up to MAX_ROUND rounds.
+    pub fn from_bytes(mut bytes: &[u8]) -> Option<Self> {
+        fn take<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+            if bytes.len() < N {
+                return None;
+            }
+            let (head, rest) = bytes.split_at(N);
+            *bytes = rest;
+            head.try_into().ok()
+        }
+
+        let mut trace = Self::default();
+        for v in trace.init_state.iter_mut() {
+            *v = u64::from_le_bytes(take(&mut bytes)?);
+        }
+        for v in trace.init_odd.iter_mut() {
+            *v = u128::from_le_bytes(take(&mut bytes)?);
+        }
+
+        let [rounds] = take::<1>(&mut bytes)?;
+        if rounds as usize > MAX_ROUND {
+            return None;
+        }
+
+        for _ in 0..rounds {
+            let mut sub_rounds = [[Blake2fTraceRow::default(); 16]; 4];
+            for row in sub_rounds.iter_mut().flatten() {
+                row.val = u64::from_le_bytes(take(&mut bytes)?);
+                for cell in row.cells.iter_mut() {
+                    *cell = u128::from_le_bytes(take(&mut bytes)?);
+                }
+            }
+            trace.rounds.push(sub_rounds);
+        }
+
+        for v in trace.h.iter_mut() {
+            *v = u64::from_le_bytes(take(&mut bytes)?);
+        }
+        for v in trace.h_odd.iter_mut() {
+            *v = u128::from_le_bytes(take(&mut bytes)?);
+        }
+
+        bytes.is_empty().then_some(trace)
+    }
+}
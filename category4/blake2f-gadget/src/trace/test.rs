@@ -0,0 +1,99 @@
+use crate::native::{compress, IV};
+use crate::util::spread_u64;
+use crate::MAX_ROUND;
+
+use super::{Blake2fTrace, ROTATE_B, ROTATE_D};
+
+fn random_trace(rounds: usize) -> Blake2fTrace {
+    let h = [(); 8].map(|_| fastrand::u64(..));
+    let m = [(); 16].map(|_| fastrand::u64(..));
+    let t = [fastrand::u64(..), fastrand::u64(..)];
+    let f = if fastrand::bool() { u64::MAX } else { 0 };
+
+    Blake2fTrace::new(h, m, t, f, rounds)
+}
+
+#[test]
+fn test_trace_matches_compress() {
+    for _ in 0..16 {
+        let h = [(); 8].map(|_| fastrand::u64(..));
+        let m = [(); 16].map(|_| fastrand::u64(..));
+        let t = fastrand::u128(..);
+        let f = fastrand::bool();
+
+        let trace = Blake2fTrace::new(h, m, [t as u64, (t >> 64) as u64], if f { u64::MAX } else { 0 }, MAX_ROUND);
+
+        let mut expected = h;
+        compress(&mut expected, &m, t, f, false);
+        assert_eq!(trace.h, expected);
+    }
+}
+
+#[test]
+fn test_trace_round_core() {
+    let vs = [5577006791947779410, 15352856648520921629, 0, 9828766684487745566, 894385949183117216, 4751997750760398084, 11199607447739267382, 12156940908066221323, 11833901312327420776, 6263450610539110790, 1874068156324778273, 14486903973548550719, 11926873763676642186, 6941261091797652072, 17204678798284737396, 4831389563158288344];
+    let m = [8674665223082153551, 13260572831089785859, 6334824724549167320, 10667007354186551956, 11998794077335055257, 7504504064263669287, 3510942875414458836, 4324745483838182873, 11926759511765359899, 11239168150708129139, 3328451335138149956, 7955079406183515637, 2740103009342231109, 1905388747193831650, 15649472107743074779, 261049867304784443];
+    let expected_new_vs = [1167885585688411048, 9132935703036027322, 13125040812557909567, 6112539474157580159, 3133968705664923835, 11042207055073639687, 10955020035800245616, 2831786654526290545, 1006267388626854281, 939610873840029904, 1829487957615954457, 18058344788569079972, 6582351318386615796, 4798940882642563951, 15738503566863505976, 14605998900807323338];
+
+    assert_eq!(Blake2fTrace::from_state(vs, m, 12).final_state(), expected_new_vs);
+}
+
+// every row satisfies its gate natively, with the word 16 rows above and the operand
+#[test]
+fn test_trace_rows() {
+    for rounds in [0, 1, MAX_ROUND] {
+        let trace = random_trace(rounds);
+        assert_eq!(trace.rounds.len(), rounds);
+
+        // v12-v14 = IV ^ (t0, t1, f) in the scheduler
+        for (i, odd) in trace.init_odd.iter().enumerate() {
+            let (iv, v) = (IV[4 + i], trace.init_state[12 + i]);
+            assert_eq!(spread_u64(iv) + spread_u64(iv ^ v), spread_u64(v) + 2 * odd);
+        }
+
+        let mut prev = trace.init_state;
+        for sub_rounds in trace.rounds.iter() {
+            for (sub_round, rows) in sub_rounds.iter().enumerate() {
+                let cur = rows.map(|row| row.val);
+                for (i, row) in rows.iter().enumerate() {
+                    // the operand: b for a, c for b, d for c, a for d, shifted in the diagonal step
+                    let shift = if sub_round < 2 { 0 } else if i % 4 == 3 { -3 } else { 1 };
+                    let base = if i < 4 || i >= 12 { -12 } else { 4 };
+                    let operand = i as i32 + base + shift;
+                    let rhs = if operand < 0 { prev[(operand + 16) as usize] } else { cur[operand as usize] };
+
+                    match i / 4 {
+                        0 | 2 => {
+                            let sum = prev[i] as u128 + rhs as u128 + row.cells[0];
+                            assert_eq!(row.val as u128 + (row.cells[1] << 64), sum);
+                        }
+                        _ => {
+                            let rotate = if i < 8 { ROTATE_B[sub_round % 2] } else { ROTATE_D[sub_round % 2] };
+                            let [even_0, even_1, odd] = row.cells;
+                            assert_eq!((even_0 << (2 * rotate)) + even_1 + 2 * odd, spread_u64(prev[i]) + spread_u64(rhs));
+                            assert_eq!((even_1 << (128 - 2 * rotate)) + even_0, spread_u64(row.val));
+                        }
+                    }
+                }
+                prev = cur;
+            }
+        }
+        assert_eq!(prev, trace.final_state());
+    }
+}
+
+#[test]
+fn test_trace_bytes() {
+    for rounds in [0, 5, MAX_ROUND] {
+        let trace = random_trace(rounds);
+        let bytes = trace.to_bytes();
+
+        assert_eq!(Blake2fTrace::from_bytes(&bytes), Some(trace));
+        assert_eq!(Blake2fTrace::from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Blake2fTrace::from_bytes(&[bytes.as_slice(), &[0]].concat()), None);
+    }
+
+    let mut bytes = random_trace(0).to_bytes();
+    bytes[16 * 8 + 3 * 16] = MAX_ROUND as u8 + 1;
+    assert_eq!(Blake2fTrace::from_bytes(&bytes), None);
+}
@@ -0,0 +1,495 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Cell, Chip, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+use halo2curves::{group::ff::{Field, PrimeField}, FieldExt, bn256::{self, Fr}};
+
+use crate::byte_table::{ByteTableChip, ByteTableConfig};
+use crate::blake2b::{initial_h_personal, BLOCK_BYTES, BLOCK_WORDS};
+use crate::{Blake2fChip, Blake2fConfig, Blake2fInstructions, Blake2fIv, Blake2fState, MAX_ROUND};
+
+#[cfg(test)]
+mod test;
+
+// halo2_proofs::transcript::Blake2bWrite
+pub const PERSONAL: &[u8; 16] = b"Halo2-Transcript";
+pub const PREFIX_CHALLENGE: u8 = 0;
+pub const PREFIX_POINT: u8 = 1;
+pub const PREFIX_SCALAR: u8 = 2;
+
+// 512-bit output, reduced into the field like `Challenge255`
+const CHALLENGE_BYTES: usize = 64;
+// field elements and point coordinates, little endian
+pub const REPR_BYTES: usize = 32;
+
+// 8 limbs and the composed value
+pub(crate) const ROWS_PER_COMPOSE: usize = 9;
+
+pub struct Blake2bTranscript<F: FieldExt, CS: TranscriptInstructions<F>> {
+    chip: CS,
+    state: CS::State,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, TranscriptChip: TranscriptInstructions<F>> Blake2bTranscript<F, TranscriptChip> {
+    pub fn new(chip: TranscriptChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let iv = chip.initialize(&mut layouter)?;
+        let state = chip.init_state(&mut layouter, &iv)?;
+
+        Ok(Self {
+            chip,
+            state,
+            _marker: PhantomData,
+        })
+    }
+
+    // The coordinates as `to_repr` bytes. The identity, (0, 0), has no encoding and is rejected,
+    // the point is not checked to be on the curve, whose coordinates are not native field elements.
+    pub fn common_point(
+        &mut self,
+        layouter: &mut impl Layouter<F>,
+        x: &[AssignedCell<F, F>; REPR_BYTES],
+        y: &[AssignedCell<F, F>; REPR_BYTES],
+    ) -> Result<(), Error> {
+        self.chip.assert_not_identity(layouter, x, y)?;
+
+        self.absorb_prefix(layouter, PREFIX_POINT)?;
+        self.chip.absorb(layouter, &mut self.state, x)?;
+        self.chip.absorb(layouter, &mut self.state, y)
+    }
+
+    pub fn common_scalar(
+        &mut self,
+        layouter: &mut impl Layouter<F>,
+        scalar: &AssignedCell<F, F>,
+    ) -> Result<(), Error> {
+        let bytes = self.chip.scalar_bytes(layouter, scalar)?;
+
+        self.absorb_prefix(layouter, PREFIX_SCALAR)?;
+        self.chip.absorb(layouter, &mut self.state, &bytes)
+    }
+
+    pub fn squeeze_challenge(&mut self, layouter: &mut impl Layouter<F>) -> Result<AssignedCell<F, F>, Error> {
+        self.absorb_prefix(layouter, PREFIX_CHALLENGE)?;
+        self.chip.squeeze(layouter, &self.state)
+    }
+
+    fn absorb_prefix(&mut self, layouter: &mut impl Layouter<F>, prefix: u8) -> Result<(), Error> {
+        let prefix = self.chip.assign_constant_byte(layouter, prefix)?;
+        self.chip.absorb(layouter, &mut self.state, &[prefix])
+    }
+}
+
+pub trait TranscriptInstructions<F: FieldExt>: Chip<F> {
+    type State: Clone + Debug;
+    // the cells every compression copies, kept in the state
+    type Iv: Clone + Debug;
+
+    fn initialize(&self, layouter: &mut impl Layouter<F>) -> Result<Self::Iv, Error>;
+
+    // BLAKE2b-512 personalized with `PERSONAL`, nothing absorbed
+    fn init_state(&self, layouter: &mut impl Layouter<F>, iv: &Self::Iv) -> Result<Self::State, Error>;
+
+    // range checked bytes
+    fn assign_bytes(&self, layouter: &mut impl Layouter<F>, bytes: &[Value<u8>]) -> Result<Vec<AssignedCell<F, F>>, Error>;
+
+    fn assign_constant_byte(&self, layouter: &mut impl Layouter<F>, byte: u8) -> Result<AssignedCell<F, F>, Error>;
+
+    // `to_repr` bytes of a scalar
+    fn scalar_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        scalar: &AssignedCell<F, F>,
+    ) -> Result<[AssignedCell<F, F>; REPR_BYTES], Error>;
+
+    // some byte of the coordinates is not zero
+    fn assert_not_identity(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &[AssignedCell<F, F>; REPR_BYTES],
+        y: &[AssignedCell<F, F>; REPR_BYTES],
+    ) -> Result<(), Error>;
+
+    fn absorb(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &mut Self::State,
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<(), Error>;
+
+    // finalizes a copy of the state, the 512-bit digest taken little endian modulo the field
+    fn squeeze(&self, layouter: &mut impl Layouter<F>, state: &Self::State) -> Result<AssignedCell<F, F>, Error>;
+}
+
+// Compressed blocks in `h`, the pending bytes in `buffer`. Like BLAKE2b, a full buffer is only
+// compressed once more bytes arrive, the last block has to be compressed with `f` set.
+#[derive(Clone, Debug)]
+pub struct TranscriptState {
+    iv: Blake2fIv,
+    h: Blake2fState,
+    buffer: Vec<AssignedCell<Fr, Fr>>,
+    len: u128,
+}
+
+#[derive(Clone, Debug)]
+pub struct TranscriptConfig {
+    blake2f: Blake2fConfig,
+    bytes: Column<Advice>,
+    byte_table: ByteTableConfig,
+    s_word: Selector,
+    s_wide: Selector,
+    s_canonical: Selector,
+    s_sum: Selector,
+    s_nonzero: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct TranscriptChip {
+    config: TranscriptConfig,
+    blake2f: Blake2fChip,
+    _marker: PhantomData<bn256::Fr>,
+}
+
+impl Chip<bn256::Fr> for TranscriptChip {
+    type Config = TranscriptConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+// rows 0..8 limbs from the least significant, row 8 their composition
+pub(crate) fn compose_gate(
+    meta: &mut ConstraintSystem<bn256::Fr>,
+    name: &'static str,
+    selector: Selector,
+    column: Column<Advice>,
+    radix: Fr,
+) {
+    meta.create_gate(name, |meta| {
+        let s = meta.query_selector(selector);
+
+        let composed = meta.query_advice(column, Rotation(ROWS_PER_COMPOSE as i32 - 1));
+        let sum = (0..ROWS_PER_COMPOSE - 1)
+            .rev()
+            .map(|i| meta.query_advice(column, Rotation(i as i32)))
+            .fold(Expression::Constant(Fr::zero()), |acc, limb| acc * Expression::Constant(radix) + limb);
+
+        Constraints::with_selector(s, vec![composed - sum])
+    });
+}
+
+// copies the limbs and assigns their composition
+pub(crate) fn compose(
+    region: &mut Region<'_, bn256::Fr>,
+    column: Column<Advice>,
+    offset: usize,
+    selector: Selector,
+    radix: Fr,
+    limbs: &[(Value<Fr>, Cell)],
+) -> Result<AssignedCell<Fr, Fr>, Error> {
+    assert_eq!(limbs.len(), ROWS_PER_COMPOSE - 1);
+
+    selector.enable(region, offset)?;
+
+    for (i, (value, cell)) in limbs.iter().enumerate() {
+        let limb = region.assign_advice(|| "copy limb", column, offset + i, || *value)?;
+        region.constrain_equal(limb.cell(), *cell)?;
+    }
+
+    let composed = limbs
+        .iter()
+        .rev()
+        .fold(Value::known(Fr::zero()), |acc, (limb, _)| acc * Value::known(radix) + limb);
+
+    region.assign_advice(|| "assign composed", column, offset + ROWS_PER_COMPOSE - 1, || composed)
+}
+
+impl TranscriptChip {
+    pub fn construct(config: <Self as Chip<bn256::Fr>>::Config) -> Self {
+        Self {
+            blake2f: Blake2fChip::construct(config.blake2f.clone()),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<bn256::Fr>,
+    ) -> <Self as Chip<bn256::Fr>>::Config {
+        let blake2f = Blake2fChip::configure(meta);
+
+        let bytes = meta.advice_column();
+        meta.enable_equality(bytes);
+
+        let byte_table = ByteTableChip::configure(meta, bytes);
+
+        // 8 bytes to a message word, 8 words to a wide field element
+        let s_word = meta.selector();
+        let s_wide = meta.selector();
+
+        compose_gate(meta, "compose word", s_word, bytes, Fr::from(1 << 8));
+        compose_gate(meta, "compose wide", s_wide, bytes, Fr::from_u128(1 << 64));
+
+        // one byte of `(r - 1) - x`: borrow in, x, the modulus byte, the difference and the borrow
+        // out on consecutive rows. The difference is a byte and the last borrow is zero iff x < r.
+        let s_canonical = meta.selector();
+
+        meta.create_gate("canonical byte", |meta| {
+            let s = meta.query_selector(s_canonical);
+
+            let borrow_in = meta.query_advice(bytes, Rotation::prev());
+            let x = meta.query_advice(bytes, Rotation::cur());
+            let modulus = meta.query_advice(bytes, Rotation::next());
+            let diff = meta.query_advice(bytes, Rotation(2));
+            let borrow = meta.query_advice(bytes, Rotation(3));
+
+            let one = Expression::Constant(Fr::one());
+            let radix = Expression::Constant(Fr::from(1 << 8));
+
+            Constraints::with_selector(
+                s,
+                vec![
+                    ("borrow is boolean", borrow.clone() * (one - borrow.clone())),
+                    ("difference", modulus - x - borrow_in + radix * borrow - diff),
+                ],
+            )
+        });
+
+        // the bytes of a point summed 8 at a time, at most 64 * 255, and the inverse of the sum
+        // on the row below it
+        let s_sum = meta.selector();
+        let s_nonzero = meta.selector();
+
+        compose_gate(meta, "sum bytes", s_sum, bytes, Fr::one());
+
+        meta.create_gate("nonzero", |meta| {
+            let s = meta.query_selector(s_nonzero);
+
+            let sum = meta.query_advice(bytes, Rotation::cur());
+            let inverse = meta.query_advice(bytes, Rotation::next());
+
+            Constraints::with_selector(s, vec![sum * inverse - Expression::Constant(Fr::one())])
+        });
+
+        TranscriptConfig { blake2f, bytes, byte_table, s_word, s_wide, s_canonical, s_sum, s_nonzero }
+    }
+
+    pub fn load(
+        config: &TranscriptConfig,
+        layouter: &mut impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        ByteTableChip::load(&config.byte_table, layouter)
+    }
+
+    // bytes -> 4 words -> scalar, with the bytes below the modulus
+    fn decompose_scalar(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        scalar: &AssignedCell<Fr, Fr>,
+        repr: Value<[u8; REPR_BYTES]>,
+    ) -> Result<[AssignedCell<Fr, Fr>; REPR_BYTES], Error> {
+        let bytes = (0..REPR_BYTES).map(|i| repr.map(|repr| repr[i])).collect::<Vec<_>>();
+
+        let bytes = self.assign_bytes(layouter, &bytes)?;
+        let zero = self.assign_constant_byte(layouter, 0)?;
+
+        layouter.assign_region(|| "transcript scalar", |mut region| {
+            let mut words = Vec::with_capacity(ROWS_PER_COMPOSE - 1);
+
+            for (i, chunk) in bytes.chunks(8).enumerate() {
+                let limbs: Vec<_> = chunk.iter().map(|byte| (byte.value().copied(), byte.cell())).collect();
+                let word = compose(&mut region, self.config.bytes, i * ROWS_PER_COMPOSE, self.config.s_word, Fr::from(1 << 8), &limbs)?;
+
+                words.push((word.value().copied(), word.cell()));
+            }
+
+            words.resize(ROWS_PER_COMPOSE - 1, (Value::known(Fr::zero()), zero.cell()));
+
+            let offset = REPR_BYTES / 8 * ROWS_PER_COMPOSE;
+            let composed = compose(&mut region, self.config.bytes, offset, self.config.s_wide, Fr::from_u128(1 << 64), &words)?;
+            region.constrain_equal(composed.cell(), scalar.cell())?;
+
+            // (r - 1) - bytes from the least significant byte, without a final borrow
+            let offset = offset + ROWS_PER_COMPOSE;
+            let modulus = (-Fr::one()).to_repr();
+
+            zero.copy_advice(|| "copy borrow", &mut region, self.config.bytes, offset)?;
+
+            let mut borrow = None;
+            let mut borrow_value = Value::known(0u8);
+
+            for (i, byte) in bytes.iter().enumerate() {
+                let row = offset + 1 + 4 * i;
+                self.config.s_canonical.enable(&mut region, row)?;
+
+                byte.copy_advice(|| "copy byte", &mut region, self.config.bytes, row)?;
+                region.assign_advice_from_constant(|| "modulus byte", self.config.bytes, row + 1, Fr::from(modulus[i] as u64))?;
+
+                let diff_borrow = repr.zip(borrow_value).map(|(repr, borrow)| {
+                    let diff = modulus[i] as i32 - repr[i] as i32 - borrow as i32;
+                    if diff < 0 { ((diff + (1 << 8)) as u8, 1) } else { (diff as u8, 0) }
+                });
+
+                self.config.byte_table.enable(&mut region, row + 2)?;
+                region.assign_advice(|| "difference", self.config.bytes, row + 2, || diff_borrow.map(|(diff, _)| Fr::from(diff as u64)))?;
+
+                borrow_value = diff_borrow.map(|(_, borrow)| borrow);
+                borrow = Some(region.assign_advice(|| "borrow", self.config.bytes, row + 3, || borrow_value.map(|b| Fr::from(b as u64)))?);
+            }
+
+            region.constrain_equal(borrow.unwrap().cell(), zero.cell())
+        })?;
+
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn compress_buffer(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        state: &TranscriptState,
+        last: bool,
+    ) -> Result<Blake2fState, Error> {
+        let zero = self.assign_constant_byte(layouter, 0)?;
+
+        let m = layouter.assign_region(|| "transcript block", |mut region| {
+            let mut m = Vec::with_capacity(BLOCK_WORDS);
+
+            for i in 0..BLOCK_WORDS {
+                let limbs: Vec<_> = (0..8)
+                    .map(|j| state.buffer.get(i * 8 + j).unwrap_or(&zero))
+                    .map(|byte| (byte.value().copied(), byte.cell()))
+                    .collect();
+
+                m.push(compose(&mut region, self.config.bytes, i * ROWS_PER_COMPOSE, self.config.s_word, Fr::from(1 << 8), &limbs)?);
+            }
+
+            Ok(m)
+        })?;
+
+        let t = [state.len as u64, (state.len >> 64) as u64];
+        self.blake2f.compress_assigned(layouter, &state.iv, &state.h, m.try_into().unwrap(), t, last, MAX_ROUND as u64)
+    }
+}
+
+impl TranscriptInstructions<bn256::Fr> for TranscriptChip {
+    type State = TranscriptState;
+    type Iv = Blake2fIv;
+
+    fn initialize(&self, layouter: &mut impl Layouter<bn256::Fr>) -> Result<Blake2fIv, Error> {
+        let iv = self.blake2f.initialize(layouter)?;
+        Self::load(&self.config, layouter)?;
+        Ok(iv)
+    }
+
+    fn init_state(&self, layouter: &mut impl Layouter<bn256::Fr>, iv: &Blake2fIv) -> Result<Self::State, Error> {
+        let h = self.blake2f.initial_state(layouter, initial_h_personal(CHALLENGE_BYTES, PERSONAL))?;
+
+        Ok(TranscriptState { iv: iv.clone(), h, buffer: vec![], len: 0 })
+    }
+
+    fn assign_bytes(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        bytes: &[Value<u8>],
+    ) -> Result<Vec<AssignedCell<Fr, Fr>>, Error> {
+        layouter.assign_region(|| "transcript bytes", |mut region| {
+            bytes
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| {
+                    self.config.byte_table.enable(&mut region, i)?;
+                    region.assign_advice(|| "assign byte", self.config.bytes, i, || byte.map(|b| Fr::from(b as u64)))
+                })
+                .collect()
+        })
+    }
+
+    fn assign_constant_byte(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        byte: u8,
+    ) -> Result<AssignedCell<Fr, Fr>, Error> {
+        layouter.assign_region(|| "transcript constant", |mut region| {
+            region.assign_advice_from_constant(|| "assign constant byte", self.config.bytes, 0, Fr::from(byte as u64))
+        })
+    }
+
+    fn scalar_bytes(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        scalar: &AssignedCell<Fr, Fr>,
+    ) -> Result<[AssignedCell<Fr, Fr>; REPR_BYTES], Error> {
+        let repr = scalar.value().map(|scalar| scalar.to_repr());
+        self.decompose_scalar(layouter, scalar, repr)
+    }
+
+    fn assert_not_identity(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        x: &[AssignedCell<Fr, Fr>; REPR_BYTES],
+        y: &[AssignedCell<Fr, Fr>; REPR_BYTES],
+    ) -> Result<(), Error> {
+        layouter.assign_region(|| "transcript point", |mut region| {
+            let bytes: Vec<_> = x.iter().chain(y.iter()).map(|byte| (byte.value().copied(), byte.cell())).collect();
+
+            let mut sums = Vec::with_capacity(ROWS_PER_COMPOSE - 1);
+            for (i, chunk) in bytes.chunks(ROWS_PER_COMPOSE - 1).enumerate() {
+                let sum = compose(&mut region, self.config.bytes, i * ROWS_PER_COMPOSE, self.config.s_sum, Fr::one(), chunk)?;
+                sums.push((sum.value().copied(), sum.cell()));
+            }
+
+            let offset = sums.len() * ROWS_PER_COMPOSE;
+            let sum = compose(&mut region, self.config.bytes, offset, self.config.s_sum, Fr::one(), &sums)?;
+
+            // the identity gets a zero inverse, which the gate rejects
+            let row = offset + ROWS_PER_COMPOSE - 1;
+            self.config.s_nonzero.enable(&mut region, row)?;
+            let inverse = sum.value().map(|sum| sum.invert().unwrap_or(Fr::zero()));
+            region.assign_advice(|| "inverse of the byte sum", self.config.bytes, row + 1, || inverse)?;
+
+            Ok(())
+        })
+    }
+
+    fn absorb(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        state: &mut Self::State,
+        bytes: &[AssignedCell<Fr, Fr>],
+    ) -> Result<(), Error> {
+        for byte in bytes {
+            if state.buffer.len() == BLOCK_BYTES {
+                state.h = self.compress_buffer(layouter, state, false)?;
+                state.buffer.clear();
+            }
+
+            state.buffer.push(byte.clone());
+            state.len += 1;
+        }
+
+        Ok(())
+    }
+
+    fn squeeze(
+        &self,
+        layouter: &mut impl Layouter<bn256::Fr>,
+        state: &Self::State,
+    ) -> Result<AssignedCell<Fr, Fr>, Error> {
+        let digest = self.compress_buffer(layouter, state, true)?;
+
+        let words: Vec<_> = digest.value().into_iter().map(|word| word.map(Fr::from)).zip(digest.cells()).collect();
+
+        layouter.assign_region(|| "transcript challenge", |mut region| {
+            compose(&mut region, self.config.bytes, 0, self.config.s_wide, Fr::from_u128(1 << 64), &words)
+        })
+    }
+}
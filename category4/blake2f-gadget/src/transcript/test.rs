@@ -0,0 +1,211 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2curves::{group::ff::PrimeField, FieldExt, bn256::{self, Fr}};
+
+use super::{
+    Blake2bTranscript, TranscriptChip, TranscriptConfig, TranscriptInstructions, CHALLENGE_BYTES, PERSONAL,
+    PREFIX_CHALLENGE, PREFIX_POINT, PREFIX_SCALAR, REPR_BYTES,
+};
+use crate::blake2b::initial_h_personal;
+use crate::native::hash;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Point([u8; REPR_BYTES], [u8; REPR_BYTES]),
+    Scalar(Fr),
+    // a scalar with the given encoding
+    ScalarRepr(Fr, [u8; REPR_BYTES]),
+    Challenge,
+}
+
+// native Blake2bWrite, returns the challenges
+fn replay(ops: &[Op]) -> Vec<Fr> {
+    let mut bytes = vec![];
+    let mut challenges = vec![];
+
+    for op in ops {
+        match op {
+            Op::Point(x, y) => {
+                bytes.push(PREFIX_POINT);
+                bytes.extend(x);
+                bytes.extend(y);
+            }
+            Op::Scalar(scalar) => {
+                bytes.push(PREFIX_SCALAR);
+                bytes.extend(scalar.to_repr());
+            }
+            Op::ScalarRepr(_, repr) => {
+                bytes.push(PREFIX_SCALAR);
+                bytes.extend(repr);
+            }
+            Op::Challenge => {
+                bytes.push(PREFIX_CHALLENGE);
+                let digest = hash(initial_h_personal(CHALLENGE_BYTES, PERSONAL), &bytes, CHALLENGE_BYTES);
+                challenges.push(Fr::from_bytes_wide(&digest.try_into().unwrap()));
+            }
+        }
+    }
+
+    challenges
+}
+
+#[derive(Default)]
+struct MyCircuit {
+    ops: Vec<Op>,
+}
+
+impl Circuit<bn256::Fr> for MyCircuit {
+    type Config = (TranscriptConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            ops: self.ops.clone(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<bn256::Fr>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (TranscriptChip::configure(meta), instance)
+    }
+
+    fn synthesize(
+        &self,
+        (config, instance): Self::Config,
+        mut layouter: impl Layouter<bn256::Fr>,
+    ) -> Result<(), Error> {
+        let chip = TranscriptChip::construct(config);
+        let mut transcript = Blake2bTranscript::new(chip.clone(), layouter.namespace(|| "load tables"))?;
+
+        let mut challenges = 0;
+        for op in self.ops.iter() {
+            match op {
+                Op::Point(x, y) => {
+                    let x = chip.assign_bytes(&mut layouter, &x.map(Value::known))?;
+                    let y = chip.assign_bytes(&mut layouter, &y.map(Value::known))?;
+
+                    transcript.common_point(&mut layouter, &x.try_into().unwrap(), &y.try_into().unwrap())?;
+                }
+                Op::Scalar(scalar) => {
+                    let scalar = layouter.assign_region(|| "scalar", |mut region| {
+                        region.assign_advice(|| "scalar", chip.config.bytes, 0, || Value::known(*scalar))
+                    })?;
+
+                    transcript.common_scalar(&mut layouter, &scalar)?;
+                }
+                Op::ScalarRepr(scalar, repr) => {
+                    let scalar = layouter.assign_region(|| "scalar", |mut region| {
+                        region.assign_advice(|| "scalar", chip.config.bytes, 0, || Value::known(*scalar))
+                    })?;
+
+                    let bytes = chip.decompose_scalar(&mut layouter, &scalar, Value::known(*repr))?;
+                    transcript.absorb_prefix(&mut layouter, PREFIX_SCALAR)?;
+                    transcript.chip.absorb(&mut layouter, &mut transcript.state, &bytes)?;
+                }
+                Op::Challenge => {
+                    let challenge = transcript.squeeze_challenge(&mut layouter)?;
+                    layouter.constrain_instance(challenge.cell(), instance, challenges)?;
+                    challenges += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn ops() -> Vec<Op> {
+    let mut rng = fastrand::Rng::with_seed(7);
+    let mut bytes = || [0; REPR_BYTES].map(|_| rng.u8(..));
+
+    // 3 points cross the first block
+    let mut ops = vec![];
+    for _ in 0..3 {
+        ops.push(Op::Point(bytes(), bytes()));
+    }
+    ops.push(Op::Challenge);
+    ops.push(Op::Scalar(Fr::from(0x1234)));
+    ops.push(Op::Scalar(-Fr::one()));
+    ops.push(Op::Challenge);
+    ops.push(Op::Challenge);
+
+    ops
+}
+
+fn run(ops: Vec<Op>, challenges: Vec<Fr>) -> bool {
+    let circuit = MyCircuit { ops };
+
+    let prover = match MockProver::<bn256::Fr>::run(17, &circuit, vec![challenges]) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify().is_ok()
+}
+
+#[test]
+fn test_transcript() {
+    let ops = ops();
+    let challenges = replay(&ops);
+
+    assert!(run(ops, challenges));
+}
+
+#[test]
+fn test_transcript_wrong_challenge() {
+    let ops = ops();
+    let mut challenges = replay(&ops);
+    challenges[1] += Fr::one();
+
+    assert!(!run(ops, challenges));
+}
+
+// little endian `scalar + r`, which still fits in 256 bits for a small scalar
+fn non_canonical(scalar: Fr) -> [u8; REPR_BYTES] {
+    let mut repr = scalar.to_repr();
+    let mut carry = 1u16;
+
+    for (byte, modulus) in repr.iter_mut().zip((-Fr::one()).to_repr()) {
+        let sum = *byte as u16 + modulus as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    assert_eq!(carry, 0);
+
+    repr
+}
+
+#[test]
+fn test_transcript_scalar_repr() {
+    let scalar = Fr::from(0x1234);
+
+    let ops = vec![Op::ScalarRepr(scalar, scalar.to_repr()), Op::Challenge];
+    let challenges = replay(&ops);
+    assert!(run(ops, challenges));
+
+    let ops = vec![Op::ScalarRepr(-Fr::one(), (-Fr::one()).to_repr()), Op::Challenge];
+    let challenges = replay(&ops);
+    assert!(run(ops, challenges));
+}
+
+#[test]
+fn test_transcript_non_canonical_scalar() {
+    let scalar = Fr::from(0x1234);
+    let ops = vec![Op::ScalarRepr(scalar, non_canonical(scalar)), Op::Challenge];
+    let challenges = replay(&ops);
+
+    assert!(!run(ops, challenges));
+}
+
+// the identity has no encoding, `Blake2bWrite::common_point` fails on it
+#[test]
+fn test_transcript_identity_point() {
+    let ops = vec![Op::Point([0; REPR_BYTES], [0; REPR_BYTES]), Op::Challenge];
+    let challenges = replay(&ops);
+
+    assert!(!run(ops, challenges));
+}
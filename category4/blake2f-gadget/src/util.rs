@@ -28,7 +28,11 @@ pub fn i2lebsp<const NUM_BITS: usize>(int: u64) -> [bool; NUM_BITS] {
     gen_const_array(|mask: usize| (int & (1 << mask)) != 0)
 }
 
-// TODO: add test
+/// The low `NUM_BITS` bits of a u128 in little-endian order.
+pub fn u1282lebsp_n<const NUM_BITS: usize>(int: u128) -> [bool; NUM_BITS] {
+    assert!(NUM_BITS <= 128);
+    std::array::from_fn(|i| (int >> i) & 1 == 1)
+}
 
 pub fn u1282lebsp(v: u128) -> [bool; 128] {
     let mut out = [false; 128];
@@ -63,104 +67,197 @@ pub fn arb_lebs2u128(bits: &[bool]) -> u128 {
         .fold(0u128, |acc, (i, b)| acc + if *b { 1 << i } else { 0 })
 }
 
+/// Interleaves the bits of `v` with zeros, bit i of `v` becomes bit 2i of the result.
+pub fn spread_u64(v: u64) -> u128 {
+    let mut x = v as u128;
+    x = (x | (x << 32)) & 0x0000_0000_ffff_ffff_0000_0000_ffff_ffff;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff_0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff_00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f_0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333_3333_3333_3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555_5555_5555_5555_5555_5555_5555;
+    x
+}
+
+/// The inverse of [`spread_u64`] on the even bits: bit 2i of `v` becomes bit i.
+pub fn even_u128(v: u128) -> u64 {
+    let mut x = v & 0x5555_5555_5555_5555_5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333_3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f_0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff_00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff_0000_ffff_0000_ffff;
+    x = (x | (x >> 16)) & 0x0000_0000_ffff_ffff_0000_0000_ffff_ffff;
+    x = (x | (x >> 32)) & 0xffff_ffff_ffff_ffff;
+    x as u64
+}
+
+/// Bit 2i + 1 of `v` becomes bit i.
+pub fn odd_u128(v: u128) -> u64 {
+    even_u128(v >> 1)
+}
+
 /// Helper function that interleaves a little-endian bit-array with zeros
 /// in the odd indices. That is, it takes the array
 ///         [b_0, b_1, ..., b_n]
 /// to
 ///         [b_0, 0, b_1, 0, ..., b_n, 0].
-/// Panics if bit-array is longer than 16 bits.
+/// Panics if bit-array is longer than 64 bits.
 pub fn spread_bits<const DENSE: usize, const SPREAD: usize>(
     bits: impl Into<[bool; DENSE]>,
 ) -> [bool; SPREAD] {
     assert_eq!(DENSE * 2, SPREAD);
-    // assert!(DENSE <= 16);
+    assert!(DENSE <= 64);
 
     let bits: [bool; DENSE] = bits.into();
-    let mut spread = [false; SPREAD];
-
-    for (idx, bit) in bits.iter().enumerate() {
-        spread[idx * 2] = *bit;
-    }
+    let spread = spread_u64(arb_lebs2u128(&bits) as u64);
 
-    spread
+    std::array::from_fn(|idx| (spread >> idx) & 1 == 1)
 }
 
 /// Returns even bits in a bit-array
 pub fn even_bits<const LEN: usize, const HALF: usize>(bits: [bool; LEN]) -> [bool; HALF] {
     assert_eq!(LEN % 2, 0);
-    let mut even_bits = [false; HALF];
-    for idx in 0..HALF {
-        even_bits[idx] = bits[idx * 2]
-    }
-    even_bits
+    assert_eq!(LEN, HALF * 2);
+    let even = even_u128(arb_lebs2u128(&bits));
+
+    std::array::from_fn(|idx| (even >> idx) & 1 == 1)
 }
 
 /// Returns odd bits in a bit-array
 pub fn odd_bits<const LEN: usize, const HALF: usize>(bits: [bool; LEN]) -> [bool; HALF] {
     assert_eq!(LEN % 2, 0);
-    let mut odd_bits = [false; HALF];
-    for idx in 0..HALF {
-        odd_bits[idx] = bits[idx * 2 + 1]
-    }
-    odd_bits
+    assert_eq!(LEN, HALF * 2);
+    let odd = odd_u128(arb_lebs2u128(&bits));
+
+    std::array::from_fn(|idx| (odd >> idx) & 1 == 1)
 }
 
 pub fn spread_odd_u128_from_xor(a: u64, b: u64) -> u128 {
-    lebs2u128(&spread_odd_bits_from_xor(a, b))
+    spread_helper::<false>(a, b)
 }
 pub fn spread_odd_u128_from_three_xor(a: u64, b: u64, c: u64) -> u128 {
-    lebs2u128(&spread_odd_bits_from_three_xor(a, b, c))
+    spread_helper_three::<false>(a, b, c)
 }
 
-// TODO: check unused
 pub fn spread_even_u128_from_xor(a: u64, b: u64) -> u128 {
-    lebs2u128(&spread_even_bits_from_xor(a, b))
+    spread_helper::<true>(a, b)
 }
 
 pub fn spread_odd_bits_from_xor(a: u64, b: u64) -> [bool; 128] {
-    spread_helper::<false>(a, b)
+    u1282lebsp(spread_odd_u128_from_xor(a, b))
 }
 
 pub fn spread_odd_bits_from_three_xor(a: u64, b: u64, c: u64) -> [bool; 128] {
-    spread_helper_three::<false>(a, b, c)
+    u1282lebsp(spread_odd_u128_from_three_xor(a, b, c))
 }
 
 pub fn spread_even_bits_from_xor(a: u64, b: u64) -> [bool; 128] {
-    spread_helper::<true>(a, b)
+    u1282lebsp(spread_even_u128_from_xor(a, b))
 }
 
-// TODO: add test
-fn spread_helper<const GET_SPREAD_EVEN: bool>(a: u64, b: u64) -> [bool; 128] {
-    let a = lebs2u128(&spread_bits::<64, 128>(i2lebsp(a)));
-    let b = lebs2u128(&spread_bits::<64, 128>(i2lebsp(b)));
+// spread(a) + spread(b) has no carry between two bit pairs, its even bits are a ^ b and
+// its odd bits a & b
+fn spread_helper<const GET_SPREAD_EVEN: bool>(a: u64, b: u64) -> u128 {
+    let c = spread_u64(a) + spread_u64(b);
 
-    let c = a + b;
+    if GET_SPREAD_EVEN {
+        spread_u64(even_u128(c))
+    } else {
+        spread_u64(odd_u128(c))
+    }
+}
 
-    let c = u1282lebsp(c);
+// the same with a pair at most 3, the even bits are a ^ b ^ c and the odd bits maj(a, b, c)
+fn spread_helper_three<const GET_SPREAD_EVEN: bool>(a: u64, b: u64, c: u64) -> u128 {
+    let c = spread_u64(a) + spread_u64(b) + spread_u64(c);
 
-    let c:[bool; 64] = if GET_SPREAD_EVEN {
-        even_bits(c)
+    if GET_SPREAD_EVEN {
+        spread_u64(even_u128(c))
     } else {
-        odd_bits(c)
-    };
+        spread_u64(odd_u128(c))
+    }
+}
 
-    spread_bits::<64, 128>(c)
+/// Returns (a ^ b ^ c, maj(a, b, c)), i.e. the even and odd bits of
+/// spread(a) + spread(b) + spread(c).
+pub fn xor_maj(a: u64, b: u64, c: u64) -> (u64, u64) {
+    (a ^ b ^ c, (a & b) | (a & c) | (b & c))
 }
 
-fn spread_helper_three<const GET_SPREAD_EVEN: bool>(a: u64, b: u64, c:u64) -> [bool; 128] {
-    let a = lebs2u128(&spread_bits::<64, 128>(i2lebsp(a)));
-    let b = lebs2u128(&spread_bits::<64, 128>(i2lebsp(b)));
-    let c = lebs2u128(&spread_bits::<64, 128>(i2lebsp(c)));
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let c = a + b + c;
+    // the bit-array versions the integer routines replaced
+    fn spread_bits_ref<const DENSE: usize, const SPREAD: usize>(bits: [bool; DENSE]) -> [bool; SPREAD] {
+        let mut spread = [false; SPREAD];
+        for (idx, bit) in bits.iter().enumerate() {
+            spread[idx * 2] = *bit;
+        }
+        spread
+    }
 
-    let c = u1282lebsp(c);
+    fn even_odd_bits_ref<const LEN: usize, const HALF: usize>(bits: [bool; LEN]) -> ([bool; HALF], [bool; HALF]) {
+        let mut even = [false; HALF];
+        let mut odd = [false; HALF];
+        for idx in 0..HALF {
+            even[idx] = bits[idx * 2];
+            odd[idx] = bits[idx * 2 + 1];
+        }
+        (even, odd)
+    }
 
-    let c:[bool; 64] = if GET_SPREAD_EVEN {
-        even_bits(c)
-    } else {
-        odd_bits(c)
-    };
+    fn spread_helper_ref(words: &[u64], get_spread_even: bool) -> u128 {
+        let sum = words.iter().map(|w| lebs2u128(&spread_bits_ref::<64, 128>(i2lebsp(*w)))).sum::<u128>();
+        let (even, odd): ([bool; 64], [bool; 64]) = even_odd_bits_ref(u1282lebsp(sum));
+
+        lebs2u128(&spread_bits_ref::<64, 128>(if get_spread_even { even } else { odd }))
+    }
+
+    #[test]
+    fn test_spread_u16_exhaustive() {
+        for v in 0..=u16::MAX as u64 {
+            let bits: [bool; 16] = i2lebsp(v);
+            let spread = spread_bits_ref::<16, 32>(bits);
 
-    spread_bits::<64, 128>(c)
+            assert_eq!(spread_bits::<16, 32>(bits), spread);
+            let word = crate::spread_table::SpreadWord::<16, 32>::new(v);
+            assert_eq!((word.dense, word.spread), (bits, spread));
+            assert_eq!(spread_u64(v), lebs2u128(&spread));
+            assert_eq!(even_u128(spread_u64(v)), v);
+            assert_eq!(odd_u128(spread_u64(v) << 1), v);
+
+            // a u32 with the bits of v in the even and the odd positions
+            let mixed = spread_u64(v) | (spread_u64(v ^ 0xffff) << 1);
+            let (even, odd): ([bool; 16], [bool; 16]) = even_odd_bits_ref(u1282lebsp(mixed));
+            assert_eq!(even_bits::<32, 16>(u1282lebsp(mixed)[..32].try_into().unwrap()), even);
+            assert_eq!(odd_bits::<32, 16>(u1282lebsp(mixed)[..32].try_into().unwrap()), odd);
+        }
+    }
+
+    #[test]
+    fn test_spread_helpers_random() {
+        let mut rng = fastrand::Rng::with_seed(44);
+        for _ in 0..10000 {
+            let (a, b, c) = (rng.u64(..), rng.u64(..), rng.u64(..));
+
+            let wide = rng.u128(..);
+            let (even, odd): ([bool; 64], [bool; 64]) = even_odd_bits_ref(u1282lebsp(wide));
+            assert_eq!(even_bits::<128, 64>(u1282lebsp(wide)), even);
+            assert_eq!(odd_bits::<128, 64>(u1282lebsp(wide)), odd);
+            assert_eq!(spread_bits::<64, 128>(i2lebsp(a)), spread_bits_ref::<64, 128>(i2lebsp(a)));
+
+            assert_eq!(spread_odd_u128_from_xor(a, b), spread_helper_ref(&[a, b], false));
+            assert_eq!(spread_even_u128_from_xor(a, b), spread_helper_ref(&[a, b], true));
+            assert_eq!(spread_odd_u128_from_three_xor(a, b, c), spread_helper_ref(&[a, b, c], false));
+            assert_eq!(spread_even_u128_from_xor(a, b), spread_u64(a ^ b));
+            assert_eq!(spread_odd_u128_from_three_xor(a, b, c), spread_u64(xor_maj(a, b, c).1));
+        }
+
+        for (a, b) in [(0, 0), (u64::MAX, u64::MAX), (u64::MAX, 0), (1 << 63, 1 << 63)] {
+            assert_eq!(spread_odd_u128_from_xor(a, b), spread_helper_ref(&[a, b], false));
+            assert_eq!(spread_even_u128_from_xor(a, b), spread_helper_ref(&[a, b], true));
+            assert_eq!(spread_odd_u128_from_three_xor(a, b, a), spread_helper_ref(&[a, b, a], false));
+        }
+    }
 }
@@ -0,0 +1,240 @@
+use halo2_proofs::{
+    circuit::{Region, Value},
+    plonk::{Advice, Column, Error, Selector},
+};
+use halo2curves::bn256::{self, Fr};
+
+use crate::{
+    bits::AssignedBits,
+    sha256::gates::SigmaGate,
+    spread_table::{SpreadInputs, SpreadVar, SpreadWord},
+    util::{i2lebsp, xor_maj},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum AddTerm<'a, const BITS: usize> {
+    Word(&'a AssignedBits<BITS>),
+    Constant(u64),
+}
+
+// The assignments behind the gates of `sha256::gates`, for words of `BITS` bits in `num` and
+// their `BITS / 16` limbs on the rows below in the spread lookup. SHA-256 and BLAKE2s use
+// 32-bit words, SHA-512 64-bit ones.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Words<'a, const BITS: usize> {
+    pub num: Column<Advice>,
+    pub s_decompose: Selector,
+    // the add gate of n terms at n
+    pub s_add: [Option<Selector>; 8],
+    pub lookup: &'a SpreadInputs,
+}
+
+impl<'a, const BITS: usize> Words<'a, BITS> {
+    pub const LIMBS: usize = BITS / 16;
+
+    fn assign_limbs(&self, region: &mut Region<'_, bn256::Fr>, offset: usize, val: Value<u64>) -> Result<(), Error> {
+        for i in 0..Self::LIMBS {
+            let limb = val.map(|v| SpreadWord::<16, 32>::new((v >> (16 * i)) & 0xffff));
+            SpreadVar::with_lookup(region, self.lookup, offset + i, limb)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn assign_word<A: Fn() -> AR, AR: Into<String>>(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        annotation: A,
+        offset: usize,
+        val: Value<u64>,
+    ) -> Result<AssignedBits<BITS>, Error> {
+        self.s_decompose.enable(region, offset)?;
+
+        let word = AssignedBits::<BITS>::assign_bits(region, annotation, self.num, offset, val.map(i2lebsp::<BITS>))?;
+        self.assign_limbs(region, offset, val)?;
+
+        Ok(word)
+    }
+
+    pub fn copy_word(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: usize,
+        word: &AssignedBits<BITS>,
+    ) -> Result<AssignedBits<BITS>, Error> {
+        self.s_decompose.enable(region, offset)?;
+
+        let copied = word.copy_advice(|| "copy word", region, self.num, offset).map(AssignedBits)?;
+        self.assign_limbs(region, offset, word.value_word())?;
+
+        Ok(copied)
+    }
+
+    // xor of the up to three shifts of `x` in `gate`, a single shift is a plain rotation
+    pub fn assign_sigma(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: &mut usize,
+        gate: &SigmaGate,
+        x: &AssignedBits<BITS>,
+    ) -> Result<AssignedBits<BITS>, Error> {
+        let row = *offset;
+        let layout = &gate.layout;
+
+        gate.selector.enable(region, row)?;
+        x.copy_advice(|| "copy sigma input", region, self.num, row)?;
+
+        let x = x.value_word();
+
+        for &(piece_row, piece_offset, len) in layout.pieces.iter() {
+            let piece = x.map(|v| (v >> piece_offset) & ((1 << len) - 1));
+
+            SpreadVar::with_lookup(
+                region,
+                self.lookup,
+                row + piece_row,
+                piece.map(|p| SpreadWord::<16, 32>::new(p)),
+            )?;
+
+            // bound a short piece to `len` bits
+            if len < 16 {
+                SpreadVar::with_lookup(
+                    region,
+                    self.lookup,
+                    row + piece_row + 1,
+                    piece.map(|p| SpreadWord::<16, 32>::new(p << (16 - len))),
+                )?;
+            }
+        }
+
+        let shifted = |v: u64, i: usize| layout.shifts.get(i).map_or(0, |shift| shift.apply(v, BITS));
+        let even_odd = x.map(|v| xor_maj(shifted(v, 0), shifted(v, 1), shifted(v, 2)));
+
+        let out = self.assign_word(region, || "sigma", row + layout.even, even_odd.map(|(even, _)| even))?;
+        self.assign_word(region, || "sigma odd", row + layout.odd, even_odd.map(|(_, odd)| odd))?;
+
+        *offset += layout.rows;
+
+        Ok(out)
+    }
+
+    // see `sha256::gates::ch_gate`
+    pub fn assign_ch(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: &mut usize,
+        selector: Selector,
+        [e, f, g]: [&AssignedBits<BITS>; 3],
+    ) -> Result<AssignedBits<BITS>, Error> {
+        let row = *offset;
+        let limbs = Self::LIMBS;
+
+        selector.enable(region, row)?;
+
+        self.copy_word(region, row, e)?;
+        self.copy_word(region, row + limbs, f)?;
+        self.copy_word(region, row + 2 * limbs, g)?;
+
+        let (e, f, g) = (e.value_word(), f.value_word(), g.value_word());
+        let ef = e.zip(f);
+        let neg = e.zip(g).map(|(e, g)| (!e & mask(BITS), g));
+
+        self.assign_word(region, || "e ^ f", row + 3 * limbs, ef.map(|(e, f)| e ^ f))?;
+        self.assign_word(region, || "e & f", row + 4 * limbs, ef.map(|(e, f)| e & f))?;
+        self.assign_word(region, || "!e ^ g", row + 5 * limbs, neg.map(|(ne, g)| ne ^ g))?;
+        self.assign_word(region, || "!e & g", row + 6 * limbs, neg.map(|(ne, g)| ne & g))?;
+
+        let ch = AssignedBits::<BITS>::assign_bits(
+            region,
+            || "ch",
+            self.num,
+            row + 1,
+            ef.zip(g).map(|((e, f), g)| i2lebsp::<BITS>((e & f) ^ (!e & g))),
+        )?;
+
+        *offset += 7 * limbs;
+
+        Ok(ch)
+    }
+
+    // (a ^ b ^ c, maj(a, b, c)), see `sha256::gates::maj_gate`
+    pub fn assign_xor_maj(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: &mut usize,
+        selector: Selector,
+        [a, b, c]: [&AssignedBits<BITS>; 3],
+    ) -> Result<(AssignedBits<BITS>, AssignedBits<BITS>), Error> {
+        let row = *offset;
+        let limbs = Self::LIMBS;
+
+        selector.enable(region, row)?;
+
+        self.copy_word(region, row, a)?;
+        self.copy_word(region, row + limbs, b)?;
+        self.copy_word(region, row + 2 * limbs, c)?;
+
+        let even_odd = a
+            .value_word()
+            .zip(b.value_word())
+            .zip(c.value_word())
+            .map(|((a, b), c)| xor_maj(a, b, c));
+
+        let xor = self.assign_word(region, || "a ^ b ^ c", row + 3 * limbs, even_odd.map(|(even, _)| even))?;
+        let maj = self.assign_word(region, || "maj", row + 4 * limbs, even_odd.map(|(_, odd)| odd))?;
+
+        *offset += 5 * limbs;
+
+        Ok((xor, maj))
+    }
+
+    // the sum of `terms` mod 2^BITS, see `sha256::gates::add_gate`
+    pub fn assign_add(
+        &self,
+        region: &mut Region<'_, bn256::Fr>,
+        offset: &mut usize,
+        terms: &[AddTerm<BITS>],
+    ) -> Result<AssignedBits<BITS>, Error> {
+        let row = *offset;
+
+        let selector = self.s_add.get(terms.len()).copied().flatten();
+        selector.unwrap_or_else(|| panic!("no addition gate for {} terms", terms.len())).enable(region, row)?;
+
+        let mut sum = Value::known(0u128);
+        for (i, term) in terms.iter().enumerate() {
+            let val = match term {
+                AddTerm::Word(word) => {
+                    word.copy_advice(|| format!("copy term {i}"), region, self.num, row + i)?;
+                    word.value_word()
+                }
+                AddTerm::Constant(c) => {
+                    region.assign_advice_from_constant(
+                        || format!("assign constant term {i}"),
+                        self.num,
+                        row + i,
+                        Fr::from(*c),
+                    )?;
+                    Value::known(*c)
+                }
+            };
+            sum = sum.zip(val).map(|(sum, v)| sum + v as u128);
+        }
+
+        let result = self.assign_word(region, || "sum", row + terms.len(), sum.map(|s| s as u64 & mask(BITS)))?;
+
+        let carry = sum.map(|s| SpreadWord::<16, 32>::new((s >> BITS) as u64));
+        SpreadVar::with_lookup(region, self.lookup, row + terms.len() + Self::LIMBS, carry)?;
+
+        *offset += terms.len() + Self::LIMBS + 1;
+
+        Ok(result)
+    }
+}
+
+fn mask(bits: usize) -> u64 {
+    if bits == 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}